@@ -1,8 +1,8 @@
 use std::collections::HashSet;
 
 use collector_core::{
-    ActionSnapshot, CursorSample, InputEvent, InputEventKind, KeyboardSnapshot, MouseButtons,
-    MouseSnapshot, QpcTimestamp, StepIndex, WindowState,
+    ActionAlignment, ActionSnapshot, CursorSample, InputEvent, InputEventKind, KeyboardSnapshot,
+    MouseButtons, MouseSnapshot, QpcTimestamp, StepIndex, WindowState,
 };
 use compiler::{compile_action_string, empty_action_string, KeyState as CompilerKeyState};
 
@@ -43,6 +43,27 @@ impl AggregatorState {
     }
 }
 
+pub fn alignment_offset_ticks(alignment: ActionAlignment, step_ms: u64, step_ticks: u64) -> i64 {
+    let offset_ms = alignment.offset_ms(step_ms);
+    if step_ms == 0 {
+        return 0;
+    }
+    (offset_ms as i128 * step_ticks as i128 / step_ms as i128) as i64
+}
+
+pub fn aligned_window(
+    frame_ts: QpcTimestamp,
+    step_ticks: u64,
+    offset_ticks: i64,
+) -> (QpcTimestamp, QpcTimestamp) {
+    let window_end = if offset_ticks >= 0 {
+        frame_ts.saturating_add(offset_ticks as u64)
+    } else {
+        frame_ts.saturating_sub(offset_ticks.unsigned_abs())
+    };
+    (window_end.saturating_sub(step_ticks), window_end)
+}
+
 #[derive(Debug)]
 pub struct AggregatedWindow {
     pub snapshot: ActionSnapshot,
@@ -92,6 +113,39 @@ pub fn aggregate_window_with_compiled(
     }
 }
 
+#[allow(clippy::too_many_arguments)]
+pub fn aggregate_frame_windows(
+    events: &[InputEvent],
+    window_start: QpcTimestamp,
+    window_end: QpcTimestamp,
+    frame_index: StepIndex,
+    action_steps_per_frame: u32,
+    is_foreground: bool,
+    cursor_provider: &CursorProvider,
+    state: &mut AggregatorState,
+) -> Vec<AggregatedWindow> {
+    let steps = action_steps_per_frame.max(1) as u64;
+    let duration = window_end.saturating_sub(window_start);
+    let first_step = frame_index.saturating_mul(steps);
+    let mut out = Vec::with_capacity(steps as usize);
+    for sub in 0..steps {
+        let sub_start = window_start.saturating_add(duration * sub / steps);
+        let sub_end = window_start.saturating_add(duration * (sub + 1) / steps);
+        let mut window = aggregate_window_with_compiled(
+            events,
+            sub_start,
+            sub_end,
+            first_step.saturating_add(sub),
+            is_foreground,
+            cursor_provider,
+            state,
+        );
+        window.snapshot.frame_index = frame_index;
+        out.push(window);
+    }
+    out
+}
+
 pub fn aggregate_window(
     events: &[InputEvent],
     window_start: QpcTimestamp,
@@ -106,6 +160,7 @@ pub fn aggregate_window(
         state.down_keys.clear();
         return ActionSnapshot {
             step_index,
+            frame_index: step_index,
             qpc_ts: window_end,
             window: WindowState { is_foreground },
             mouse: MouseSnapshot {
@@ -156,6 +211,7 @@ pub fn aggregate_window(
 
     ActionSnapshot {
         step_index,
+        frame_index: step_index,
         qpc_ts: window_end,
         window: WindowState { is_foreground },
         mouse: MouseSnapshot {
@@ -213,6 +269,47 @@ mod tests {
         assert!(state.down_keys.is_empty());
     }
 
+    #[test]
+    fn aligned_window_follows_alignment_mode() {
+        let step_ticks = 2000;
+        let preceding = alignment_offset_ticks(ActionAlignment::Preceding, 200, step_ticks);
+        let following = alignment_offset_ticks(ActionAlignment::Following, 200, step_ticks);
+        let custom = alignment_offset_ticks(ActionAlignment::OffsetMs(-50), 200, step_ticks);
+        assert_eq!(aligned_window(10_000, step_ticks, preceding), (8_000, 10_000));
+        assert_eq!(aligned_window(10_000, step_ticks, following), (10_000, 12_000));
+        assert_eq!(aligned_window(10_000, step_ticks, custom), (7_500, 9_500));
+        assert_eq!(aligned_window(100, step_ticks, preceding), (0, 100));
+    }
+
+    #[test]
+    fn frame_windows_split_into_action_steps() {
+        let events = vec![
+            InputEvent {
+                qpc_ts: 10,
+                kind: InputEventKind::MouseMove { dx: 3, dy: 0 },
+            },
+            InputEvent {
+                qpc_ts: 250,
+                kind: InputEventKind::MouseMove { dx: 7, dy: 0 },
+            },
+        ];
+        let cursor = CursorProvider {
+            visible: false,
+            x_norm: 0.0,
+            y_norm: 0.0,
+        };
+        let mut state = AggregatorState::new();
+        let windows =
+            aggregate_frame_windows(&events, 0, 400, 2, 2, true, &cursor, &mut state);
+        assert_eq!(windows.len(), 2);
+        assert_eq!(windows[0].snapshot.step_index, 4);
+        assert_eq!(windows[1].snapshot.step_index, 5);
+        assert!(windows.iter().all(|w| w.snapshot.frame_index == 2));
+        assert_eq!(windows[0].snapshot.qpc_ts, 200);
+        assert_eq!(windows[0].snapshot.mouse.dx, 3);
+        assert_eq!(windows[1].snapshot.mouse.dx, 7);
+    }
+
     #[test]
     fn compiled_action_is_generated() {
         let events = vec![InputEvent {
@@ -0,0 +1,359 @@
+use capture::resize::{crop_rect, placement_in, Placement};
+use collector_core::{ResizeMode, SourceCrop};
+
+// The target window's client area in screen coordinates.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ClientRect {
+    pub left: i32,
+    pub top: i32,
+    pub width: u32,
+    pub height: u32,
+}
+
+// A cursor position in every space along the way to the recorded frame.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct CursorPoint {
+    pub client_x: f32,
+    pub client_y: f32,
+    pub src_x: f32,
+    pub src_y: f32,
+    pub record_x: f32,
+    pub record_y: f32,
+    // Clamped to 0..1.
+    pub x_norm: f32,
+    pub y_norm: f32,
+    // False when the cursor is not over the recorded image, e.g. cropped
+    // away by `CenterCrop`, outside the source crop or outside the window.
+    pub inside: bool,
+}
+
+// Maps between screen, client, source (captured pixels), record (output
+// pixels) and normalized coordinates. Client pixels are DPI-unscaled and
+// stretched onto the captured size, then placed the same way
+// `resize_frame_region` places the source crop.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct CursorTransform {
+    client: ClientRect,
+    // Source pixels per client pixel, DPI included.
+    scale_x: f32,
+    scale_y: f32,
+    record_w: u32,
+    record_h: u32,
+    placement: Placement,
+}
+
+impl CursorTransform {
+    // None when any of the sizes is empty.
+    pub fn new(
+        dpi: u32,
+        client: ClientRect,
+        src_size: [u32; 2],
+        record_size: [u32; 2],
+        mode: ResizeMode,
+        crop: Option<&SourceCrop>,
+    ) -> Option<Self> {
+        let [src_w, src_h] = src_size;
+        let [record_w, record_h] = record_size;
+        if client.width == 0 || client.height == 0 || src_w == 0 || src_h == 0 {
+            return None;
+        }
+        if record_w == 0 || record_h == 0 {
+            return None;
+        }
+        let dpi_scale = (dpi as f32 / 96.0).max(0.0001);
+        Some(Self {
+            client,
+            scale_x: src_w as f32 / client.width as f32 / dpi_scale,
+            scale_y: src_h as f32 / client.height as f32 / dpi_scale,
+            record_w,
+            record_h,
+            placement: placement_in(crop_rect(crop, src_w, src_h), record_w, record_h, mode),
+        })
+    }
+
+    pub fn client(&self) -> ClientRect {
+        self.client
+    }
+
+    pub fn placement(&self) -> Placement {
+        self.placement
+    }
+
+    pub fn screen_to_client(&self, x: f32, y: f32) -> (f32, f32) {
+        (x - self.client.left as f32, y - self.client.top as f32)
+    }
+
+    pub fn client_to_screen(&self, x: f32, y: f32) -> (f32, f32) {
+        (x + self.client.left as f32, y + self.client.top as f32)
+    }
+
+    pub fn client_to_source(&self, x: f32, y: f32) -> (f32, f32) {
+        (x * self.scale_x, y * self.scale_y)
+    }
+
+    pub fn source_to_client(&self, x: f32, y: f32) -> (f32, f32) {
+        (x / self.scale_x, y / self.scale_y)
+    }
+
+    pub fn source_to_record(&self, x: f32, y: f32) -> (f32, f32) {
+        self.placement.map(x, y)
+    }
+
+    pub fn record_to_source(&self, x: f32, y: f32) -> (f32, f32) {
+        self.placement.unmap(x, y)
+    }
+
+    pub fn record_to_normalized(&self, x: f32, y: f32) -> (f32, f32) {
+        (x / self.record_w as f32, y / self.record_h as f32)
+    }
+
+    pub fn normalized_to_record(&self, x: f32, y: f32) -> (f32, f32) {
+        (x * self.record_w as f32, y * self.record_h as f32)
+    }
+
+    pub fn map_screen(&self, x: i32, y: i32) -> CursorPoint {
+        let (client_x, client_y) = self.screen_to_client(x as f32, y as f32);
+        self.map_client(client_x, client_y)
+    }
+
+    pub fn map_client(&self, client_x: f32, client_y: f32) -> CursorPoint {
+        let (src_x, src_y) = self.client_to_source(client_x, client_y);
+        let (record_x, record_y) = self.source_to_record(src_x, src_y);
+        let (x_norm, y_norm) = self.record_to_normalized(record_x, record_y);
+        let image = self.placement.dst;
+        let inside = record_x >= image.x as f32
+            && record_y >= image.y as f32
+            && record_x <= (image.x + image.width) as f32
+            && record_y <= (image.y + image.height) as f32;
+        CursorPoint {
+            client_x,
+            client_y,
+            src_x,
+            src_y,
+            record_x,
+            record_y,
+            x_norm: x_norm.clamp(0.0, 1.0),
+            y_norm: y_norm.clamp(0.0, 1.0),
+            inside,
+        }
+    }
+
+    // Where a normalized position came from, in screen coordinates.
+    pub fn normalized_to_screen(&self, x_norm: f32, y_norm: f32) -> (f32, f32) {
+        let (client_x, client_y) = self.normalized_to_client(x_norm, y_norm);
+        self.client_to_screen(client_x, client_y)
+    }
+
+    pub fn normalized_to_client(&self, x_norm: f32, y_norm: f32) -> (f32, f32) {
+        let (record_x, record_y) = self.normalized_to_record(x_norm, y_norm);
+        let (src_x, src_y) = self.record_to_source(record_x, record_y);
+        self.source_to_client(src_x, src_y)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const MODES: [ResizeMode; 3] = [
+        ResizeMode::BLACK_LETTERBOX,
+        ResizeMode::CenterCrop,
+        ResizeMode::Stretch,
+    ];
+
+    const CORNERS_AND_CENTER: [(f32, f32); 5] =
+        [(0.0, 0.0), (1.0, 0.0), (1.0, 1.0), (0.0, 1.0), (0.5, 0.5)];
+
+    fn client(left: i32, top: i32, width: u32, height: u32) -> ClientRect {
+        ClientRect {
+            left,
+            top,
+            width,
+            height,
+        }
+    }
+
+    // A 1920x1200 (16:10) client at (100, 50) and 96 DPI, recorded at 1280x720.
+    fn transform(mode: ResizeMode) -> CursorTransform {
+        CursorTransform::new(96, client(100, 50, 1920, 1200), [1920, 1200], [1280, 720], mode, None)
+            .unwrap()
+    }
+
+    fn round(value: f32) -> f32 {
+        (value * 1000.0).round() / 1000.0
+    }
+
+    fn norm(point: CursorPoint) -> (f32, f32) {
+        (round(point.x_norm), round(point.y_norm))
+    }
+
+    fn close(a: (f32, f32), b: (f32, f32)) -> bool {
+        (a.0 - b.0).abs() < 0.01 && (a.1 - b.1).abs() < 0.01
+    }
+
+    #[test]
+    fn center_maps_to_center_in_every_mode() {
+        for mode in MODES {
+            let point = transform(mode).map_screen(100 + 960, 50 + 600);
+            assert_eq!((point.client_x, point.client_y), (960.0, 600.0));
+            assert_eq!(norm(point), (0.5, 0.5), "{:?}", mode);
+            assert!(point.inside);
+        }
+    }
+
+    #[test]
+    fn client_corners_in_every_mode() {
+        // Client corners and the expected (x_norm, y_norm) per mode.
+        let corners = [(0, 0), (1920, 0), (1920, 1200), (0, 1200)];
+        let letterbox = [(0.05, 0.0), (0.95, 0.0), (0.95, 1.0), (0.05, 1.0)];
+        let stretch = [(0.0, 0.0), (1.0, 0.0), (1.0, 1.0), (0.0, 1.0)];
+        for (index, (x, y)) in corners.into_iter().enumerate() {
+            let point = transform(ResizeMode::BLACK_LETTERBOX).map_screen(100 + x, 50 + y);
+            assert_eq!(norm(point), letterbox[index]);
+            assert!(point.inside);
+
+            let point = transform(ResizeMode::Stretch).map_screen(100 + x, 50 + y);
+            assert_eq!(norm(point), stretch[index]);
+            assert!(point.inside);
+
+            // Center crop cuts 60 rows from the top and bottom of the client.
+            let point = transform(ResizeMode::CenterCrop).map_screen(100 + x, 50 + y);
+            assert_eq!(norm(point), stretch[index]);
+            assert!(!point.inside);
+        }
+        let crop = transform(ResizeMode::CenterCrop);
+        for (index, (x, y)) in [(0, 60), (1920, 60), (1920, 1140), (0, 1140)]
+            .into_iter()
+            .enumerate()
+        {
+            let point = crop.map_screen(100 + x, 50 + y);
+            assert_eq!(norm(point), stretch[index]);
+            assert!(point.inside);
+        }
+    }
+
+    #[test]
+    fn record_image_corners_round_trip_through_screen() {
+        let clients = [
+            client(0, 0, 1280, 720),
+            client(100, 50, 1920, 1200),
+            client(-1920, 200, 3440, 1440),
+            client(40, 30, 800, 600),
+        ];
+        for dpi in [96, 120, 144, 192] {
+            for client in clients {
+                let src = [client.width * dpi / 96, client.height * dpi / 96];
+                for mode in MODES {
+                    let transform =
+                        CursorTransform::new(dpi, client, src, [1280, 720], mode, None).unwrap();
+                    let image = transform.placement().dst;
+                    for (fx, fy) in CORNERS_AND_CENTER {
+                        let target = (
+                            (image.x as f32 + fx * image.width as f32) / 1280.0,
+                            (image.y as f32 + fy * image.height as f32) / 720.0,
+                        );
+                        let (screen_x, screen_y) = transform.normalized_to_screen(target.0, target.1);
+                        let point = transform.map_client(
+                            screen_x - client.left as f32,
+                            screen_y - client.top as f32,
+                        );
+                        assert!(
+                            close((point.x_norm, point.y_norm), target),
+                            "dpi={} client={:?} mode={:?} target={:?} got={:?}",
+                            dpi,
+                            client,
+                            mode,
+                            target,
+                            point
+                        );
+                        assert!(point.inside);
+                    }
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn forward_then_inverse_returns_the_screen_point() {
+        for mode in MODES {
+            let transform = transform(mode);
+            for (x, y) in [(100, 50), (2020, 50), (2020, 1250), (100, 1250), (1060, 650), (500, 700)] {
+                let point = transform.map_screen(x, y);
+                let (record_x, record_y) = (point.record_x, point.record_y);
+                let (norm_x, norm_y) = transform.record_to_normalized(record_x, record_y);
+                let screen = transform.normalized_to_screen(norm_x, norm_y);
+                assert!(close(screen, (x as f32, y as f32)), "{:?} {:?}", mode, screen);
+            }
+        }
+    }
+
+    #[test]
+    fn dpi_scaling_divides_client_coordinates() {
+        // 150% DPI: a 1280x800 client captured at 1920x1200.
+        let transform = CursorTransform::new(
+            144,
+            client(0, 0, 1280, 800),
+            [1920, 1200],
+            [1280, 720],
+            ResizeMode::Stretch,
+            None,
+        )
+        .unwrap();
+        let point = transform.map_client(960.0, 600.0);
+        assert_eq!((point.src_x, point.src_y), (960.0, 600.0));
+        assert_eq!(norm(point), (0.5, 0.5));
+        assert_eq!(transform.normalized_to_client(0.5, 0.5), (960.0, 600.0));
+    }
+
+    #[test]
+    fn outside_the_window_is_clamped_and_not_inside() {
+        let point = transform(ResizeMode::BLACK_LETTERBOX).map_screen(-100, 1400);
+        assert_eq!((point.x_norm, point.y_norm), (0.0, 1.0));
+        assert!(!point.inside);
+    }
+
+    #[test]
+    fn source_crop_fills_the_record_and_hides_the_rest() {
+        // A 1280x720 play area at (320, 180) of a 1920x1080 client.
+        let crops = [
+            SourceCrop::Client([320, 180, 1280, 720]),
+            SourceCrop::Normalized([1.0 / 6.0, 1.0 / 6.0, 2.0 / 3.0, 2.0 / 3.0]),
+        ];
+        for crop in crops {
+            for mode in MODES {
+                let transform = CursorTransform::new(
+                    96,
+                    client(0, 0, 1920, 1080),
+                    [1920, 1080],
+                    [1280, 720],
+                    mode,
+                    Some(&crop),
+                )
+                .unwrap();
+                let point = transform.map_client(320.0, 180.0);
+                assert_eq!(norm(point), (0.0, 0.0), "{:?}", mode);
+                assert!(point.inside);
+                let point = transform.map_client(960.0, 540.0);
+                assert_eq!(norm(point), (0.5, 0.5));
+                assert!(point.inside);
+                let point = transform.map_client(1600.0, 900.0);
+                assert_eq!(norm(point), (1.0, 1.0));
+                assert!(point.inside);
+                // Over the surrounding UI: clamped and not visible.
+                let point = transform.map_client(100.0, 540.0);
+                assert_eq!(norm(point), (0.0, 0.5));
+                assert!(!point.inside);
+                assert!(!transform.map_client(960.0, 1000.0).inside);
+                assert!(close(transform.normalized_to_client(0.25, 0.75), (640.0, 720.0)));
+            }
+        }
+    }
+
+    #[test]
+    fn empty_sizes_have_no_transform() {
+        let mode = ResizeMode::Stretch;
+        assert!(CursorTransform::new(96, client(0, 0, 0, 720), [1280, 720], [1280, 720], mode, None).is_none());
+        assert!(CursorTransform::new(96, client(0, 0, 1280, 720), [1280, 0], [1280, 720], mode, None).is_none());
+        assert!(CursorTransform::new(96, client(0, 0, 1280, 720), [1280, 720], [0, 720], mode, None).is_none());
+    }
+}
@@ -0,0 +1,213 @@
+use collector_core::{ActionSnapshot, IdleOptions, StepIndex};
+
+// Every Nth pixel is enough to tell a static screen from a moving one.
+const SAMPLE_STRIDE: usize = 97;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum IdleTransition {
+    Pause { since_step: StepIndex },
+    Resume { paused_step: StepIndex },
+}
+
+pub struct IdlePolicy {
+    idle_frames: u64,
+    static_threshold: f32,
+    idle_run: u64,
+    idle_since: StepIndex,
+    paused_at: Option<StepIndex>,
+}
+
+impl IdlePolicy {
+    pub fn new(options: &IdleOptions, frame_ms: f64) -> Self {
+        let idle_frames = (options.idle_secs as f64 * 1000.0 / frame_ms.max(1.0)).ceil() as u64;
+        Self {
+            idle_frames: idle_frames.max(1),
+            static_threshold: options.static_threshold,
+            idle_run: 0,
+            idle_since: 0,
+            paused_at: None,
+        }
+    }
+
+    pub fn is_paused(&self) -> bool {
+        self.paused_at.is_some()
+    }
+
+    // `snapshots` are the action steps of one frame and `frame_diff` the
+    // difference to the previous frame. Input always resumes; pausing needs
+    // `idle_frames` consecutive frames without input on a near-static screen.
+    pub fn observe(
+        &mut self,
+        snapshots: &[ActionSnapshot],
+        frame_diff: f32,
+    ) -> Option<IdleTransition> {
+        let step = snapshots.first()?.step_index;
+        let has_input = snapshots.iter().any(snapshot_has_input);
+        if has_input {
+            self.idle_run = 0;
+            return self
+                .paused_at
+                .take()
+                .map(|paused_step| IdleTransition::Resume { paused_step });
+        }
+        if self.paused_at.is_some() {
+            return None;
+        }
+        if frame_diff > self.static_threshold {
+            self.idle_run = 0;
+            return None;
+        }
+        if self.idle_run == 0 {
+            self.idle_since = step;
+        }
+        self.idle_run += 1;
+        if self.idle_run < self.idle_frames {
+            return None;
+        }
+        self.paused_at = Some(step);
+        Some(IdleTransition::Pause {
+            since_step: self.idle_since,
+        })
+    }
+}
+
+pub fn snapshot_has_input(snapshot: &ActionSnapshot) -> bool {
+    let mouse = &snapshot.mouse;
+    let buttons = &mouse.buttons;
+    !snapshot.keyboard.down.is_empty()
+        || !snapshot.keyboard.pressed.is_empty()
+        || !snapshot.keyboard.released.is_empty()
+        || mouse.dx != 0
+        || mouse.dy != 0
+        || mouse.wheel != 0
+        || buttons.left
+        || buttons.right
+        || buttons.middle
+        || buttons.x1
+        || buttons.x2
+}
+
+#[derive(Default)]
+pub struct FrameDiffMeter {
+    prev: Option<Vec<u8>>,
+}
+
+impl FrameDiffMeter {
+    // Mean absolute difference of sampled green values between consecutive
+    // BGRA frames; the first frame reports 0.
+    pub fn measure(&mut self, frame: &[u8]) -> f32 {
+        let sample: Vec<u8> = frame
+            .chunks_exact(4)
+            .step_by(SAMPLE_STRIDE)
+            .map(|pixel| pixel[1])
+            .collect();
+        let diff = match self.prev.as_ref() {
+            Some(prev) if prev.len() == sample.len() && !sample.is_empty() => {
+                let total: u64 = prev
+                    .iter()
+                    .zip(sample.iter())
+                    .map(|(a, b)| a.abs_diff(*b) as u64)
+                    .sum();
+                total as f32 / sample.len() as f32
+            }
+            Some(_) => f32::MAX,
+            None => 0.0,
+        };
+        self.prev = Some(sample);
+        diff
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use collector_core::{
+        CursorSample, KeyboardSnapshot, MouseButtons, MouseSnapshot, WindowState,
+    };
+
+    fn snapshot(step: u64, dx: i32) -> ActionSnapshot {
+        ActionSnapshot {
+            step_index: step,
+            frame_index: step,
+            qpc_ts: step * 100,
+            window: WindowState {
+                is_foreground: true,
+            },
+            mouse: MouseSnapshot {
+                dx,
+                dy: 0,
+                wheel: 0,
+                buttons: MouseButtons::default(),
+                cursor: CursorSample {
+                    visible: false,
+                    x_norm: 0.0,
+                    y_norm: 0.0,
+                },
+            },
+            keyboard: KeyboardSnapshot::default(),
+        }
+    }
+
+    fn policy() -> IdlePolicy {
+        let options = IdleOptions {
+            enabled: true,
+            idle_secs: 1.0,
+            static_threshold: 2.0,
+        };
+        IdlePolicy::new(&options, 250.0)
+    }
+
+    #[test]
+    fn pauses_after_idle_static_frames_and_resumes_on_input() {
+        let mut policy = policy();
+        let mut transitions = Vec::new();
+        for step in 0..10 {
+            let dx = if step == 8 { 5 } else { 0 };
+            if let Some(transition) = policy.observe(&[snapshot(step, dx)], 0.5) {
+                transitions.push((step, transition));
+            }
+            assert_eq!(policy.is_paused(), (3..8).contains(&step));
+        }
+        assert_eq!(
+            transitions,
+            vec![
+                (3, IdleTransition::Pause { since_step: 0 }),
+                (8, IdleTransition::Resume { paused_step: 3 }),
+            ]
+        );
+    }
+
+    #[test]
+    fn moving_frames_keep_recording() {
+        let mut policy = policy();
+        for step in 0..10 {
+            let diff = if step % 3 == 2 { 9.0 } else { 0.0 };
+            assert!(policy.observe(&[snapshot(step, 0)], diff).is_none());
+        }
+        assert!(!policy.is_paused());
+    }
+
+    #[test]
+    fn any_action_step_counts_as_input() {
+        let mut policy = policy();
+        for step in 0..4 {
+            policy.observe(&[snapshot(step * 2, 0), snapshot(step * 2 + 1, 0)], 0.0);
+        }
+        assert!(policy.is_paused());
+        let resumed = policy.observe(&[snapshot(8, 0), snapshot(9, 1)], 0.0);
+        assert_eq!(resumed, Some(IdleTransition::Resume { paused_step: 6 }));
+    }
+
+    #[test]
+    fn frame_diff_meter_compares_consecutive_frames() {
+        let mut meter = FrameDiffMeter::default();
+        let dark = vec![0u8; 400 * 4];
+        let mut bright = dark.clone();
+        for pixel in bright.chunks_exact_mut(4) {
+            pixel[1] = 40;
+        }
+        assert_eq!(meter.measure(&dark), 0.0);
+        assert_eq!(meter.measure(&dark), 0.0);
+        assert_eq!(meter.measure(&bright), 40.0);
+    }
+}
@@ -1 +1,5 @@
+pub mod cursor;
+pub mod idle;
 pub mod pipeline;
+pub mod recover;
+pub mod replay;
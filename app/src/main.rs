@@ -3,10 +3,18 @@ use std::fs::File;
 use std::io::{self, BufRead, BufReader};
 use std::path::{Path, PathBuf};
 
-use aggregator::CursorProvider;
+use aggregator::{aligned_window, alignment_offset_ticks, CursorProvider};
 use capture::WgcCapture;
-use collector_core::{BuildInfo, InputEvent, Meta, Options, RECORD_HEIGHT, RECORD_WIDTH};
+use collector_core::{
+    ActionAlignment, BuildInfo, CursorStyle, EncodeOptions, EncodeQueueOptions, FramePacing, FrameRecord,
+    ImageFormat, InputEvent, MaskRegion, MaskStyle, Meta, Options, PixelFormat, Rendition, ResizeFilter, ResizeMode, SegmentOptions, SourceCrop, VideoCodec,
+    VideoOutputOptions, VideoSinkKind, RECORD_HEIGHT, RECORD_WIDTH,
+};
 use app::pipeline::{ensure_dataset_root, PipelineConfig, SessionPipeline};
+use app::recover::recover_session;
+use app::replay::replay_rules;
+use events::{resolve_config_path, RuleSet};
+use writer::{scan_orphaned_sessions, SessionLayout};
 
 fn main() {
     if let Err(err) = run() {
@@ -16,28 +24,79 @@ fn main() {
 }
 
 fn run() -> io::Result<()> {
-    let args = parse_args().map_err(|msg| io::Error::new(io::ErrorKind::Other, msg))?;
+    let args = parse_args().map_err(io::Error::other)?;
     #[cfg(not(windows))]
     let _ = args.cursor_debug;
     ensure_dataset_root(&args.dataset_root)?;
+    for path in scan_orphaned_sessions(&args.dataset_root)? {
+        eprintln!(
+            "WARN orphaned unfinished session: {} (repair with --recover)",
+            path.display()
+        );
+    }
 
-    let options = build_options();
-    let config = PipelineConfig {
-        dataset_root: args.dataset_root.clone(),
-        session_name: args.session_name.clone(),
-        ffmpeg_path: args.ffmpeg_path.clone(),
-        record_width: options.capture.record_resolution[0],
-        record_height: options.capture.record_resolution[1],
-        fps: options.capture.fps,
-    };
+    if args.recover {
+        let report = recover_session(&args.dataset_root, &args.session_name, &args.ffmpeg_path)?;
+        println!(
+            "recovered {} steps into {} ({} bytes dropped, video {})",
+            report.steps,
+            report.session_dir.display(),
+            report.dropped_bytes,
+            if report.video_remuxed { "remuxed" } else { "missing" }
+        );
+        return Ok(());
+    }
 
-    let pipeline = SessionPipeline::create(config)?;
+    if let Some(path) = args.replay_rules.as_ref() {
+        let layout = SessionLayout::new(&args.dataset_root, &args.session_name);
+        let rules = RuleSet::load(&resolve_config_path(&args.dataset_root, path))?;
+        let count = replay_rules(&layout, rules)?;
+        let target = if layout.chunk_index_path.exists() {
+            &layout.root_dir
+        } else {
+            &layout.auto_events_path
+        };
+        println!("{} rule events written to {}", count, target.display());
+        return Ok(());
+    }
+
+    let mut options = build_options(args.alignment, args.action_steps_per_frame);
+    if let Some(video) = args.video.as_ref() {
+        options.video = video.clone();
+    }
+    options.video.encode = args.encode.clone();
+    options.video.queue = args.queue;
+    options.video.renditions = args.renditions.clone();
+    if let Some(mode) = args.resize_mode {
+        options.capture.resize_mode = mode;
+    }
+    options.capture.resize_filter = args.resize_filter;
+    if let Some(crop) = args.source_crop {
+        options.capture.source_crop = Some(crop);
+    }
+    if let Some(style) = args.cursor_style.clone() {
+        options.capture.cursor_style = style;
+    }
+    options.capture.include_cursor_in_video = args.cursor_in_video;
+    options.privacy.masks = args.masks.clone();
+    options.segment = args.segment;
+    options.auto_events.rules = args.rules.clone();
     let meta = build_meta(&args.session_name, options.capture.fps);
+    let config = PipelineConfig::from_options(
+        &options,
+        &meta,
+        &args.dataset_root,
+        &args.session_name,
+        &args.ffmpeg_path,
+    );
+
+    let pipeline = SessionPipeline::create(config)?;
     pipeline.write_options_meta(&options, &meta)?;
     let step_ms = options.timing.step_ms;
 
     let layout = if let Some(hwnd) = args.target_hwnd {
-        let capture = WgcCapture::new(options.capture.clone(), hwnd)?;
+        let mut capture = WgcCapture::new(options.capture.clone(), hwnd)?;
+        capture.keep_source_frames(!options.video.renditions.is_empty());
         let input = input::RawInputCollector::new()?;
         let _cursor = CursorProvider {
             visible: false,
@@ -61,10 +120,7 @@ fn run() -> io::Result<()> {
             let _ = capture;
             let _ = input;
             let _ = pipeline;
-            return Err(io::Error::new(
-                io::ErrorKind::Other,
-                "--target-hwnd requires Windows",
-            ));
+            return Err(io::Error::other("--target-hwnd requires Windows"));
         }
     } else {
         let events = if let Some(path) = args.events_jsonl.as_ref() {
@@ -77,7 +133,17 @@ fn run() -> io::Result<()> {
         } else {
             Vec::new()
         };
-        let frame = load_frame(args.frame_raw.as_ref())?;
+        let mut frame = FrameRecord {
+            step_index: 0,
+            qpc_ts: 0,
+            src_width: RECORD_WIDTH,
+            src_height: RECORD_HEIGHT,
+            width: RECORD_WIDTH,
+            height: RECORD_HEIGHT,
+            data: load_frame(args.frame_raw.as_ref())?,
+            pacing: FramePacing::default(),
+            source: None,
+        };
 
         let cursor = CursorProvider {
             visible: false,
@@ -86,10 +152,15 @@ fn run() -> io::Result<()> {
         };
 
         let mut pipeline = pipeline;
+        let offset_ticks = alignment_offset_ticks(pipeline.alignment(), step_ms, step_ms);
         let mut event_index = 0usize;
         for step in 0..args.steps {
-            let window_start = step.saturating_mul(step_ms);
-            let window_end = window_start.saturating_add(step_ms);
+            let frame_ts = step.saturating_add(1).saturating_mul(step_ms);
+            let (window_start, window_end) = aligned_window(frame_ts, step_ms, offset_ticks);
+            frame.step_index = step;
+            frame.qpc_ts = frame_ts;
+            // The dry run repeats one raw image for every step.
+            frame.pacing.duplicated = step > 0;
 
             while event_index < events.len() && events[event_index].qpc_ts < window_start {
                 event_index += 1;
@@ -130,6 +201,22 @@ struct Args {
     thoughts_jsonl: Option<PathBuf>,
     target_hwnd: Option<isize>,
     cursor_debug: bool,
+    alignment: ActionAlignment,
+    action_steps_per_frame: u32,
+    rules: Option<String>,
+    replay_rules: Option<String>,
+    recover: bool,
+    video: Option<VideoOutputOptions>,
+    encode: EncodeOptions,
+    queue: EncodeQueueOptions,
+    segment: SegmentOptions,
+    renditions: Vec<Rendition>,
+    resize_mode: Option<ResizeMode>,
+    resize_filter: ResizeFilter,
+    source_crop: Option<SourceCrop>,
+    cursor_style: Option<CursorStyle>,
+    cursor_in_video: bool,
+    masks: Vec<MaskRegion>,
 }
 
 fn parse_args() -> Result<Args, String> {
@@ -142,6 +229,24 @@ fn parse_args() -> Result<Args, String> {
     let mut thoughts_jsonl: Option<PathBuf> = None;
     let mut target_hwnd: Option<isize> = None;
     let mut cursor_debug = false;
+    let mut alignment = ActionAlignment::Preceding;
+    let mut action_steps_per_frame = 1u32;
+    let mut rules: Option<String> = None;
+    let mut replay_rules: Option<String> = None;
+    let mut recover = false;
+    let mut video: Option<VideoOutputOptions> = None;
+    let mut encode = EncodeOptions::default();
+    let mut queue = EncodeQueueOptions::default();
+    let mut segment = SegmentOptions::default();
+    let mut renditions: Vec<Rendition> = Vec::new();
+    let mut resize_mode: Option<ResizeMode> = None;
+    let mut resize_filter = ResizeFilter::default();
+    let mut source_crop: Option<SourceCrop> = None;
+    let mut cursor_style: Option<CursorStyle> = None;
+    let mut cursor_in_video = true;
+    let mut masks: Vec<MaskRegion> = Vec::new();
+    let mut rate_set = false;
+    let mut pix_fmt_set = false;
 
     let mut iter = env::args().skip(1);
     while let Some(arg) = iter.next() {
@@ -177,6 +282,127 @@ fn parse_args() -> Result<Args, String> {
             "--cursor-debug" => {
                 cursor_debug = true;
             }
+            "--alignment" => {
+                let value = next_string(&mut iter, &arg)?;
+                alignment = parse_alignment(&value)?;
+            }
+            "--action-steps-per-frame" => {
+                let value = next_string(&mut iter, &arg)?;
+                action_steps_per_frame = value
+                    .parse::<u32>()
+                    .ok()
+                    .filter(|steps| *steps > 0)
+                    .ok_or_else(|| format!("invalid --action-steps-per-frame value: {}", value))?;
+            }
+            "--rules" => {
+                rules = Some(next_string(&mut iter, &arg)?);
+            }
+            "--replay-rules" => {
+                replay_rules = Some(next_string(&mut iter, &arg)?);
+            }
+            "--video-sink" => {
+                let value = next_string(&mut iter, &arg)?;
+                video = Some(parse_video_sink(&value)?);
+            }
+            "--codec" => {
+                let value = next_string(&mut iter, &arg)?;
+                encode.codec = parse_enum_arg(&arg, &value)?;
+            }
+            "--preset" => {
+                encode.preset = Some(next_string(&mut iter, &arg)?);
+            }
+            "--tune" => {
+                encode.tune = Some(next_string(&mut iter, &arg)?);
+            }
+            "--crf" => {
+                let value = next_string(&mut iter, &arg)?;
+                encode.crf = Some(value.parse::<u32>().map_err(|_| {
+                    format!("invalid --crf value: {}", value)
+                })?);
+                encode.bitrate_kbps = None;
+                rate_set = true;
+            }
+            "--bitrate-kbps" => {
+                let value = next_string(&mut iter, &arg)?;
+                encode.bitrate_kbps = Some(value.parse::<u32>().map_err(|_| {
+                    format!("invalid --bitrate-kbps value: {}", value)
+                })?);
+                encode.crf = None;
+                rate_set = true;
+            }
+            "--gop" => {
+                let value = next_string(&mut iter, &arg)?;
+                encode.gop = value
+                    .parse::<u32>()
+                    .map_err(|_| format!("invalid --gop value: {}", value))?;
+            }
+            "--pix-fmt" => {
+                let value = next_string(&mut iter, &arg)?;
+                encode.pixel_format = parse_enum_arg(&arg, &value)?;
+                pix_fmt_set = true;
+            }
+            "--queue-capacity" => {
+                let value = next_string(&mut iter, &arg)?;
+                queue.capacity = value
+                    .parse::<u32>()
+                    .ok()
+                    .filter(|capacity| *capacity > 0)
+                    .ok_or_else(|| format!("invalid --queue-capacity value: {}", value))?;
+            }
+            "--queue-policy" => {
+                let value = next_string(&mut iter, &arg)?;
+                queue.full_policy = parse_enum_arg(&arg, &value)?;
+            }
+            "--segment-frames" => {
+                let value = next_string(&mut iter, &arg)?;
+                segment.every_frames = value.parse::<u64>().map_err(|_| {
+                    format!("invalid --segment-frames value: {}", value)
+                })?;
+            }
+            "--segment-minutes" => {
+                let value = next_string(&mut iter, &arg)?;
+                segment.every_minutes = value.parse::<u32>().map_err(|_| {
+                    format!("invalid --segment-minutes value: {}", value)
+                })?;
+            }
+            "--resize-mode" => {
+                let value = next_string(&mut iter, &arg)?;
+                resize_mode = Some(parse_enum_arg(&arg, &value)?);
+            }
+            "--resize-filter" => {
+                let value = next_string(&mut iter, &arg)?;
+                resize_filter = parse_enum_arg(&arg, &value)?;
+            }
+            "--source-crop" => {
+                let value = next_string(&mut iter, &arg)?;
+                source_crop = Some(parse_source_crop(&value)?);
+            }
+            "--cursor-style" => {
+                let value = next_string(&mut iter, &arg)?;
+                cursor_style = Some(match value.as_str() {
+                    "arrow" => CursorStyle::Arrow,
+                    "crosshair" => CursorStyle::Crosshair,
+                    _ if value.ends_with(".png") => CursorStyle::Png {
+                        path: value,
+                        hotspot: [0, 0],
+                    },
+                    _ => return Err(format!("invalid --cursor-style value: {}", value)),
+                });
+            }
+            "--no-cursor-in-video" => {
+                cursor_in_video = false;
+            }
+            "--rendition" => {
+                let value = next_string(&mut iter, &arg)?;
+                renditions.push(parse_rendition(&value)?);
+            }
+            "--mask" => {
+                let value = next_string(&mut iter, &arg)?;
+                masks.push(parse_mask(&value)?);
+            }
+            "--recover" => {
+                recover = true;
+            }
             "--help" | "-h" => {
                 return Err(usage());
             }
@@ -190,7 +416,20 @@ fn parse_args() -> Result<Args, String> {
     let session_name = session_name.ok_or_else(|| "missing --session-name".to_string())?;
     let ffmpeg_path = ffmpeg_path.unwrap_or_else(|| PathBuf::from("ffmpeg"));
     let steps = steps.unwrap_or(0);
-    if target_hwnd.is_none() && steps == 0 {
+    // Codec-specific defaults for whatever was not given explicitly.
+    match encode.codec {
+        VideoCodec::Ffv1 if !rate_set => encode.crf = None,
+        VideoCodec::Mjpeg => {
+            if !rate_set {
+                encode.crf = Some(3);
+            }
+            if !pix_fmt_set {
+                encode.pixel_format = PixelFormat::Yuvj420p;
+            }
+        }
+        _ => {}
+    }
+    if target_hwnd.is_none() && replay_rules.is_none() && !recover && steps == 0 {
         return Err("missing --steps (required for dry-run mode)".to_string());
     }
 
@@ -204,6 +443,22 @@ fn parse_args() -> Result<Args, String> {
         thoughts_jsonl,
         target_hwnd,
         cursor_debug,
+        alignment,
+        action_steps_per_frame,
+        rules,
+        replay_rules,
+        recover,
+        video,
+        encode,
+        queue,
+        segment,
+        renditions,
+        resize_mode,
+        resize_filter,
+        source_crop,
+        cursor_style,
+        cursor_in_video,
+        masks,
     })
 }
 
@@ -219,6 +474,47 @@ Options:
   --thoughts-jsonl <path> Thoughts JSONL (one line per step)
   --target-hwnd <hex>     Capture target HWND (enables WGC capture)
   --cursor-debug          Log cursor mapping diagnostics (realtime mode)
+  --alignment <mode>      Action/frame alignment: preceding, following or an
+                          offset in ms (default: preceding)
+  --action-steps-per-frame <n>
+                          Action labels written per video frame (default: 1)
+  --rules <path>          Rule file (JSON or TOML) evaluated while recording
+  --replay-rules <path>   Re-run a rule file over the existing session
+                          --session-name and rewrite its auto_events.jsonl
+  --video-sink <kind>     Video output: ffmpeg, png, jpeg, webp or raw
+                          (default: ffmpeg)
+  --codec <name>          Encoder: x264, x265, av1, ffv1 or mjpeg
+                          (default: x264)
+  --preset <name>         Encoder preset (x264/x265 names, 0-13 for av1)
+  --tune <name>           Encoder tune (x264/x265 only)
+  --crf <n>               Constant quality (default: 16; -q:v for mjpeg)
+  --bitrate-kbps <n>      Target bitrate instead of --crf
+  --gop <n>               Keyframe interval in frames (default: 10)
+  --pix-fmt <name>        Output pixel format (default: yuv420p)
+  --queue-capacity <n>    Frames buffered for the encoder thread (default: 8)
+  --queue-policy <policy> When the queue is full: block, drop_oldest or error
+                          (default: block)
+  --segment-frames <n>    Start a new chunk_NNNN directory every n frames
+  --segment-minutes <n>   Start a new chunk_NNNN directory every n minutes
+  --resize-mode <mode>    Aspect handling: letterbox, center_crop or stretch
+                          (default: letterbox)
+  --resize-filter <name>  Scaling filter: nearest, bilinear or area
+                          (default: area)
+  --source-crop <x>,<y>,<w>,<h>
+                          Record only this part of the window, in client
+                          pixels or, with decimals, fractions (0.1,0,0.8,1)
+  --cursor-style <style>  Cursor drawn into the video: arrow, crosshair or a
+                          .png path (hotspot at its top-left; default: arrow)
+  --no-cursor-in-video    Do not draw the cursor into the video
+  --rendition <name>=<W>x<H>
+                          Also encode video_<name> at this size; repeat for
+                          several renditions (e.g. 720p=1280x720)
+  --mask <x>,<y>,<w>,<h>[:<style>]
+                          Hide a record-space rectangle in the video with
+                          fill (black), blur or pixelate (default: fill);
+                          repeat for several masks
+  --recover               Repair the interrupted session --session-name
+                          (truncate partial lines, remux video, finalize)
   --help                  Show this help
 "#;
     text.to_string()
@@ -252,6 +548,17 @@ fn parse_hwnd(value: &str) -> Result<isize, String> {
     }
 }
 
+fn parse_alignment(value: &str) -> Result<ActionAlignment, String> {
+    match value {
+        "preceding" => Ok(ActionAlignment::Preceding),
+        "following" => Ok(ActionAlignment::Following),
+        _ => value
+            .parse::<i64>()
+            .map(ActionAlignment::OffsetMs)
+            .map_err(|_| format!("invalid --alignment value: {}", value)),
+    }
+}
+
 fn load_events(path: &Path) -> io::Result<Vec<InputEvent>> {
     let file = File::open(path)?;
     let reader = BufReader::new(file);
@@ -296,9 +603,102 @@ fn load_frame(path: Option<&PathBuf>) -> io::Result<Vec<u8>> {
     Ok(data)
 }
 
-fn build_options() -> Options {
+fn parse_enum_arg<T: serde::de::DeserializeOwned>(arg: &str, value: &str) -> Result<T, String> {
+    serde_json::from_value(serde_json::Value::String(value.to_string()))
+        .map_err(|_| format!("invalid {} value: {}", arg, value))
+}
+
+fn parse_video_sink(value: &str) -> Result<VideoOutputOptions, String> {
+    let mut video = VideoOutputOptions::default();
+    match value {
+        "ffmpeg" => {}
+        "png" | "jpeg" | "webp" => {
+            video.sink = VideoSinkKind::ImageSequence;
+            video.image_format = match value {
+                "png" => ImageFormat::Png,
+                "jpeg" => ImageFormat::Jpeg,
+                _ => ImageFormat::Webp,
+            };
+        }
+        "raw" => video.sink = VideoSinkKind::RawBgra,
+        _ => return Err(format!("invalid --video-sink value: {}", value)),
+    }
+    Ok(video)
+}
+
+fn parse_rendition(value: &str) -> Result<Rendition, String> {
+    let invalid = || format!("invalid --rendition value: {}", value);
+    let (name, size) = value.split_once('=').ok_or_else(invalid)?;
+    let (width, height) = size.split_once('x').ok_or_else(invalid)?;
+    let width = width.parse::<u32>().map_err(|_| invalid())?;
+    let height = height.parse::<u32>().map_err(|_| invalid())?;
+    if name.is_empty() {
+        return Err(invalid());
+    }
+    Ok(Rendition {
+        name: name.to_string(),
+        resolution: [width, height],
+        encode: None,
+    })
+}
+
+fn parse_source_crop(value: &str) -> Result<SourceCrop, String> {
+    let invalid = || format!("invalid --source-crop value: {}", value);
+    let parts: Vec<&str> = value.split(',').map(str::trim).collect();
+    if parts.len() != 4 {
+        return Err(invalid());
+    }
+    if value.contains('.') {
+        let mut rect = [0f32; 4];
+        for (slot, part) in rect.iter_mut().zip(&parts) {
+            *slot = part.parse().map_err(|_| invalid())?;
+        }
+        let [x, y, width, height] = rect;
+        if rect.iter().any(|value| !(0.0..=1.0).contains(value))
+            || width == 0.0
+            || height == 0.0
+            || x + width > 1.0
+            || y + height > 1.0
+        {
+            return Err(invalid());
+        }
+        Ok(SourceCrop::Normalized(rect))
+    } else {
+        let mut rect = [0u32; 4];
+        for (slot, part) in rect.iter_mut().zip(&parts) {
+            *slot = part.parse().map_err(|_| invalid())?;
+        }
+        if rect[2] == 0 || rect[3] == 0 {
+            return Err(invalid());
+        }
+        Ok(SourceCrop::Client(rect))
+    }
+}
+
+fn parse_mask(value: &str) -> Result<MaskRegion, String> {
+    let invalid = || format!("invalid --mask value: {}", value);
+    let (rect, style) = value.split_once(':').unwrap_or((value, "fill"));
+    let rect: Vec<u32> = rect
+        .split(',')
+        .map(|part| part.trim().parse::<u32>())
+        .collect::<Result<_, _>>()
+        .map_err(|_| invalid())?;
+    let rect: [u32; 4] = rect.try_into().map_err(|_| invalid())?;
+    // Through serde, so each style takes its configured defaults.
+    let style: MaskStyle = serde_json::from_value(serde_json::json!({ "style": style }))
+        .map_err(|_| invalid())?;
+    Ok(MaskRegion {
+        rect,
+        style,
+        game: None,
+    })
+}
+
+fn build_options(alignment: ActionAlignment, action_steps_per_frame: u32) -> Options {
     let mut options = Options::default_v1();
     options.capture.target.method = "cli".to_string();
+    options.timing.alignment = alignment;
+    options.timing.action_steps_per_frame = action_steps_per_frame;
     options
 }
 
@@ -316,5 +716,7 @@ fn build_meta(session_id: &str, record_fps: u32) -> Meta {
             git_commit: "unknown".to_string(),
         },
         notes: "".to_string(),
+        encoder_command: None,
+        renditions: Vec::new(),
     }
 }
@@ -1,18 +1,34 @@
+use std::collections::VecDeque;
 use std::io;
 use std::path::{Path, PathBuf};
 use std::time::Duration;
 
-use aggregator::{aggregate_window_with_compiled, AggregatorState, CursorProvider};
+use aggregator::{
+    aggregate_frame_windows, aligned_window, alignment_offset_ticks, AggregatorState,
+    CursorProvider,
+};
+use capture::mask::{apply_mask, map_mask_rect};
+use capture::overlay::CursorSprite;
+use capture::resize::{crop_rect, placement_in, resize_frame_region};
 use capture::FrameSource;
-use collector_core::{InputEvent, Meta, Options, QpcTimestamp, StepIndex};
+use collector_core::{
+    action_step_ms, ActionAlignment, AutoEventsOptions, CursorStyle, FrameRecord, FrameTiming, IdleOptions, InputEvent,
+    MarkerOptions, MaskRegion, MaskStyle, Meta, Options, QpcTimestamp, ResizeFilter, ResizeMode, SegmentOptions, SourceCrop,
+    StepIndex, VideoOutputOptions,
+};
+use events::{
+    resolve_config_path, AutoEvent, AutoEventEngine, AutoEventKind, MarkerMap, PauseReason,
+    RuleEngine, RuleSet,
+};
 
-#[cfg(windows)]
-use collector_core::FrameRecord;
+use crate::idle::{FrameDiffMeter, IdlePolicy, IdleTransition};
 
+#[cfg(windows)]
+use crate::cursor::{ClientRect, CursorPoint, CursorTransform};
 #[cfg(windows)]
 use collector_core::InputEventKind;
 use input::InputCollector;
-use writer::{SessionLayout, SessionWriter};
+use writer::{EncoderProgress, EnqueueReport, SessionLayout, SessionWriter};
 
 #[cfg(windows)]
 use windows::Win32::Foundation::HWND;
@@ -24,7 +40,7 @@ use windows::Win32::UI::HiDpi::{
     GetDpiForWindow, SetProcessDpiAwarenessContext, DPI_AWARENESS_CONTEXT_PER_MONITOR_AWARE_V2,
 };
 #[cfg(windows)]
-use windows::Win32::Graphics::Gdi::ScreenToClient;
+use windows::Win32::Graphics::Gdi::ClientToScreen;
 #[cfg(windows)]
 use windows::Win32::UI::WindowsAndMessaging::{
     GetClientRect, GetCursorInfo, GetCursorPos, GetForegroundWindow, CURSORINFO, CURSOR_SHOWING,
@@ -43,19 +59,135 @@ pub struct PipelineConfig {
     pub record_width: u32,
     pub record_height: u32,
     pub fps: u32,
+    pub alignment: ActionAlignment,
+    pub action_steps_per_frame: u32,
+    pub auto_events: AutoEventsOptions,
+    pub markers: MarkerOptions,
+    pub idle: IdleOptions,
+    pub video: VideoOutputOptions,
+    pub segment: SegmentOptions,
+    pub resize_mode: ResizeMode,
+    pub resize_filter: ResizeFilter,
+    // Applied to kept source frames before renditions are resized, and to
+    // cursor normalization.
+    pub source_crop: Option<SourceCrop>,
+    // Drawn into every video output while the cursor is visible.
+    pub cursor_overlay: Option<CursorStyle>,
+    // Privacy masks for this session, applied to every video output.
+    pub masks: Vec<MaskRegion>,
+}
+
+impl PipelineConfig {
+    // The pipeline settings of a session recorded with `options`; masks are
+    // picked for `meta.game`.
+    pub fn from_options(
+        options: &Options,
+        meta: &Meta,
+        dataset_root: &Path,
+        session_name: &str,
+        ffmpeg_path: &Path,
+    ) -> Self {
+        let capture = &options.capture;
+        Self {
+            dataset_root: dataset_root.to_path_buf(),
+            session_name: session_name.to_string(),
+            ffmpeg_path: ffmpeg_path.to_path_buf(),
+            record_width: capture.record_resolution[0],
+            record_height: capture.record_resolution[1],
+            fps: capture.fps,
+            alignment: options.timing.alignment,
+            action_steps_per_frame: options.timing.action_steps_per_frame,
+            auto_events: options.auto_events.clone(),
+            markers: options.markers.clone(),
+            idle: options.idle.clone(),
+            video: options.video.clone(),
+            segment: options.segment,
+            resize_mode: capture.resize_mode,
+            resize_filter: capture.resize_filter,
+            source_crop: capture.source_crop,
+            cursor_overlay: capture
+                .include_cursor_in_video
+                .then(|| capture.cursor_style.clone()),
+            masks: options.privacy.masks_for(&meta.game),
+        }
+    }
 }
 
 pub struct SessionPipeline {
     writer: SessionWriter,
     state: AggregatorState,
+    alignment: ActionAlignment,
+    action_steps_per_frame: u32,
+    auto_events: Option<AutoEventEngine>,
+    markers: Option<MarkerMap>,
+    rules: Option<RuleEngine>,
+    idle: Option<IdlePolicy>,
+    frame_diff: FrameDiffMeter,
+    rendition_buffers: Vec<Vec<u8>>,
+    resize_mode: ResizeMode,
+    resize_filter: ResizeFilter,
+    source_crop: Option<SourceCrop>,
+    cursor_sprite: Option<CursorSprite>,
+    masks: Vec<MaskRegion>,
+    paused_at: Option<StepIndex>,
+    action_step_ms: f64,
 }
 
 impl SessionPipeline {
     pub fn create(config: PipelineConfig) -> io::Result<Self> {
+        let auto_events = if config.auto_events.enabled {
+            Some(AutoEventEngine::from_options(
+                &config.auto_events,
+                &config.dataset_root,
+            )?)
+        } else {
+            None
+        };
+        let markers = config
+            .markers
+            .enabled
+            .then(|| MarkerMap::new(&config.markers));
+        let action_steps_per_frame = config.action_steps_per_frame.max(1);
+        let action_step_ms = action_step_ms(config.fps, action_steps_per_frame);
+        let rules = match config.auto_events.rules.as_deref() {
+            Some(path) => {
+                let rules = RuleSet::load(&resolve_config_path(&config.dataset_root, path))?;
+                Some(RuleEngine::new(rules, action_step_ms))
+            }
+            None => None,
+        };
+        let idle = config
+            .idle
+            .enabled
+            .then(|| IdlePolicy::new(&config.idle, 1000.0 / config.fps.max(1) as f64));
+        let cursor_sprite = match &config.cursor_overlay {
+            Some(CursorStyle::Arrow) => Some(CursorSprite::arrow()),
+            Some(CursorStyle::Crosshair) => Some(CursorSprite::crosshair()),
+            Some(CursorStyle::Png { path, hotspot }) => Some(CursorSprite::load_png(
+                &resolve_config_path(&config.dataset_root, path),
+                *hotspot,
+            )?),
+            None => None,
+        };
+        for mask in &config.masks {
+            let empty = match mask.style {
+                MaskStyle::Blur { radius } => radius == 0,
+                MaskStyle::Pixelate { block } => block == 0,
+                MaskStyle::Fill { .. } => false,
+            };
+            if empty {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidInput,
+                    format!("mask {:?}: blur radius and pixelate block must be > 0", mask.rect),
+                ));
+            }
+        }
         let writer = SessionWriter::create(
             &config.dataset_root,
             &config.session_name,
             &config.ffmpeg_path,
+            &config.video,
+            &config.segment,
             config.record_width,
             config.record_height,
             config.fps,
@@ -65,98 +197,413 @@ impl SessionPipeline {
         Ok(Self {
             writer,
             state: AggregatorState::new(),
+            alignment: config.alignment,
+            action_steps_per_frame,
+            auto_events,
+            markers,
+            rules,
+            idle,
+            frame_diff: FrameDiffMeter::default(),
+            rendition_buffers: Vec::new(),
+            resize_mode: config.resize_mode,
+            resize_filter: config.resize_filter,
+            source_crop: config.source_crop,
+            cursor_sprite,
+            masks: config.masks,
+            paused_at: None,
+            action_step_ms,
         })
     }
 
+    pub fn alignment(&self) -> ActionAlignment {
+        self.alignment
+    }
+
+    pub fn resize_mode(&self) -> ResizeMode {
+        self.resize_mode
+    }
+
+    pub fn source_crop(&self) -> Option<&SourceCrop> {
+        self.source_crop.as_ref()
+    }
+
+    pub fn encoder_progress(&self) -> Option<EncoderProgress> {
+        self.writer.encoder_progress()
+    }
+
     pub fn write_options_meta(&self, options: &Options, meta: &Meta) -> io::Result<()> {
         self.writer.write_options(options)?;
-        self.writer.write_meta(meta)?;
+        let mut meta = meta.clone();
+        meta.encoder_command = self.writer.encoder_command();
+        meta.renditions = self.writer.rendition_meta();
+        self.writer.write_meta(&meta)?;
         Ok(())
     }
 
+    pub fn is_paused(&self) -> bool {
+        self.paused_at.is_some()
+    }
+
+    // Operator pause: nothing is written while paused, but step indices keep
+    // counting so the gap between the paused/resumed events maps video time
+    // back to action time. Held keys are forgotten on resume.
+    pub fn set_paused(
+        &mut self,
+        paused: bool,
+        frame_index: StepIndex,
+        qpc_ts: QpcTimestamp,
+    ) -> io::Result<()> {
+        let step_index = frame_index.saturating_mul(self.action_steps_per_frame as u64);
+        let kind = match (paused, self.paused_at) {
+            (true, None) => {
+                self.paused_at = Some(step_index);
+                AutoEventKind::Paused {
+                    reason: PauseReason::Operator,
+                    since_step: step_index,
+                }
+            }
+            (false, Some(paused_step)) => {
+                self.paused_at = None;
+                self.state.reset();
+                self.frame_diff = FrameDiffMeter::default();
+                AutoEventKind::Resumed {
+                    reason: PauseReason::Operator,
+                    paused_step,
+                }
+            }
+            _ => return Ok(()),
+        };
+        eprintln!("[pause] step={} {:?}", step_index, kind);
+        self.write_auto_event(&AutoEvent {
+            step_index,
+            frame_index,
+            qpc_ts,
+            kind,
+        })
+    }
+
+    // Runs the frame detectors and gauge stream on a frame whose window is
+    // being recorded, so their records land in the frame's chunk after the
+    // pause checks.
+    fn process_auto_events(&mut self, frame: &FrameRecord) -> io::Result<()> {
+        let Some(engine) = self.auto_events.as_mut() else {
+            return Ok(());
+        };
+        let step_index = frame
+            .step_index
+            .saturating_mul(self.action_steps_per_frame as u64);
+        let output = engine.process_frame(frame);
+        for mut event in output.events {
+            event.frames_to_action_steps(self.action_steps_per_frame as u64);
+            self.write_auto_event(&event)?;
+        }
+        if let Some(mut sample) = output.gauge_sample {
+            sample.step_index = step_index;
+            self.writer.write_gauge_sample(&sample)?;
+        }
+        Ok(())
+    }
+
+    #[allow(clippy::too_many_arguments)]
     pub fn process_window(
         &mut self,
         events: &[InputEvent],
         window_start: QpcTimestamp,
         window_end: QpcTimestamp,
-        step_index: StepIndex,
+        frame_index: StepIndex,
         is_foreground: bool,
         cursor: &CursorProvider,
-        frame: &[u8],
+        frame: &FrameRecord,
         thought_content: Option<&str>,
         goal_long: Option<&str>,
         goal_mid: Option<&str>,
     ) -> io::Result<()> {
-        let aggregated = aggregate_window_with_compiled(
+        if self.paused_at.is_some() {
+            return Ok(());
+        }
+        if self.writer.rotate_if_due()? {
+            eprintln!(
+                "[chunk] step={} {}",
+                frame_index,
+                self.writer.layout().data_dir.display()
+            );
+        }
+        let (found, stripped) = match self.markers.as_mut() {
+            Some(markers) => {
+                let found = markers.detect(
+                    events,
+                    window_start,
+                    window_end,
+                    frame_index,
+                    self.action_steps_per_frame,
+                    self.action_step_ms,
+                );
+                let stripped = markers.strips_actions().then(|| markers.strip(events));
+                (found, stripped)
+            }
+            None => (Vec::new(), None),
+        };
+        for marker in &found {
+            eprintln!("[marker] step={} {:?}", marker.step_index, marker.kind);
+            self.write_auto_event(marker)?;
+        }
+        let events = stripped.as_deref().unwrap_or(events);
+        let windows = aggregate_frame_windows(
             events,
             window_start,
             window_end,
-            step_index,
+            frame_index,
+            self.action_steps_per_frame,
             is_foreground,
             cursor,
             &mut self.state,
         );
 
-        self.writer.write_window(&aggregated)?;
-        self.writer.write_frame(frame)?;
+        if let Some(idle) = self.idle.as_mut() {
+            let diff = self.frame_diff.measure(&frame.data);
+            let snapshots: Vec<_> = windows.iter().map(|w| w.snapshot.clone()).collect();
+            let transition = idle.observe(&snapshots, diff);
+            let paused = idle.is_paused();
+            if let (Some(transition), Some(first)) = (transition, snapshots.first()) {
+                let kind = match transition {
+                    IdleTransition::Pause { since_step } => AutoEventKind::Paused {
+                        reason: PauseReason::Idle,
+                        since_step,
+                    },
+                    IdleTransition::Resume { paused_step } => AutoEventKind::Resumed {
+                        reason: PauseReason::Idle,
+                        paused_step,
+                    },
+                };
+                eprintln!("[idle] step={} {:?}", first.step_index, transition);
+                self.write_auto_event(&AutoEvent {
+                    step_index: first.step_index,
+                    frame_index: first.frame_index,
+                    qpc_ts: first.qpc_ts,
+                    kind,
+                })?;
+            }
+            if paused {
+                return Ok(());
+            }
+        }
+        self.process_auto_events(frame)?;
+
         let thought_line = format_thought_line(thought_content.unwrap_or_default());
-        self.writer.write_thought(&thought_line)?;
         let goal_line = format_goal_line(goal_long.unwrap_or_default(), goal_mid.unwrap_or_default());
-        self.writer.write_goal(&goal_line)?;
+        for aggregated in &windows {
+            self.writer.write_window(aggregated)?;
+            if let Some(rules) = self.rules.as_mut() {
+                for derived in rules.process_snapshot(&aggregated.snapshot) {
+                    self.writer.write_auto_event(&derived)?;
+                }
+            }
+            self.writer.write_thought(&thought_line)?;
+            self.writer.write_goal(&goal_line)?;
+        }
+        let video_frame = self.writer.chunk_video_frames();
+        let step_index = frame_index.saturating_mul(self.action_steps_per_frame as u64);
+        let queued = self.write_video_frame(step_index, frame, cursor)?;
+        let mut timing = FrameTiming::from_frame(frame, step_index, video_frame);
+        timing.queue_depth = queued.depth;
+        timing.queue_stall_ms = queued.stall_ms;
+        timing.queue_evicted = queued.evicted;
+        timing.evicted_frames = queued.evicted_frames;
+        self.writer.write_frame_timing(&timing)?;
         Ok(())
     }
 
+    // Outputs at the frame's size take it as is; every other output is
+    // resized once, from the unscaled source (cropped like the capture) when
+    // the capture kept it.
+    // Privacy masks, then a visible cursor, are drawn into a copy of each
+    // output. Both are given in record coordinates and reach each output
+    // through the source region, so letterbox bars and crops of a rendition
+    // with another aspect ratio move them with the image.
+    fn write_video_frame(
+        &mut self,
+        step_index: StepIndex,
+        frame: &FrameRecord,
+        cursor: &CursorProvider,
+    ) -> io::Result<EnqueueReport> {
+        let outputs = self.writer.outputs();
+        let (src, src_w, src_h, region) = match frame.source.as_deref() {
+            Some(source)
+                if source.len()
+                    == frame.src_width as usize * frame.src_height as usize * 4 =>
+            {
+                let region =
+                    crop_rect(self.source_crop.as_ref(), frame.src_width, frame.src_height);
+                (source.as_slice(), frame.src_width, frame.src_height, region)
+            }
+            // Already cropped by the capture.
+            _ => (
+                frame.data.as_slice(),
+                frame.width,
+                frame.height,
+                crop_rect(None, frame.width, frame.height),
+            ),
+        };
+        let record = placement_in(region, frame.width, frame.height, self.resize_mode);
+        self.rendition_buffers.resize_with(outputs.len(), Vec::new);
+        let sprite = self.cursor_sprite.as_ref().filter(|_| cursor.visible);
+        let mut passthrough = Vec::with_capacity(outputs.len());
+        for (output, buffer) in outputs.iter().zip(&mut self.rendition_buffers) {
+            let same_size = output.width == frame.width && output.height == frame.height;
+            let copy = sprite.is_some() || !self.masks.is_empty();
+            passthrough.push(same_size && !copy);
+            if same_size && copy {
+                buffer.clear();
+                buffer.extend_from_slice(&frame.data);
+            } else if !same_size {
+                buffer.resize(output.width as usize * output.height as usize * 4, 0);
+                resize_frame_region(
+                    src,
+                    src_w,
+                    src_h,
+                    region,
+                    buffer,
+                    output.width,
+                    output.height,
+                    self.resize_mode,
+                    self.resize_filter,
+                );
+            }
+            let placed = placement_in(region, output.width, output.height, self.resize_mode);
+            let scale = (placed.dst.height as f32 / placed.src.height as f32)
+                / (record.dst.height as f32 / record.src.height as f32);
+            for mask in &self.masks {
+                let Some(rect) =
+                    map_mask_rect(mask.rect, &record, &placed, output.width, output.height)
+                else {
+                    continue;
+                };
+                apply_mask(buffer, output.width, output.height, rect, mask.style, scale);
+            }
+            if let Some(sprite) = sprite {
+                let (x, y) = record.unmap(
+                    cursor.x_norm * frame.width as f32,
+                    cursor.y_norm * frame.height as f32,
+                );
+                let (x, y) = placed.map(x, y);
+                sprite.draw(buffer, output.width, output.height, x, y, scale);
+            }
+        }
+        let frames: Vec<&[u8]> = passthrough
+            .iter()
+            .zip(&self.rendition_buffers)
+            .map(|(same_size, buffer)| {
+                if *same_size {
+                    frame.data.as_slice()
+                } else {
+                    buffer.as_slice()
+                }
+            })
+            .collect();
+        self.writer.write_renditions(step_index, &frames)
+    }
+
+    fn write_auto_event(&mut self, event: &AutoEvent) -> io::Result<()> {
+        if let Some(rules) = self.rules.as_mut() {
+            rules.push_auto_event(event);
+        }
+        self.writer.write_auto_event(event)
+    }
+
     pub fn finalize(self) -> io::Result<SessionLayout> {
+        let stats = self.writer.queue_stats();
+        if stats.frames_evicted > 0 || stats.stall_ms_total >= 1.0 {
+            eprintln!(
+                "[encoder] queued={} evicted={} max_depth={} stall_ms={:.1}",
+                stats.frames_queued, stats.frames_evicted, stats.max_depth, stats.stall_ms_total
+            );
+        }
         self.writer.finalize()
     }
 }
 
 #[allow(dead_code)]
 pub fn run_realtime<S: FrameSource, I: InputCollector>(
+    capture: S,
+    input: I,
+    cursor: &CursorProvider,
+    pipeline: SessionPipeline,
+    step_ms: u64,
+) -> io::Result<SessionLayout> {
+    run_realtime_with_controls(
+        capture,
+        input,
+        cursor,
+        pipeline,
+        &mut || false,
+        &mut || false,
+        step_ms,
+    )
+}
+
+pub fn run_realtime_with_controls<
+    S: FrameSource,
+    I: InputCollector,
+    P: FnMut() -> bool,
+    Q: FnMut() -> bool,
+>(
     mut capture: S,
     mut input: I,
     cursor: &CursorProvider,
     mut pipeline: SessionPipeline,
+    should_stop: &mut P,
+    should_pause: &mut Q,
     step_ms: u64,
 ) -> io::Result<SessionLayout> {
     let step_ticks = qpc_step_ticks(step_ms)?;
+    let offset_ticks = alignment_offset_ticks(pipeline.alignment(), step_ms, step_ticks);
+    let mut pending = LookaheadQueue::new();
     loop {
-        let frame = match capture.next_frame() {
-            Ok(frame) => frame,
-            Err(err) if err.kind() == io::ErrorKind::UnexpectedEof => break,
-            Err(err) => return Err(err),
+        let next = if should_stop() {
+            None
+        } else {
+            match capture.next_frame() {
+                Ok(frame) => Some(frame),
+                Err(err) if err.kind() == io::ErrorKind::UnexpectedEof => None,
+                Err(err) => return Err(err),
+            }
         };
 
-        let window_end = frame.qpc_ts;
-        let window_start = window_end.saturating_sub(step_ticks);
-        let events = input.drain_events(window_start, window_end)?;
-        if events.is_empty() {
-            eprintln!(
-                "[input] step={} events=0 window=({}-{})",
-                frame.step_index, window_start, window_end
-            );
-        } else {
-            eprintln!(
-                "[input] step={} events={}",
+        let now = match next {
+            Some(frame) => {
+                let paused = should_pause();
+                let now = frame.qpc_ts;
+                let (window_start, window_end) = aligned_window(now, step_ticks, offset_ticks);
+                pending.push(window_start, window_end, (frame, paused));
+                Some(now)
+            }
+            None => None,
+        };
+
+        while let Some(ready) = pending.pop_ready(now) {
+            let (frame, paused) = ready.payload;
+            let events = input.drain_events(ready.window_start, ready.window_end)?;
+            log_input_window(frame.step_index, &events, ready.window_start, ready.window_end);
+            pipeline.set_paused(paused, frame.step_index, frame.qpc_ts)?;
+            let is_foreground = true;
+            let cursor_sample = cursor.clone();
+
+            pipeline.process_window(
+                &events,
+                ready.window_start,
+                ready.window_end,
                 frame.step_index,
-                events.len()
-            );
+                is_foreground,
+                &cursor_sample,
+                &frame,
+                None,
+                None,
+                None,
+            )?;
+        }
+        if now.is_none() {
+            break;
         }
-        let is_foreground = true;
-        let cursor_sample = cursor.clone();
-
-        pipeline.process_window(
-            &events,
-            window_start,
-            window_end,
-            frame.step_index,
-            is_foreground,
-            &cursor_sample,
-            &frame.data,
-            None,
-            None,
-            None,
-        )?;
     }
 
     pipeline.finalize()
@@ -210,6 +657,7 @@ pub fn run_realtime_with_hwnd_and_hook<
 }
 
 #[cfg(windows)]
+#[allow(clippy::too_many_arguments)]
 pub fn run_realtime_with_hwnd_and_hook_and_thought<
     S: FrameSource,
     I: InputCollector,
@@ -242,6 +690,7 @@ pub fn run_realtime_with_hwnd_and_hook_and_thought<
 }
 
 #[cfg(windows)]
+#[allow(clippy::too_many_arguments)]
 pub fn run_realtime_with_hwnd_and_hook_and_thought_with_stop<
     S: FrameSource,
     I: InputCollector,
@@ -249,6 +698,43 @@ pub fn run_realtime_with_hwnd_and_hook_and_thought_with_stop<
     T: FnMut() -> String,
     G: FnMut() -> (String, String),
     P: FnMut() -> bool,
+>(
+    capture: S,
+    input: I,
+    target_hwnd: isize,
+    debug_cursor: bool,
+    pipeline: SessionPipeline,
+    on_frame: &mut F,
+    thought_provider: &mut T,
+    goal_provider: &mut G,
+    should_stop: &mut P,
+    step_ms: u64,
+) -> io::Result<SessionLayout> {
+    run_realtime_with_hwnd_and_hook_and_thought_with_controls(
+        capture,
+        input,
+        target_hwnd,
+        debug_cursor,
+        pipeline,
+        on_frame,
+        thought_provider,
+        goal_provider,
+        should_stop,
+        &mut || false,
+        step_ms,
+    )
+}
+
+#[cfg(windows)]
+#[allow(clippy::too_many_arguments)]
+pub fn run_realtime_with_hwnd_and_hook_and_thought_with_controls<
+    S: FrameSource,
+    I: InputCollector,
+    F: FnMut(&FrameRecord, bool, &CursorProvider),
+    T: FnMut() -> String,
+    G: FnMut() -> (String, String),
+    P: FnMut() -> bool,
+    Q: FnMut() -> bool,
 >(
     mut capture: S,
     mut input: I,
@@ -259,93 +745,167 @@ pub fn run_realtime_with_hwnd_and_hook_and_thought_with_stop<
     thought_provider: &mut T,
     goal_provider: &mut G,
     should_stop: &mut P,
+    should_pause: &mut Q,
     step_ms: u64,
 ) -> io::Result<SessionLayout> {
     let step_ticks = qpc_step_ticks(step_ms)?;
+    let offset_ticks = alignment_offset_ticks(pipeline.alignment(), step_ms, step_ticks);
+    let mut pending = LookaheadQueue::new();
     let mut cursor_test = CursorTestState::new();
     set_per_monitor_dpi_awareness();
     loop {
-        if should_stop() {
-            break;
-        }
-        let frame = match capture.next_frame() {
-            Ok(frame) => frame,
-            Err(err) if err.kind() == io::ErrorKind::UnexpectedEof => break,
-            Err(err) => return Err(err),
+        let next = if should_stop() {
+            None
+        } else {
+            match capture.next_frame() {
+                Ok(frame) => Some(frame),
+                Err(err) if err.kind() == io::ErrorKind::UnexpectedEof => None,
+                Err(err) => return Err(err),
+            }
         };
 
-        let window_end = frame.qpc_ts;
-        let window_start = window_end.saturating_sub(step_ticks);
-        let events = input.drain_events(window_start, window_end)?;
-        if events.is_empty() {
-            eprintln!(
-                "[input] step={} events=0 window=({}-{})",
-                frame.step_index, window_start, window_end
-            );
-        } else {
-            eprintln!(
-                "[input] step={} events={}",
+        let now = match next {
+            Some(frame) => {
+                let (is_foreground, cursor, debug_info) = sample_foreground_and_cursor(
+                    target_hwnd,
+                    frame.src_width,
+                    frame.src_height,
+                    frame.width,
+                    frame.height,
+                    pipeline.resize_mode(),
+                    pipeline.source_crop(),
+                )?;
+                on_frame(&frame, is_foreground, &cursor);
+                let paused = should_pause();
+                if debug_cursor {
+                    if let Some(info) = debug_info.as_ref() {
+                        let client = info.transform.client();
+                        let placement = info.transform.placement();
+                        eprintln!(
+                            "[cursor] step={} fg={} vis={} dpi={} screen=({}, {}) client_rect=({}, {}, {}, {}) client=({:.1}, {:.1}) src=({:.1}, {:.1}) src_rect=({}, {}, {}, {}) dst_rect=({}, {}, {}, {}) inside={} record_xy=({:.1}, {:.1}) norm=({:.4}, {:.4})",
+                            frame.step_index,
+                            is_foreground,
+                            cursor.visible,
+                            info.dpi,
+                            info.screen_x,
+                            info.screen_y,
+                            client.left,
+                            client.top,
+                            client.width,
+                            client.height,
+                            info.point.client_x,
+                            info.point.client_y,
+                            info.point.src_x,
+                            info.point.src_y,
+                            placement.src.x,
+                            placement.src.y,
+                            placement.src.width,
+                            placement.src.height,
+                            placement.dst.x,
+                            placement.dst.y,
+                            placement.dst.width,
+                            placement.dst.height,
+                            info.point.inside,
+                            info.point.record_x,
+                            info.point.record_y,
+                            cursor.x_norm,
+                            cursor.y_norm
+                        );
+                    }
+                }
+                let now = frame.qpc_ts;
+                let (window_start, window_end) = aligned_window(now, step_ticks, offset_ticks);
+                pending.push(
+                    window_start,
+                    window_end,
+                    (frame, is_foreground, cursor, debug_info, paused),
+                );
+                Some(now)
+            }
+            None => None,
+        };
+
+        while let Some(ready) = pending.pop_ready(now) {
+            let (frame, is_foreground, cursor, debug_info, paused) = ready.payload;
+            let events = input.drain_events(ready.window_start, ready.window_end)?;
+            log_input_window(frame.step_index, &events, ready.window_start, ready.window_end);
+            pipeline.set_paused(paused, frame.step_index, frame.qpc_ts)?;
+            if debug_cursor && cursor_test.triggered(&events) {
+                cursor_test.log_result(&cursor, debug_info.as_ref());
+            }
+
+            let thought_line = thought_provider();
+            let (goal_long, goal_mid) = goal_provider();
+            pipeline.process_window(
+                &events,
+                ready.window_start,
+                ready.window_end,
                 frame.step_index,
-                events.len()
-            );
+                is_foreground,
+                &cursor,
+                &frame,
+                Some(thought_line.as_str()),
+                Some(goal_long.as_str()),
+                Some(goal_mid.as_str()),
+            )?;
         }
-        let (is_foreground, cursor, debug_info) = sample_foreground_and_cursor(
-            target_hwnd,
-            frame.src_width,
-            frame.src_height,
-            frame.width,
-            frame.height,
-        )?;
-        on_frame(&frame, is_foreground, &cursor);
-        if debug_cursor && cursor_test.triggered(&events) {
-            cursor_test.log_result(&cursor, debug_info.as_ref());
+        if now.is_none() {
+            break;
         }
-        if debug_cursor {
-            if let Some(info) = debug_info {
-                eprintln!(
-                    "[cursor] step={} fg={} vis={} dpi={} client=({}, {}) client_wh=({:.1}, {:.1}) src=({:.1}, {:.1}) src_wh=({:.1}, {:.1}) record_wh=({:.1}, {:.1}) scale={:.4} pad=({:.1}, {:.1}) record_xy=({:.1}, {:.1}) norm=({:.4}, {:.4})",
-                    frame.step_index,
-                    is_foreground,
-                    cursor.visible,
-                    info.dpi,
-                    info.client_x,
-                    info.client_y,
-                    info.client_w,
-                    info.client_h,
-                    info.src_x,
-                    info.src_y,
-                    info.src_w,
-                    info.src_h,
-                    info.record_w,
-                    info.record_h,
-                    info.scale,
-                    info.pad_x,
-                    info.pad_y,
-                    info.record_x,
-                    info.record_y,
-                    cursor.x_norm,
-                    cursor.y_norm
-                );
-            }
+    }
+
+    pipeline.finalize()
+}
+
+struct PendingWindow<T> {
+    window_start: QpcTimestamp,
+    window_end: QpcTimestamp,
+    payload: T,
+}
+
+struct LookaheadQueue<T> {
+    pending: VecDeque<PendingWindow<T>>,
+}
+
+impl<T> LookaheadQueue<T> {
+    fn new() -> Self {
+        Self {
+            pending: VecDeque::new(),
         }
+    }
 
-        let thought_line = thought_provider();
-        let (goal_long, goal_mid) = goal_provider();
-        pipeline.process_window(
-            &events,
+    fn push(&mut self, window_start: QpcTimestamp, window_end: QpcTimestamp, payload: T) {
+        self.pending.push_back(PendingWindow {
             window_start,
             window_end,
-            frame.step_index,
-            is_foreground,
-            &cursor,
-            &frame.data,
-            Some(thought_line.as_str()),
-            Some(goal_long.as_str()),
-            Some(goal_mid.as_str()),
-        )?;
+            payload,
+        });
     }
 
-    pipeline.finalize()
+    // `now == None` flushes everything that is still waiting for lookahead input.
+    fn pop_ready(&mut self, now: Option<QpcTimestamp>) -> Option<PendingWindow<T>> {
+        match (self.pending.front(), now) {
+            (Some(front), Some(now)) if front.window_end > now => None,
+            (Some(_), _) => self.pending.pop_front(),
+            (None, _) => None,
+        }
+    }
+}
+
+fn log_input_window(
+    step_index: StepIndex,
+    events: &[InputEvent],
+    window_start: QpcTimestamp,
+    window_end: QpcTimestamp,
+) {
+    if events.is_empty() {
+        eprintln!(
+            "[input] step={} events=0 window=({}-{})",
+            step_index, window_start, window_end
+        );
+    } else {
+        eprintln!("[input] step={} events={}", step_index, events.len());
+    }
 }
 
 fn qpc_step_ticks(step_ms: u64) -> io::Result<u64> {
@@ -354,7 +914,7 @@ fn qpc_step_ticks(step_ms: u64) -> io::Result<u64> {
         unsafe {
             let mut freq = 0i64;
             QueryPerformanceFrequency(&mut freq)
-                .map_err(|err| io::Error::new(io::ErrorKind::Other, format!("{:?}", err)))?;
+                .map_err(|err| io::Error::other(format!("{:?}", err)))?;
             let ticks = (freq as u64)
                 .saturating_mul(step_ms)
                 .saturating_div(1000)
@@ -375,6 +935,8 @@ fn sample_foreground_and_cursor(
     src_height: u32,
     record_width: u32,
     record_height: u32,
+    resize_mode: ResizeMode,
+    source_crop: Option<&SourceCrop>,
 ) -> io::Result<(bool, CursorProvider, Option<CursorDebug>)> {
     unsafe {
         let target = HWND(target_hwnd);
@@ -394,59 +956,40 @@ fn sample_foreground_and_cursor(
         }
 
         let mut point = windows::Win32::Foundation::POINT { x: 0, y: 0 };
+        let mut origin = windows::Win32::Foundation::POINT { x: 0, y: 0 };
+        let mut rect = windows::Win32::Foundation::RECT::default();
         if GetCursorPos(&mut point).is_ok()
-            && record_width > 0
-            && record_height > 0
-            && src_width > 0
-            && src_height > 0
+            && ClientToScreen(target, &mut origin).as_bool()
+            && GetClientRect(target, &mut rect).is_ok()
         {
-            let mut client_point = point;
-            if ScreenToClient(target, &mut client_point).as_bool() {
-                let mut rect = windows::Win32::Foundation::RECT::default();
-                if GetClientRect(target, &mut rect).is_ok() {
-                    let client_w = (rect.right - rect.left).max(0) as f32;
-                    let client_h = (rect.bottom - rect.top).max(0) as f32;
-                    if client_w > 0.0 && client_h > 0.0 {
-                        let dpi = GetDpiForWindow(target) as f32;
-                        let src_w = src_width as f32;
-                        let src_h = src_height as f32;
-                        let dst_w = record_width as f32;
-                        let dst_h = record_height as f32;
-                        let scale_x = src_w / client_w;
-                        let scale_y = src_h / client_h;
-                        let dpi_scale = (dpi / 96.0).max(0.0001);
-                        let src_x = (client_point.x as f32 / dpi_scale) * scale_x;
-                        let src_y = (client_point.y as f32 / dpi_scale) * scale_y;
-
-                        let scale = (dst_w / src_w).min(dst_h / src_h);
-                        let scaled_w = src_w * scale;
-                        let scaled_h = src_h * scale;
-                        let pad_x = (dst_w - scaled_w) * 0.5;
-                        let pad_y = (dst_h - scaled_h) * 0.5;
-                        let record_x = (src_x * scale) + pad_x;
-                        let record_y = (src_y * scale) + pad_y;
-                        x_norm = (record_x / dst_w).clamp(0.0, 1.0);
-                        y_norm = (record_y / dst_h).clamp(0.0, 1.0);
-                        debug_info = Some(CursorDebug {
-                            dpi: dpi.round() as u32,
-                            client_x: client_point.x,
-                            client_y: client_point.y,
-                            client_w,
-                            client_h,
-                            src_x,
-                            src_y,
-                            src_w: src_width,
-                            src_h: src_height,
-                            record_w: record_width,
-                            record_h: record_height,
-                            scale,
-                            pad_x,
-                            pad_y,
-                            record_x,
-                            record_y,
-                        });
-                    }
-                }
+            let client = ClientRect {
+                left: origin.x,
+                top: origin.y,
+                width: (rect.right - rect.left).max(0) as u32,
+                height: (rect.bottom - rect.top).max(0) as u32,
+            };
+            let dpi = GetDpiForWindow(target);
+            if let Some(transform) = CursorTransform::new(
+                dpi,
+                client,
+                [src_width, src_height],
+                [record_width, record_height],
+                resize_mode,
+                source_crop,
+            ) {
+                let mapped = transform.map_screen(point.x, point.y);
+                x_norm = mapped.x_norm;
+                y_norm = mapped.y_norm;
+                // A cursor over a cropped-away area (center crop or the
+                // source crop) is not in the video.
+                visible &= mapped.inside;
+                debug_info = Some(CursorDebug {
+                    dpi,
+                    screen_x: point.x,
+                    screen_y: point.y,
+                    transform,
+                    point: mapped,
+                });
             }
         }
 
@@ -468,21 +1011,10 @@ fn set_per_monitor_dpi_awareness() {
 #[cfg(windows)]
 struct CursorDebug {
     dpi: u32,
-    client_x: i32,
-    client_y: i32,
-    client_w: f32,
-    client_h: f32,
-    src_x: f32,
-    src_y: f32,
-    src_w: u32,
-    src_h: u32,
-    record_w: u32,
-    record_h: u32,
-    scale: f32,
-    pad_x: f32,
-    pad_y: f32,
-    record_x: f32,
-    record_y: f32,
+    screen_x: i32,
+    screen_y: i32,
+    transform: CursorTransform,
+    point: CursorPoint,
 }
 
 #[cfg(windows)]
@@ -516,15 +1048,20 @@ impl CursorTestState {
         ];
         let target = targets[self.index % targets.len()];
         if let Some(debug) = debug {
+            let (expected_x, expected_y) = debug.transform.normalized_to_screen(target.1, target.2);
             eprintln!(
-                "[cursor-test] target={} expected=({:.2}, {:.2}) measured=({:.4}, {:.4}) record_xy=({:.1}, {:.1})",
+                "[cursor-test] target={} expected=({:.2}, {:.2}) measured=({:.4}, {:.4}) record_xy=({:.1}, {:.1}) screen=({}, {}) expected_screen=({:.1}, {:.1})",
                 target.0,
                 target.1,
                 target.2,
                 cursor.x_norm,
                 cursor.y_norm,
-                debug.record_x,
-                debug.record_y
+                debug.point.record_x,
+                debug.point.record_y,
+                debug.screen_x,
+                debug.screen_y,
+                expected_x,
+                expected_y
             );
         } else {
             eprintln!(
@@ -598,3 +1135,315 @@ pub fn ensure_dataset_root(path: &Path) -> io::Result<()> {
     }
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn lookahead_queue_waits_for_window_end() {
+        let mut queue = LookaheadQueue::new();
+        queue.push(100, 300, 0u64);
+        queue.push(300, 500, 1u64);
+        assert!(queue.pop_ready(Some(299)).is_none());
+        assert_eq!(queue.pop_ready(Some(300)).map(|ready| ready.payload), Some(0));
+        assert!(queue.pop_ready(Some(300)).is_none());
+        assert_eq!(queue.pop_ready(None).map(|ready| ready.payload), Some(1));
+        assert!(queue.pop_ready(None).is_none());
+    }
+
+    // A 32x32 raw-video session under `root`, without cursor or masks.
+    fn raw_config(root: &Path) -> PipelineConfig {
+        let options = Options::default_v1();
+        PipelineConfig {
+            dataset_root: root.to_path_buf(),
+            session_name: "session".to_string(),
+            ffmpeg_path: PathBuf::from("ffmpeg-not-needed"),
+            record_width: 32,
+            record_height: 32,
+            fps: 5,
+            alignment: ActionAlignment::Preceding,
+            action_steps_per_frame: 1,
+            auto_events: options.auto_events.clone(),
+            markers: options.markers.clone(),
+            idle: options.idle.clone(),
+            video: VideoOutputOptions {
+                sink: collector_core::VideoSinkKind::RawBgra,
+                ..VideoOutputOptions::default()
+            },
+            segment: SegmentOptions::default(),
+            resize_mode: ResizeMode::BLACK_LETTERBOX,
+            resize_filter: ResizeFilter::Area,
+            source_crop: None,
+            cursor_overlay: None,
+            masks: Vec::new(),
+        }
+    }
+
+    fn gray_frame() -> FrameRecord {
+        FrameRecord {
+            step_index: 0,
+            qpc_ts: 0,
+            src_width: 32,
+            src_height: 32,
+            width: 32,
+            height: 32,
+            data: vec![100; 32 * 32 * 4],
+            pacing: Default::default(),
+            source: None,
+        }
+    }
+
+    #[test]
+    fn visible_cursor_is_drawn_into_the_video() {
+        use std::fs;
+        let root = std::env::temp_dir().join(format!("pipeline_cursor_{}", std::process::id()));
+        let _ = fs::remove_dir_all(&root);
+        fs::create_dir_all(&root).unwrap();
+        let mut pipeline = SessionPipeline::create(PipelineConfig {
+            cursor_overlay: Some(CursorStyle::Crosshair),
+            ..raw_config(&root)
+        })
+        .unwrap();
+        let frame = gray_frame();
+        for visible in [true, false] {
+            let cursor = CursorProvider {
+                visible,
+                x_norm: 0.5,
+                y_norm: 0.25,
+            };
+            pipeline
+                .process_window(&[], 0, 1, 0, true, &cursor, &frame, None, None, None)
+                .unwrap();
+        }
+        let layout = pipeline.finalize().unwrap();
+        let video = fs::read(&layout.raw_video_path).unwrap();
+        let pixel = |index: usize, x: usize, y: usize| {
+            let at = index * 32 * 32 * 4 + (y * 32 + x) * 4;
+            video[at..at + 4].to_vec()
+        };
+        assert_eq!(pixel(0, 16, 8), vec![255, 255, 255, 100]);
+        assert_eq!(pixel(0, 17, 9), vec![0, 0, 0, 100]);
+        assert_eq!(pixel(0, 0, 0), vec![100; 4]);
+        assert_eq!(pixel(1, 16, 8), vec![100; 4]);
+        fs::remove_dir_all(&root).unwrap();
+    }
+
+    #[test]
+    fn masks_are_applied_before_the_cursor() {
+        use std::fs;
+        let root = std::env::temp_dir().join(format!("pipeline_masks_{}", std::process::id()));
+        let _ = fs::remove_dir_all(&root);
+        fs::create_dir_all(&root).unwrap();
+        let mask = MaskRegion {
+            rect: [0, 0, 20, 10],
+            style: MaskStyle::Fill { color: [255, 0, 0] },
+            game: None,
+        };
+        let mut pipeline = SessionPipeline::create(PipelineConfig {
+            cursor_overlay: Some(CursorStyle::Crosshair),
+            masks: vec![mask],
+            ..raw_config(&root)
+        })
+        .unwrap();
+        let cursor = CursorProvider {
+            visible: true,
+            x_norm: 0.25,
+            y_norm: 0.25,
+        };
+        pipeline
+            .process_window(&[], 0, 1, 0, true, &cursor, &gray_frame(), None, None, None)
+            .unwrap();
+        let layout = pipeline.finalize().unwrap();
+        let video = fs::read(&layout.raw_video_path).unwrap();
+        let pixel = |x: usize, y: usize| video[(y * 32 + x) * 4..(y * 32 + x) * 4 + 4].to_vec();
+        assert_eq!(pixel(0, 0), vec![0, 0, 255, 255]);
+        assert_eq!(pixel(19, 9), vec![0, 0, 255, 255]);
+        assert_eq!(pixel(20, 9), vec![100; 4]);
+        assert_eq!(pixel(0, 10), vec![100; 4]);
+        // The crosshair center sits on top of the mask.
+        assert_eq!(pixel(8, 8), vec![255, 255, 255, 255]);
+        fs::remove_dir_all(&root).unwrap();
+    }
+
+    #[test]
+    fn masks_follow_the_image_into_letterboxed_renditions() {
+        use std::fs;
+        let root = std::env::temp_dir().join(format!("pipeline_mask_aspect_{}", std::process::id()));
+        let _ = fs::remove_dir_all(&root);
+        fs::create_dir_all(&root).unwrap();
+        let mut config = raw_config(&root);
+        // A 32x32 record in a 16x32 rendition: the image is scaled to 16x16
+        // between 8-row bars.
+        config.video.renditions = vec![collector_core::Rendition {
+            name: "tall".to_string(),
+            resolution: [16, 32],
+            encode: None,
+        }];
+        config.masks = vec![MaskRegion {
+            rect: [0, 24, 32, 8],
+            style: MaskStyle::Fill { color: [255, 0, 0] },
+            game: None,
+        }];
+        let mut pipeline = SessionPipeline::create(config).unwrap();
+        let cursor = CursorProvider {
+            visible: false,
+            x_norm: 0.0,
+            y_norm: 0.0,
+        };
+        pipeline
+            .process_window(&[], 0, 1, 0, true, &cursor, &gray_frame(), None, None, None)
+            .unwrap();
+        let layout = pipeline.finalize().unwrap();
+        let video = fs::read(layout.root_dir.join("video_tall.bgra")).unwrap();
+        let row = |y: usize| video[y * 16 * 4..(y + 1) * 16 * 4].to_vec();
+        // Record rows 24..32 land on rendition rows 20..24.
+        assert_eq!(row(19), [100; 4].repeat(16));
+        for y in 20..24 {
+            assert_eq!(row(y), [0, 0, 255, 255].repeat(16), "row {}", y);
+        }
+        assert_eq!(row(24), [0, 0, 0, 255].repeat(16));
+        fs::remove_dir_all(&root).unwrap();
+    }
+
+    #[test]
+    fn cursor_follows_the_image_into_letterboxed_renditions() {
+        use std::fs;
+        let root = std::env::temp_dir().join(format!("pipeline_cursor_aspect_{}", std::process::id()));
+        let _ = fs::remove_dir_all(&root);
+        fs::create_dir_all(&root).unwrap();
+        let mut config = raw_config(&root);
+        config.cursor_overlay = Some(CursorStyle::Crosshair);
+        config.video.renditions = vec![collector_core::Rendition {
+            name: "tall".to_string(),
+            resolution: [32, 64],
+            encode: None,
+        }];
+        let mut pipeline = SessionPipeline::create(config).unwrap();
+        // The top edge of the record is row 16 of the rendition, below the bar.
+        let cursor = CursorProvider {
+            visible: true,
+            x_norm: 0.5,
+            y_norm: 0.0,
+        };
+        pipeline
+            .process_window(&[], 0, 1, 0, true, &cursor, &gray_frame(), None, None, None)
+            .unwrap();
+        let layout = pipeline.finalize().unwrap();
+        let video = fs::read(layout.root_dir.join("video_tall.bgra")).unwrap();
+        let pixel = |x: usize, y: usize| video[(y * 32 + x) * 4..(y * 32 + x) * 4 + 4].to_vec();
+        assert_eq!(pixel(16, 16)[..3], [255, 255, 255]);
+        assert_eq!(pixel(16, 0), vec![0, 0, 0, 255]);
+        fs::remove_dir_all(&root).unwrap();
+    }
+
+    #[test]
+    fn gauge_samples_follow_their_frame_into_chunks_and_pauses() {
+        use std::fs;
+        let root = std::env::temp_dir().join(format!("pipeline_gauges_{}", std::process::id()));
+        let _ = fs::remove_dir_all(&root);
+        fs::create_dir_all(&root).unwrap();
+        fs::write(
+            root.join("rois.json"),
+            r#"{"width":32,"height":32,"rois":[{"name":"bar","x":0,"y":0,"w":32,"h":32}],
+                "gauges":[{"name":"hp","roi":"bar","orientation":"horizontal",
+                    "fill_min":[180,0,0],"fill_max":[255,60,60],"stream":true}]}"#,
+        )
+        .unwrap();
+        let mut config = raw_config(&root);
+        config.auto_events.enabled = true;
+        config.auto_events.roi_config = "rois.json".to_string();
+        config.segment.every_frames = 1;
+        let mut pipeline = SessionPipeline::create(config).unwrap();
+        let cursor = CursorProvider {
+            visible: false,
+            x_norm: 0.0,
+            y_norm: 0.0,
+        };
+        for step in 0..3 {
+            let frame = FrameRecord {
+                step_index: step,
+                ..gray_frame()
+            };
+            // Frame 1 is recorded while the operator has paused.
+            pipeline.set_paused(step == 1, step, 0).unwrap();
+            pipeline
+                .process_window(&[], 0, 1, step, true, &cursor, &frame, None, None, None)
+                .unwrap();
+        }
+        pipeline.finalize().unwrap();
+        let chunks = SessionLayout::new(&root, "session").chunk_layouts().unwrap();
+        let gauge_steps: Vec<Vec<u64>> = chunks
+            .iter()
+            .map(|chunk| {
+                fs::read_to_string(&chunk.gauges_path)
+                    .unwrap_or_default()
+                    .lines()
+                    .map(|line| {
+                        let value: serde_json::Value = serde_json::from_str(line).unwrap();
+                        value["step_index"].as_u64().unwrap()
+                    })
+                    .collect()
+            })
+            .collect();
+        assert_eq!(gauge_steps, vec![vec![0], vec![2]]);
+        fs::remove_dir_all(&root).unwrap();
+    }
+
+    #[test]
+    fn zero_sized_blur_is_rejected() {
+        let root = std::env::temp_dir().join(format!("pipeline_bad_mask_{}", std::process::id()));
+        let result = SessionPipeline::create(PipelineConfig {
+            masks: vec![MaskRegion {
+                rect: [0, 0, 8, 8],
+                style: MaskStyle::Blur { radius: 0 },
+                game: None,
+            }],
+            ..raw_config(&root)
+        });
+        assert_eq!(result.err().unwrap().kind(), io::ErrorKind::InvalidInput);
+    }
+
+    #[test]
+    fn renditions_resize_the_cropped_source() {
+        use std::fs;
+        use std::sync::Arc;
+        let root = std::env::temp_dir().join(format!("pipeline_crop_{}", std::process::id()));
+        let _ = fs::remove_dir_all(&root);
+        fs::create_dir_all(&root).unwrap();
+        let mut config = raw_config(&root);
+        config.video.renditions = vec![collector_core::Rendition {
+            name: "small".to_string(),
+            resolution: [16, 16],
+            encode: None,
+        }];
+        // The right half of a 64x32 source is white play area, the left half
+        // red UI.
+        config.source_crop = Some(SourceCrop::Normalized([0.5, 0.0, 0.5, 1.0]));
+        let mut pipeline = SessionPipeline::create(config).unwrap();
+        let mut source = Vec::new();
+        for _ in 0..32 {
+            source.extend_from_slice(&[0, 0, 255, 255].repeat(32));
+            source.extend_from_slice(&[255; 32 * 4]);
+        }
+        let frame = FrameRecord {
+            src_width: 64,
+            src_height: 32,
+            source: Some(Arc::new(source)),
+            ..gray_frame()
+        };
+        let cursor = CursorProvider {
+            visible: false,
+            x_norm: 0.0,
+            y_norm: 0.0,
+        };
+        pipeline
+            .process_window(&[], 0, 1, 0, true, &cursor, &frame, None, None, None)
+            .unwrap();
+        let layout = pipeline.finalize().unwrap();
+        let video = fs::read(layout.root_dir.join("video_small.bgra")).unwrap();
+        assert_eq!(video.len(), 16 * 16 * 4);
+        assert!(video.iter().all(|&value| value == 255));
+        fs::remove_dir_all(&root).unwrap();
+    }
+}
@@ -0,0 +1,682 @@
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, BufRead, BufReader};
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+
+use collector_core::{Options, VideoSinkKind};
+use serde_json::Value;
+use writer::{
+    chunk_dir_name, read_chunk_index, scan_chunk_dirs, seal_session_dir, video_outputs,
+    write_chunk_index, ChunkEntry, ChunkIndex, SessionLayout, VideoOutput,
+};
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RecoveryReport {
+    pub session_dir: PathBuf,
+    pub steps: u64,
+    pub dropped_bytes: u64,
+    pub video_remuxed: bool,
+}
+
+// Repairs a session left behind by a crash: JSONL files are cut back to their
+// last complete line, the video is remuxed so it gets a proper index, the
+// step-aligned files are cut to a common whole-frame step count the video
+// still covers, and meta.json is flagged `recovered`.
+// Segmented sessions are repaired chunk by chunk and get a rebuilt chunk
+// index. A `<name>.tmp` staging directory is sealed to its final name
+// afterwards.
+pub fn recover_session(
+    dataset_root: &Path,
+    session_name: &str,
+    ffmpeg_path: &Path,
+) -> io::Result<RecoveryReport> {
+    let final_layout = SessionLayout::new(dataset_root, session_name);
+    let staged = final_layout.temp_dir.is_dir();
+    let layout = if staged {
+        if final_layout.root_dir.exists() {
+            return Err(io::Error::new(
+                io::ErrorKind::AlreadyExists,
+                "both the staging and the final session directory exist",
+            ));
+        }
+        final_layout.staging()
+    } else if final_layout.root_dir.is_dir() {
+        final_layout
+    } else {
+        return Err(io::Error::new(
+            io::ErrorKind::NotFound,
+            "session directory not found",
+        ));
+    };
+
+    let options = read_options(&layout.options_path);
+    let outputs = options
+        .as_ref()
+        .and_then(|options| {
+            let [width, height] = options.capture.record_resolution;
+            video_outputs(&options.video, width, height).ok()
+        })
+        .unwrap_or_default();
+    let steps_per_frame = options
+        .as_ref()
+        .map_or(1, |options| options.timing.action_steps_per_frame.max(1) as u64);
+
+    let chunk_dirs = scan_chunk_dirs(&layout.data_dir)?;
+    let mut steps = 0;
+    let mut dropped_bytes = 0;
+    let mut video_remuxed = false;
+    if chunk_dirs.is_empty() {
+        let recovered = recover_chunk(&layout, ffmpeg_path, &outputs, steps_per_frame)?;
+        steps = recovered.steps;
+        dropped_bytes = recovered.dropped_bytes;
+        video_remuxed = recovered.video_remuxed;
+    } else {
+        let previous = read_chunk_index(&layout.chunk_index_path).unwrap_or_default();
+        let mut index = ChunkIndex::default();
+        for (chunk_index, chunk_dir) in chunk_dirs {
+            let chunk = layout.chunk(chunk_index);
+            let recovered = recover_chunk(&chunk, ffmpeg_path, &outputs, steps_per_frame)?;
+            steps += recovered.steps;
+            dropped_bytes += recovered.dropped_bytes;
+            video_remuxed |= recovered.video_remuxed;
+            let known = previous
+                .chunks
+                .iter()
+                .find(|entry| entry.index == chunk_index);
+            index.chunks.push(ChunkEntry {
+                index: chunk_index,
+                dir: chunk_dir_name(chunk_index),
+                video: known
+                    .map(|entry| entry.video.clone())
+                    .unwrap_or_else(|| detect_video_name(&chunk_dir)),
+                renditions: known
+                    .map(|entry| entry.renditions.clone())
+                    .unwrap_or_default(),
+                first_step: recovered.first_step,
+                steps: recovered.steps,
+                frames: recovered.frames,
+                complete: true,
+            });
+        }
+        write_chunk_index(&layout.chunk_index_path, &index)?;
+    }
+
+    mark_recovered(&layout.meta_path, steps)?;
+    File::open(&layout.meta_path)?.sync_all()?;
+
+    if staged {
+        seal_session_dir(&layout)?;
+    }
+    Ok(RecoveryReport {
+        session_dir: layout.root_dir.clone(),
+        steps,
+        dropped_bytes,
+        video_remuxed,
+    })
+}
+
+struct ChunkRecovery {
+    first_step: u64,
+    steps: u64,
+    frames: u64,
+    dropped_bytes: u64,
+    video_remuxed: bool,
+}
+
+// Repairs the video and JSONL files of one chunk (or of an unsegmented
+// session) and fsyncs them. Actions, compiled actions, thoughts and goals
+// keep the steps of the frames that every file, frames.jsonl and every video
+// output still hold; a missing file does not limit them. `frames` is the kept
+// frame count.
+fn recover_chunk(
+    layout: &SessionLayout,
+    ffmpeg_path: &Path,
+    outputs: &[VideoOutput],
+    steps_per_frame: u64,
+) -> io::Result<ChunkRecovery> {
+    let mut dropped_bytes = 0;
+    let aligned = [
+        &layout.actions_path,
+        &layout.compiled_path,
+        &layout.thoughts_path,
+        &layout.goals_path,
+    ];
+    let mut steps = None::<u64>;
+    for path in aligned {
+        if path.exists() {
+            dropped_bytes += truncate_partial_line(path)?;
+            let lines = line_offsets(path)?.len() as u64;
+            steps = Some(steps.map_or(lines, |steps| steps.min(lines)));
+        }
+    }
+    let mut steps = steps.unwrap_or(0);
+    for path in [&layout.auto_events_path, &layout.gauges_path, &layout.frames_path] {
+        if path.exists() {
+            dropped_bytes += truncate_partial_line(path)?;
+        }
+    }
+
+    // Frames that reached the videos, in frames.jsonl lines: a frame the
+    // encoder queue evicted takes a line but no video frame.
+    let timings = frame_timings(&layout.frames_path)?;
+    let videos = video_files(&layout.data_dir)?;
+    let mut video_frames = Vec::new();
+    for video in &videos {
+        video_frames.push(remux_video(ffmpeg_path, video)?);
+    }
+    for output in outputs {
+        let files = output.layout(layout);
+        match output.options.sink {
+            // Counted while remuxing.
+            VideoSinkKind::Ffmpeg => {}
+            VideoSinkKind::RawBgra if files.raw_video_path.exists() => {
+                let frame_bytes = output.width as u64 * output.height as u64 * 4;
+                let (frames, dropped) = raw_video_frames(&files.raw_video_path, frame_bytes)?;
+                dropped_bytes += dropped;
+                video_frames.push(Some(frames));
+            }
+            VideoSinkKind::ImageSequence if files.images_dir.is_dir() => {
+                video_frames.push(Some(image_frames(&files.images_dir)?));
+            }
+            _ => {}
+        }
+    }
+    let mut frames = steps / steps_per_frame;
+    if let Some(timings) = &timings {
+        frames = frames.min(timings.len() as u64);
+    }
+    for count in video_frames.into_iter().flatten() {
+        frames = frames.min(lines_backed_by(timings.as_ref(), count));
+    }
+    steps = steps.min(frames.saturating_mul(steps_per_frame));
+    steps -= steps % steps_per_frame;
+    let frames = steps / steps_per_frame;
+
+    for path in aligned {
+        if path.exists() {
+            dropped_bytes += truncate_lines(path, steps)?;
+        }
+    }
+    if layout.frames_path.exists() {
+        dropped_bytes += truncate_lines(&layout.frames_path, frames)?;
+    }
+    let first_step = match steps {
+        0 => 0,
+        _ => step_of_line(&layout.actions_path, 0)?.unwrap_or(0),
+    };
+    // Records past the last kept action step belong to the lost tail.
+    let end_step = match steps {
+        0 => 0,
+        n => step_of_line(&layout.actions_path, n - 1)?.map_or(n, |step| step + 1),
+    };
+    for path in [&layout.auto_events_path, &layout.gauges_path] {
+        if path.exists() {
+            dropped_bytes += drop_steps_from(path, end_step)?;
+        }
+    }
+
+    for path in aligned
+        .into_iter()
+        .chain([
+            &layout.auto_events_path,
+            &layout.gauges_path,
+            &layout.frames_path,
+        ])
+        .chain(&videos)
+    {
+        if path.exists() {
+            File::open(path)?.sync_all()?;
+        }
+    }
+    Ok(ChunkRecovery {
+        first_step,
+        steps,
+        frames,
+        dropped_bytes,
+        video_remuxed: !videos.is_empty(),
+    })
+}
+
+fn read_options(path: &Path) -> Option<Options> {
+    serde_json::from_str(&fs::read_to_string(path).ok()?).ok()
+}
+
+// The `video_frame` of each frames.jsonl line and the video frames evicted
+// before reaching the encoder.
+struct FrameTimings {
+    video_frames: Vec<u64>,
+    evicted: Vec<u64>,
+}
+
+impl FrameTimings {
+    fn len(&self) -> usize {
+        self.video_frames.len()
+    }
+}
+
+// None when there is no frames.jsonl.
+fn frame_timings(path: &Path) -> io::Result<Option<FrameTimings>> {
+    if !path.exists() {
+        return Ok(None);
+    }
+    let mut timings = FrameTimings {
+        video_frames: Vec::new(),
+        evicted: Vec::new(),
+    };
+    for line in BufReader::new(File::open(path)?).split(b'\n') {
+        let value = serde_json::from_slice::<Value>(&line?).unwrap_or(Value::Null);
+        let video_frame = value.get("video_frame").and_then(Value::as_u64);
+        timings
+            .video_frames
+            .push(video_frame.unwrap_or(timings.video_frames.len() as u64));
+        for evicted in value
+            .get("evicted_frames")
+            .and_then(Value::as_array)
+            .into_iter()
+            .flatten()
+        {
+            if let Some(frame) = evicted.get("video_frame").and_then(Value::as_u64) {
+                timings.evicted.push(frame);
+            }
+        }
+    }
+    timings.evicted.sort_unstable();
+    Ok(Some(timings))
+}
+
+// How many leading frames.jsonl lines a video of `video_frames` frames covers.
+fn lines_backed_by(timings: Option<&FrameTimings>, video_frames: u64) -> u64 {
+    let Some(timings) = timings else {
+        return video_frames;
+    };
+    let mut encoded = 0;
+    for (line, video_frame) in timings.video_frames.iter().enumerate() {
+        if timings.evicted.binary_search(video_frame).is_ok() {
+            continue;
+        }
+        if encoded == video_frames {
+            return line as u64;
+        }
+        encoded += 1;
+    }
+    timings.len() as u64
+}
+
+// Whole frames in a raw BGRA video and the bytes of a partly written last
+// frame, which is cut off.
+fn raw_video_frames(path: &Path, frame_bytes: u64) -> io::Result<(u64, u64)> {
+    let frames = fs::metadata(path)?.len() / frame_bytes.max(1);
+    let dropped = truncate_to(path, frames * frame_bytes)?;
+    Ok((frames, dropped))
+}
+
+// Images are numbered from 000000; counting stops at the first gap.
+fn image_frames(dir: &Path) -> io::Result<u64> {
+    let mut names = Vec::new();
+    for entry in fs::read_dir(dir)? {
+        let name = entry?.file_name().to_string_lossy().to_string();
+        if let Some((index, _)) = name.split_once('.')
+            && let Ok(index) = index.parse::<u64>()
+        {
+            names.push(index);
+        }
+    }
+    names.sort_unstable();
+    Ok(names
+        .iter()
+        .enumerate()
+        .take_while(|(position, index)| *position as u64 == **index)
+        .count() as u64)
+}
+
+// `video.<ext>` and every rendition's `video_<name>.<ext>`.
+fn video_files(dir: &Path) -> io::Result<Vec<PathBuf>> {
+    let mut videos = Vec::new();
+    for entry in fs::read_dir(dir)? {
+        let path = entry?.path();
+        let name = path
+            .file_name()
+            .map(|name| name.to_string_lossy().to_string())
+            .unwrap_or_default();
+        let is_video = name.starts_with("video")
+            && (name.ends_with(".mp4") || name.ends_with(".mkv"))
+            && !name.contains(".remux.");
+        if is_video && path.is_file() {
+            videos.push(path);
+        }
+    }
+    videos.sort();
+    Ok(videos)
+}
+
+fn detect_video_name(chunk_dir: &Path) -> String {
+    ["video.mp4", "video.mkv", "images", "video.bgra"]
+        .iter()
+        .find(|name| chunk_dir.join(name).exists())
+        .unwrap_or(&"video.mp4")
+        .to_string()
+}
+
+fn step_of_line(path: &Path, line_index: u64) -> io::Result<Option<u64>> {
+    let reader = BufReader::new(File::open(path)?);
+    match reader.split(b'\n').nth(line_index as usize) {
+        Some(line) => Ok(serde_json::from_slice::<Value>(&line?)
+            .ok()
+            .and_then(|value| value.get("step_index").and_then(Value::as_u64))),
+        None => Ok(None),
+    }
+}
+
+// Byte offset just past each complete line.
+fn line_offsets(path: &Path) -> io::Result<Vec<u64>> {
+    let mut reader = BufReader::new(File::open(path)?);
+    let mut offsets = Vec::new();
+    let mut offset = 0u64;
+    let mut buf = Vec::new();
+    loop {
+        buf.clear();
+        let read = reader.read_until(b'\n', &mut buf)?;
+        if read == 0 {
+            break;
+        }
+        offset += read as u64;
+        if buf.last() == Some(&b'\n') {
+            offsets.push(offset);
+        }
+    }
+    Ok(offsets)
+}
+
+fn truncate_to(path: &Path, len: u64) -> io::Result<u64> {
+    let current = fs::metadata(path)?.len();
+    if len >= current {
+        return Ok(0);
+    }
+    OpenOptions::new().write(true).open(path)?.set_len(len)?;
+    Ok(current - len)
+}
+
+fn truncate_partial_line(path: &Path) -> io::Result<u64> {
+    let end = line_offsets(path)?.last().copied().unwrap_or(0);
+    truncate_to(path, end)
+}
+
+fn truncate_lines(path: &Path, lines: u64) -> io::Result<u64> {
+    let offsets = line_offsets(path)?;
+    let end = match lines {
+        0 => 0,
+        n => offsets
+            .get(n as usize - 1)
+            .copied()
+            .unwrap_or_else(|| offsets.last().copied().unwrap_or(0)),
+    };
+    truncate_to(path, end)
+}
+
+// Cuts a step-sorted JSONL file at the first record whose `step_index` is at
+// or past `end_step`.
+fn drop_steps_from(path: &Path, end_step: u64) -> io::Result<u64> {
+    let reader = BufReader::new(File::open(path)?);
+    let mut end = 0u64;
+    for line in reader.split(b'\n') {
+        let line = line?;
+        let step = serde_json::from_slice::<Value>(&line)
+            .ok()
+            .and_then(|value| value.get("step_index").and_then(Value::as_u64));
+        match step {
+            Some(step) if step < end_step => end += line.len() as u64 + 1,
+            _ => break,
+        }
+    }
+    truncate_to(path, end)
+}
+
+// Rewrites the video with a proper index and returns its frame count, as
+// reported by ffmpeg's progress output.
+fn remux_video(ffmpeg_path: &Path, video_path: &Path) -> io::Result<Option<u64>> {
+    let ext = video_path
+        .extension()
+        .and_then(|ext| ext.to_str())
+        .unwrap_or("mp4");
+    let tmp_path = video_path.with_extension(format!("remux.{}", ext));
+    let mut cmd = Command::new(ffmpeg_path);
+    cmd.arg("-y")
+        .arg("-v")
+        .arg("error")
+        .arg("-nostats")
+        .arg("-progress")
+        .arg("pipe:1")
+        .arg("-i")
+        .arg(video_path)
+        .arg("-c")
+        .arg("copy");
+    if ext == "mp4" {
+        cmd.arg("-movflags").arg("+faststart");
+    }
+    let output = cmd
+        .arg(&tmp_path)
+        .stdin(Stdio::null())
+        .stderr(Stdio::inherit())
+        .output()?;
+    if !output.status.success() || !tmp_path.exists() {
+        let _ = fs::remove_file(&tmp_path);
+        return Err(io::Error::other(format!(
+            "ffmpeg remux exited with {}",
+            output.status
+        )));
+    }
+    fs::rename(&tmp_path, video_path)?;
+    Ok(String::from_utf8_lossy(&output.stdout)
+        .lines()
+        .rev()
+        .find_map(|line| line.trim().strip_prefix("frame=")?.trim().parse().ok()))
+}
+
+fn mark_recovered(meta_path: &Path, steps: u64) -> io::Result<()> {
+    let mut meta = match fs::read_to_string(meta_path) {
+        Ok(text) => serde_json::from_str(&text)
+            .map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err))?,
+        Err(err) if err.kind() == io::ErrorKind::NotFound => Value::Object(Default::default()),
+        Err(err) => return Err(err),
+    };
+    if let Value::Object(map) = &mut meta {
+        map.insert("recovered".to_string(), Value::Bool(true));
+        map.insert("recovered_steps".to_string(), Value::from(steps));
+    }
+    let text = serde_json::to_string(&meta).map_err(io::Error::other)?;
+    fs::write(meta_path, text)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn truncates_partial_lines_and_aligns_steps() {
+        let root = std::env::temp_dir().join(format!("app_recover_{}", std::process::id()));
+        let _ = fs::remove_dir_all(&root);
+        let layout = SessionLayout::new(&root, "crashed").staging();
+        fs::create_dir_all(&layout.temp_dir).unwrap();
+        let step = |i: u64| format!("{{\"step_index\":{}}}\n", i);
+        fs::write(
+            &layout.actions_path,
+            format!("{}{}{}{{\"step_in", step(0), step(1), step(2)),
+        )
+        .unwrap();
+        fs::write(&layout.compiled_path, "a\nb\n").unwrap();
+        fs::write(&layout.thoughts_path, "t\nt\nt\n").unwrap();
+        fs::write(&layout.goals_path, "g\ng\ng\n").unwrap();
+        fs::write(
+            &layout.auto_events_path,
+            format!("{}{}", step(1), step(2)),
+        )
+        .unwrap();
+        fs::write(&layout.meta_path, "{\"fps\":5}").unwrap();
+
+        let report = recover_session(&root, "crashed", Path::new("ffmpeg")).unwrap();
+        let final_layout = SessionLayout::new(&root, "crashed");
+        assert_eq!(report.steps, 2);
+        assert!(!report.video_remuxed);
+        assert_eq!(report.session_dir, final_layout.root_dir);
+        assert!(!final_layout.temp_dir.exists());
+        assert!(final_layout.complete_marker_path().exists());
+        assert_eq!(
+            fs::read_to_string(&final_layout.actions_path).unwrap(),
+            format!("{}{}", step(0), step(1))
+        );
+        assert_eq!(fs::read_to_string(&final_layout.thoughts_path).unwrap(), "t\nt\n");
+        assert_eq!(
+            fs::read_to_string(&final_layout.auto_events_path).unwrap(),
+            step(1)
+        );
+        let meta: Value =
+            serde_json::from_str(&fs::read_to_string(&final_layout.meta_path).unwrap()).unwrap();
+        assert_eq!(meta["recovered"], Value::Bool(true));
+        assert_eq!(meta["fps"], Value::from(5));
+        fs::remove_dir_all(&root).unwrap();
+    }
+
+    #[test]
+    fn recovers_each_chunk_and_rebuilds_index() {
+        let root = std::env::temp_dir().join(format!("app_recover_chunks_{}", std::process::id()));
+        let _ = fs::remove_dir_all(&root);
+        let layout = SessionLayout::new(&root, "chunked").staging();
+        let step = |i: u64| format!("{{\"step_index\":{}}}\n", i);
+        let first = layout.chunk(0);
+        let second = layout.chunk(1);
+        for (chunk, steps) in [(&first, 0..2u64), (&second, 2..4u64)] {
+            fs::create_dir_all(&chunk.data_dir).unwrap();
+            let lines: String = steps.clone().map(step).collect();
+            for path in [
+                &chunk.actions_path,
+                &chunk.compiled_path,
+                &chunk.thoughts_path,
+                &chunk.goals_path,
+                &chunk.frames_path,
+            ] {
+                fs::write(path, &lines).unwrap();
+            }
+        }
+        // The crash hit while the second chunk was writing step 3.
+        fs::write(&second.goals_path, step(2)).unwrap();
+        fs::write(&second.auto_events_path, format!("{}{}", step(2), step(3))).unwrap();
+
+        let report = recover_session(&root, "chunked", Path::new("ffmpeg")).unwrap();
+        assert_eq!(report.steps, 3);
+        let final_layout = SessionLayout::new(&root, "chunked");
+        let index = read_chunk_index(&final_layout.chunk_index_path).unwrap();
+        let summary: Vec<(u64, u64, u64)> = index
+            .chunks
+            .iter()
+            .map(|entry| (entry.first_step, entry.steps, entry.frames))
+            .collect();
+        assert_eq!(summary, vec![(0, 2, 2), (2, 1, 1)]);
+        let chunks = final_layout.chunk_layouts().unwrap();
+        assert_eq!(fs::read_to_string(&chunks[1].actions_path).unwrap(), step(2));
+        assert_eq!(fs::read_to_string(&chunks[1].auto_events_path).unwrap(), step(2));
+        assert!(final_layout.complete_marker_path().exists());
+        fs::remove_dir_all(&root).unwrap();
+    }
+
+    fn write_options(layout: &SessionLayout, edit: impl FnOnce(&mut Options)) {
+        let mut options = Options::default_v1();
+        edit(&mut options);
+        fs::write(&layout.options_path, serde_json::to_string(&options).unwrap()).unwrap();
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn cuts_steps_to_the_frames_in_the_video() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let root = std::env::temp_dir().join(format!("app_recover_video_{}", std::process::id()));
+        let _ = fs::remove_dir_all(&root);
+        let layout = SessionLayout::new(&root, "short_video").staging();
+        fs::create_dir_all(&layout.temp_dir).unwrap();
+        // Copies the input and reports two frames, like a remux of a video
+        // that lost its last frame.
+        let ffmpeg = root.join("ffmpeg");
+        fs::write(
+            &ffmpeg,
+            "#!/bin/sh\nwhile [ $# -gt 1 ]; do [ \"$1\" = -i ] && in=$2; shift; done\ncp \"$in\" \"$1\"\necho frame=2\necho progress=end\n",
+        )
+        .unwrap();
+        fs::set_permissions(&ffmpeg, fs::Permissions::from_mode(0o755)).unwrap();
+        write_options(&layout, |options| options.timing.action_steps_per_frame = 2);
+        let step = |i: u64| format!("{{\"step_index\":{}}}\n", i);
+        let steps: String = (0..6).map(step).collect();
+        for path in [&layout.actions_path, &layout.compiled_path, &layout.goals_path] {
+            fs::write(path, &steps).unwrap();
+        }
+        // No thoughts file: it does not limit the others.
+        fs::write(
+            &layout.frames_path,
+            (0..3)
+                .map(|frame| format!("{{\"step_index\":{},\"video_frame\":{}}}\n", frame * 2, frame))
+                .collect::<String>(),
+        )
+        .unwrap();
+        fs::write(&layout.gauges_path, format!("{}{}", step(2), step(4))).unwrap();
+        fs::write(&layout.video_path, b"video").unwrap();
+
+        let report = recover_session(&root, "short_video", &ffmpeg).unwrap();
+        assert_eq!(report.steps, 4);
+        assert!(report.video_remuxed);
+        let final_layout = SessionLayout::new(&root, "short_video");
+        assert_eq!(
+            fs::read_to_string(&final_layout.actions_path).unwrap(),
+            (0..4).map(step).collect::<String>()
+        );
+        assert_eq!(line_offsets(&final_layout.frames_path).unwrap().len(), 2);
+        assert_eq!(fs::read_to_string(&final_layout.gauges_path).unwrap(), step(2));
+        assert_eq!(fs::read(&final_layout.video_path).unwrap(), b"video");
+        fs::remove_dir_all(&root).unwrap();
+    }
+
+    #[test]
+    fn evicted_frames_do_not_count_against_a_raw_video() {
+        let root = std::env::temp_dir().join(format!("app_recover_raw_{}", std::process::id()));
+        let _ = fs::remove_dir_all(&root);
+        let layout = SessionLayout::new(&root, "raw").staging();
+        fs::create_dir_all(&layout.temp_dir).unwrap();
+        write_options(&layout, |options| {
+            options.capture.record_resolution = [2, 2];
+            options.video.sink = VideoSinkKind::RawBgra;
+        });
+        let step = |i: u64| format!("{{\"step_index\":{}}}\n", i);
+        let steps: String = (0..4).map(step).collect();
+        for path in [
+            &layout.actions_path,
+            &layout.compiled_path,
+            &layout.thoughts_path,
+            &layout.goals_path,
+        ] {
+            fs::write(path, &steps).unwrap();
+        }
+        // Frame 1 was evicted by the queue; frames 0 and 2 reached the video,
+        // frame 3 only partly.
+        fs::write(
+            &layout.frames_path,
+            concat!(
+                "{\"step_index\":0,\"video_frame\":0}\n",
+                "{\"step_index\":1,\"video_frame\":1}\n",
+                "{\"step_index\":2,\"video_frame\":2,\"evicted_frames\":[{\"step_index\":1,\"video_frame\":1}]}\n",
+                "{\"step_index\":3,\"video_frame\":3}\n",
+            ),
+        )
+        .unwrap();
+        fs::write(&layout.raw_video_path, [0u8; 16 * 2 + 5]).unwrap();
+
+        let report = recover_session(&root, "raw", Path::new("ffmpeg-not-needed")).unwrap();
+        assert_eq!(report.steps, 3);
+        let last_timing = "{\"step_index\":3,\"video_frame\":3}\n";
+        assert_eq!(
+            report.dropped_bytes,
+            5 + step(3).len() as u64 * 4 + last_timing.len() as u64
+        );
+        let final_layout = SessionLayout::new(&root, "raw");
+        assert_eq!(fs::read(&final_layout.raw_video_path).unwrap().len(), 32);
+        assert_eq!(line_offsets(&final_layout.frames_path).unwrap().len(), 3);
+        fs::remove_dir_all(&root).unwrap();
+    }
+}
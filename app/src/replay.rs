@@ -0,0 +1,88 @@
+use std::fs::{self, File};
+use std::io::{self, BufRead, BufReader, BufWriter, Write};
+use std::path::Path;
+
+use collector_core::{action_step_ms, ActionSnapshot, Options};
+use events::{AutoEvent, AutoEventKind, RuleEngine, RuleSet};
+use serde::de::DeserializeOwned;
+use writer::SessionLayout;
+
+// Re-runs `rules` over a finished session and rewrites auto_events.jsonl with
+// the original detector events plus the new rule events. Returns the number of
+// rule events written. Segmented sessions are replayed as one stream; each
+// rule event goes to the chunk holding its step.
+pub fn replay_rules(layout: &SessionLayout, rules: RuleSet) -> io::Result<usize> {
+    let options: Options = serde_json::from_str(&fs::read_to_string(&layout.options_path)?)
+        .map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err))?;
+    let action_step_ms =
+        action_step_ms(options.capture.fps, options.timing.action_steps_per_frame);
+    let chunks = layout.chunk_layouts()?;
+    let mut snapshots: Vec<ActionSnapshot> = Vec::new();
+    let mut chunk_events: Vec<Vec<AutoEvent>> = Vec::new();
+    let mut chunk_starts = Vec::new();
+    for chunk in &chunks {
+        let chunk_snapshots: Vec<ActionSnapshot> = read_jsonl(&chunk.actions_path)?;
+        let previous = chunk_starts.last().copied().unwrap_or(0);
+        chunk_starts.push(
+            chunk_snapshots
+                .first()
+                .map(|snapshot| snapshot.step_index)
+                .unwrap_or(previous),
+        );
+        snapshots.extend(chunk_snapshots);
+        chunk_events.push(if chunk.auto_events_path.exists() {
+            read_jsonl(&chunk.auto_events_path)?
+        } else {
+            Vec::new()
+        });
+    }
+    let auto_events: Vec<AutoEvent> = chunk_events.iter().flatten().cloned().collect();
+
+    let mut engine = RuleEngine::new(rules, action_step_ms);
+    let derived = engine.replay(&snapshots, &auto_events);
+    let count = derived.len();
+    for events in &mut chunk_events {
+        events.retain(|event| !matches!(event.kind, AutoEventKind::Rule { .. }));
+    }
+    for event in derived {
+        let chunk = chunk_starts
+            .iter()
+            .rposition(|start| *start <= event.step_index)
+            .unwrap_or(0);
+        chunk_events[chunk].push(event);
+    }
+
+    for (chunk, mut merged) in chunks.iter().zip(chunk_events) {
+        merged.sort_by_key(|event| event.step_index);
+        write_auto_events(&chunk.auto_events_path, &merged)?;
+    }
+    Ok(count)
+}
+
+fn write_auto_events(path: &Path, events: &[AutoEvent]) -> io::Result<()> {
+    let tmp_path = path.with_extension("jsonl.tmp");
+    {
+        let mut out = BufWriter::new(File::create(&tmp_path)?);
+        for event in events {
+            serde_json::to_writer(&mut out, event).map_err(io::Error::other)?;
+            out.write_all(b"\n")?;
+        }
+        out.flush()?;
+    }
+    fs::rename(&tmp_path, path)
+}
+
+fn read_jsonl<T: DeserializeOwned>(path: &Path) -> io::Result<Vec<T>> {
+    let reader = BufReader::new(File::open(path)?);
+    let mut items = Vec::new();
+    for line in reader.lines() {
+        let line = line?;
+        if line.trim().is_empty() {
+            continue;
+        }
+        let item = serde_json::from_str(&line)
+            .map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err))?;
+        items.push(item);
+    }
+    Ok(items)
+}
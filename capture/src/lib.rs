@@ -2,6 +2,9 @@ use std::io;
 
 use collector_core::{CaptureOptions, FrameRecord};
 
+pub mod mask;
+pub mod overlay;
+pub mod resize;
 mod wgc;
 
 pub trait FrameSource {
@@ -17,6 +20,12 @@ impl WgcCapture {
         let inner = wgc::WgcCaptureImpl::new(&options, target_hwnd)?;
         Ok(Self { inner })
     }
+
+    // Attach the unscaled source image to every frame, for renditions that
+    // resize from the source.
+    pub fn keep_source_frames(&mut self, keep: bool) {
+        self.inner.set_keep_source(keep);
+    }
 }
 
 impl FrameSource for WgcCapture {
@@ -0,0 +1,263 @@
+use collector_core::MaskStyle;
+
+use crate::resize::{Placement, Rect};
+
+// Moves `[x, y, width, height]` from one output of a source to another:
+// `from` places the source in the frame the rect was drawn on, `to` in the
+// `to_w` x `to_h` target. Letterbox bars and crops of either side are
+// accounted for. Edges round outward so a moved mask never uncovers a pixel
+// the original covered; the result is clipped to the target. None when
+// nothing is left.
+pub fn map_mask_rect(
+    rect: [u32; 4],
+    from: &Placement,
+    to: &Placement,
+    to_w: u32,
+    to_h: u32,
+) -> Option<Rect> {
+    let [x, y, width, height] = rect.map(|value| value as f32);
+    let corner = |x: f32, y: f32| {
+        let (x, y) = from.unmap(x, y);
+        to.map(x, y)
+    };
+    let (left, top) = corner(x, y);
+    let (right, bottom) = corner(x + width, y + height);
+    let clip = |value: f32, to: u32| value.clamp(0.0, to as f32) as u32;
+    let (left, top) = (clip(left.floor(), to_w), clip(top.floor(), to_h));
+    let (right, bottom) = (clip(right.ceil(), to_w), clip(bottom.ceil(), to_h));
+    (width > 0.0 && height > 0.0 && right > left && bottom > top).then_some(Rect {
+        x: left,
+        y: top,
+        width: right - left,
+        height: bottom - top,
+    })
+}
+
+// Hides `rect` of a BGRA frame. Blur and pixelate only read pixels inside the
+// rectangle, so nothing outside it bleeds in or out. `scale` shrinks the blur
+// radius and pixel block for smaller renditions.
+pub fn apply_mask(frame: &mut [u8], width: u32, height: u32, rect: Rect, style: MaskStyle, scale: f32) {
+    if frame.len() < width as usize * height as usize * 4
+        || rect.width == 0
+        || rect.height == 0
+        || rect.x + rect.width > width
+        || rect.y + rect.height > height
+    {
+        return;
+    }
+    let scaled = |value: u32| ((value as f32 * scale).round() as u32).max(1);
+    match style {
+        MaskStyle::Fill { color: [r, g, b] } => {
+            for_each_row(frame, width, rect, |row| {
+                for pixel in row.chunks_exact_mut(4) {
+                    pixel.copy_from_slice(&[b, g, r, 255]);
+                }
+            });
+        }
+        MaskStyle::Pixelate { block } => pixelate(frame, width, rect, scaled(block)),
+        MaskStyle::Blur { radius } => {
+            // Three box passes approximate a gaussian.
+            let radius = scaled(radius);
+            for _ in 0..3 {
+                box_blur(frame, width, rect, radius);
+            }
+        }
+    }
+}
+
+fn for_each_row(frame: &mut [u8], width: u32, rect: Rect, mut visit: impl FnMut(&mut [u8])) {
+    let stride = width as usize * 4;
+    for y in rect.y..rect.y + rect.height {
+        let start = y as usize * stride + rect.x as usize * 4;
+        visit(&mut frame[start..start + rect.width as usize * 4]);
+    }
+}
+
+fn pixelate(frame: &mut [u8], width: u32, rect: Rect, block: u32) {
+    let stride = width as usize * 4;
+    for cell_y in (rect.y..rect.y + rect.height).step_by(block as usize) {
+        let cell_h = block.min(rect.y + rect.height - cell_y);
+        for cell_x in (rect.x..rect.x + rect.width).step_by(block as usize) {
+            let cell_w = block.min(rect.x + rect.width - cell_x);
+            let mut sum = [0u64; 3];
+            for y in cell_y..cell_y + cell_h {
+                for x in cell_x..cell_x + cell_w {
+                    let at = y as usize * stride + x as usize * 4;
+                    for (channel, total) in sum.iter_mut().enumerate() {
+                        *total += frame[at + channel] as u64;
+                    }
+                }
+            }
+            let count = (cell_w * cell_h) as u64;
+            let average = sum.map(|total| ((total + count / 2) / count) as u8);
+            for y in cell_y..cell_y + cell_h {
+                for x in cell_x..cell_x + cell_w {
+                    let at = y as usize * stride + x as usize * 4;
+                    frame[at..at + 3].copy_from_slice(&average);
+                }
+            }
+        }
+    }
+}
+
+// One horizontal and one vertical running-sum pass, with edges clamped to
+// the rectangle.
+fn box_blur(frame: &mut [u8], width: u32, rect: Rect, radius: u32) {
+    let stride = width as usize * 4;
+    let mut line = Vec::new();
+    for y in rect.y..rect.y + rect.height {
+        let start = y as usize * stride + rect.x as usize * 4;
+        line.clear();
+        line.extend_from_slice(&frame[start..start + rect.width as usize * 4]);
+        blur_line(&line, &mut frame[start..], 4, rect.width as usize, radius as usize);
+    }
+    for x in rect.x..rect.x + rect.width {
+        let start = rect.y as usize * stride + x as usize * 4;
+        line.clear();
+        for y in 0..rect.height as usize {
+            line.extend_from_slice(&frame[start + y * stride..start + y * stride + 4]);
+        }
+        blur_line(&line, &mut frame[start..], stride, rect.height as usize, radius as usize);
+    }
+}
+
+// Blurs `len` BGRA pixels of `src` (packed) into `dst`, whose pixels are
+// `step` bytes apart. Alpha is left alone.
+fn blur_line(src: &[u8], dst: &mut [u8], step: usize, len: usize, radius: usize) {
+    let at = |index: isize| (index.clamp(0, len as isize - 1) as usize) * 4;
+    let window = (radius * 2 + 1) as u32;
+    let mut sum = [0u32; 3];
+    for offset in -(radius as isize)..=radius as isize {
+        for (channel, total) in sum.iter_mut().enumerate() {
+            *total += src[at(offset) + channel] as u32;
+        }
+    }
+    for index in 0..len {
+        for channel in 0..3 {
+            dst[index * step + channel] = ((sum[channel] + window / 2) / window) as u8;
+        }
+        let leaving = at(index as isize - radius as isize);
+        let entering = at(index as isize + radius as isize + 1);
+        for (channel, total) in sum.iter_mut().enumerate() {
+            *total = *total + src[entering + channel] as u32 - src[leaving + channel] as u32;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn frame(width: u32, height: u32, pixel: impl Fn(u32, u32) -> [u8; 4]) -> Vec<u8> {
+        let mut out = Vec::with_capacity((width * height * 4) as usize);
+        for y in 0..height {
+            for x in 0..width {
+                out.extend_from_slice(&pixel(x, y));
+            }
+        }
+        out
+    }
+
+    fn pixel_at(data: &[u8], width: u32, x: u32, y: u32) -> [u8; 4] {
+        let at = ((y * width + x) * 4) as usize;
+        data[at..at + 4].try_into().unwrap()
+    }
+
+    fn rect(x: u32, y: u32, width: u32, height: u32) -> Rect {
+        Rect {
+            x,
+            y,
+            width,
+            height,
+        }
+    }
+
+    // Checkerboard, so any pixel changed by a mask is visible.
+    fn checker(x: u32, y: u32) -> [u8; 4] {
+        if (x + y).is_multiple_of(2) {
+            [0, 0, 0, 255]
+        } else {
+            [200, 200, 200, 255]
+        }
+    }
+
+    #[test]
+    fn fill_covers_exactly_the_rect() {
+        let mut data = frame(8, 8, checker);
+        let style = MaskStyle::Fill {
+            color: [10, 20, 30],
+        };
+        apply_mask(&mut data, 8, 8, rect(2, 3, 4, 2), style, 1.0);
+        for y in 0..8 {
+            for x in 0..8 {
+                let inside = (2..6).contains(&x) && (3..5).contains(&y);
+                let expected = if inside { [30, 20, 10, 255] } else { checker(x, y) };
+                assert_eq!(pixel_at(&data, 8, x, y), expected, "({}, {})", x, y);
+            }
+        }
+    }
+
+    #[test]
+    fn pixelate_averages_cells_including_partial_ones() {
+        let mut data = frame(5, 2, |x, _| [x as u8 * 10, 0, 0, 255]);
+        apply_mask(&mut data, 5, 2, rect(0, 0, 5, 2), MaskStyle::Pixelate { block: 2 }, 1.0);
+        let row: Vec<u8> = (0..5).map(|x| pixel_at(&data, 5, x, 1)[0]).collect();
+        assert_eq!(row, vec![5, 5, 25, 25, 40]);
+        assert_eq!(pixel_at(&data, 5, 0, 0), [5, 0, 0, 255]);
+    }
+
+    #[test]
+    fn blur_flattens_detail_and_stays_inside_the_rect() {
+        let mut data = frame(16, 16, checker);
+        apply_mask(&mut data, 16, 16, rect(4, 4, 8, 8), MaskStyle::Blur { radius: 2 }, 1.0);
+        for y in 0..16 {
+            for x in 0..16 {
+                let value = pixel_at(&data, 16, x, y);
+                if (4..12).contains(&x) && (4..12).contains(&y) {
+                    assert!((80..=120).contains(&value[0]), "({}, {}) {:?}", x, y, value);
+                    assert_eq!(value[3], 255);
+                } else {
+                    assert_eq!(value, checker(x, y));
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn uniform_area_is_unchanged_by_blur() {
+        let mut data = frame(6, 6, |_, _| [50, 60, 70, 255]);
+        let before = data.clone();
+        apply_mask(&mut data, 6, 6, rect(0, 0, 6, 6), MaskStyle::Blur { radius: 4 }, 1.0);
+        assert_eq!(data, before);
+    }
+
+    #[test]
+    fn mapped_rects_round_outward_and_clip() {
+        use crate::resize::placement;
+        use collector_core::ResizeMode;
+
+        let record = placement(1280, 720, 1280, 720, ResizeMode::Stretch);
+        let same = |rect| map_mask_rect(rect, &record, &record, 1280, 720);
+        assert_eq!(same([0, 600, 300, 120]), Some(rect(0, 600, 300, 120)));
+        assert_eq!(same([1200, 700, 500, 500]), Some(rect(1200, 700, 80, 20)));
+        assert_eq!(same([2000, 0, 10, 10]), None);
+        assert_eq!(same([0, 0, 0, 10]), None);
+        // 1280x720 -> 854x480: edges round outward.
+        let small = placement(1280, 720, 854, 480, ResizeMode::Stretch);
+        assert_eq!(map_mask_rect([1, 1, 3, 3], &record, &small, 854, 480), Some(rect(0, 0, 3, 3)));
+        // A 4:3 letterboxed rendition puts the 16:9 image between bars.
+        let boxed = placement(1280, 720, 640, 480, ResizeMode::BLACK_LETTERBOX);
+        assert_eq!(
+            map_mask_rect([0, 0, 640, 360], &record, &boxed, 640, 480),
+            Some(rect(0, 60, 320, 180))
+        );
+    }
+
+    #[test]
+    fn out_of_bounds_rect_is_ignored() {
+        let mut data = frame(4, 4, checker);
+        let before = data.clone();
+        apply_mask(&mut data, 4, 4, rect(2, 2, 4, 4), MaskStyle::Fill { color: [0, 0, 0] }, 1.0);
+        assert_eq!(data, before);
+    }
+}
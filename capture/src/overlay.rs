@@ -0,0 +1,268 @@
+use std::fs::File;
+use std::io::{self, BufReader};
+use std::path::Path;
+
+// Largest user-supplied cursor image, per side.
+const MAX_SPRITE_SIZE: u32 = 256;
+
+// Classic arrow: `B` outline, `W` fill, `.` transparent. Hotspot at the tip.
+const ARROW: [&str; 19] = [
+    "B...........",
+    "BB..........",
+    "BWB.........",
+    "BWWB........",
+    "BWWWB.......",
+    "BWWWWB......",
+    "BWWWWWB.....",
+    "BWWWWWWB....",
+    "BWWWWWWWB...",
+    "BWWWWWWWWB..",
+    "BWWWWWWWWWB.",
+    "BWWWWWWBBBBB",
+    "BWWWBWWB....",
+    "BWWBBWWB....",
+    "BWB..BWWB...",
+    "BB...BWWB...",
+    "B.....BWWB..",
+    "......BWWB..",
+    ".......BB...",
+];
+
+const CROSSHAIR_RADIUS: i32 = 7;
+
+// A BGRA cursor image with straight (non-premultiplied) alpha.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CursorSprite {
+    pub width: u32,
+    pub height: u32,
+    // The pixel that sits on the cursor position.
+    pub hotspot: [u32; 2],
+    pub pixels: Vec<u8>,
+}
+
+impl CursorSprite {
+    pub fn arrow() -> Self {
+        let width = ARROW[0].len() as u32;
+        let mut pixels = Vec::with_capacity(ARROW.len() * width as usize * 4);
+        for row in ARROW {
+            for cell in row.bytes() {
+                pixels.extend_from_slice(match cell {
+                    b'B' => &[0, 0, 0, 255],
+                    b'W' => &[255, 255, 255, 255],
+                    _ => &[0, 0, 0, 0],
+                });
+            }
+        }
+        Self {
+            width,
+            height: ARROW.len() as u32,
+            hotspot: [0, 0],
+            pixels,
+        }
+    }
+
+    // A white plus outlined in black, centered on the hotspot.
+    pub fn crosshair() -> Self {
+        let size = (CROSSHAIR_RADIUS * 2 + 1) as u32;
+        let mut pixels = Vec::with_capacity((size * size * 4) as usize);
+        for y in -CROSSHAIR_RADIUS..=CROSSHAIR_RADIUS {
+            for x in -CROSSHAIR_RADIUS..=CROSSHAIR_RADIUS {
+                let (dx, dy) = (x.abs(), y.abs());
+                let near = dx.min(dy);
+                let far = dx.max(dy);
+                pixels.extend_from_slice(if near == 0 && far < CROSSHAIR_RADIUS {
+                    &[255, 255, 255, 255]
+                } else if near <= 1 {
+                    &[0, 0, 0, 255]
+                } else {
+                    &[0, 0, 0, 0]
+                });
+            }
+        }
+        Self {
+            width: size,
+            height: size,
+            hotspot: [CROSSHAIR_RADIUS as u32; 2],
+            pixels,
+        }
+    }
+
+    // Any 8- or 16-bit PNG; grayscale, palette and RGB images are expanded.
+    pub fn load_png(path: &Path, hotspot: [u32; 2]) -> io::Result<Self> {
+        let invalid = |message: String| {
+            io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("cursor image {}: {}", path.display(), message),
+            )
+        };
+        let file = File::open(path).map_err(|err| {
+            io::Error::new(err.kind(), format!("cursor image {}: {}", path.display(), err))
+        })?;
+        let mut decoder = png::Decoder::new(BufReader::new(file));
+        decoder.set_transformations(png::Transformations::normalize_to_color8());
+        let mut reader = decoder.read_info().map_err(|err| invalid(err.to_string()))?;
+        let mut data = vec![0; reader.output_buffer_size()];
+        let info = reader
+            .next_frame(&mut data)
+            .map_err(|err| invalid(err.to_string()))?;
+        let (width, height) = (info.width, info.height);
+        if width == 0 || height == 0 || width > MAX_SPRITE_SIZE || height > MAX_SPRITE_SIZE {
+            return Err(invalid(format!(
+                "{}x{} is outside 1..={} pixels per side",
+                width, height, MAX_SPRITE_SIZE
+            )));
+        }
+        if hotspot[0] >= width || hotspot[1] >= height {
+            return Err(invalid(format!(
+                "hotspot {:?} is outside the {}x{} image",
+                hotspot, width, height
+            )));
+        }
+        let channels = info.color_type.samples();
+        let mut pixels = Vec::with_capacity((width * height * 4) as usize);
+        for row in data[..info.buffer_size()].chunks_exact(info.line_size) {
+            for pixel in row[..width as usize * channels].chunks_exact(channels) {
+                let [r, g, b, a] = match info.color_type {
+                    png::ColorType::Rgba => [pixel[0], pixel[1], pixel[2], pixel[3]],
+                    png::ColorType::Rgb => [pixel[0], pixel[1], pixel[2], 255],
+                    png::ColorType::GrayscaleAlpha => [pixel[0], pixel[0], pixel[0], pixel[1]],
+                    png::ColorType::Grayscale => [pixel[0], pixel[0], pixel[0], 255],
+                    png::ColorType::Indexed => {
+                        return Err(invalid("palette was not expanded".to_string()));
+                    }
+                };
+                pixels.extend_from_slice(&[b, g, r, a]);
+            }
+        }
+        Ok(Self {
+            width,
+            height,
+            hotspot,
+            pixels,
+        })
+    }
+
+    // Alpha-blends the sprite into a BGRA frame with its hotspot at (x, y),
+    // scaled by `scale` (nearest neighbour) and clipped to the frame. The
+    // frame's alpha channel is left as is.
+    pub fn draw(&self, frame: &mut [u8], width: u32, height: u32, x: f32, y: f32, scale: f32) {
+        if scale <= 0.0 || frame.len() < width as usize * height as usize * 4 {
+            return;
+        }
+        let left = (x - self.hotspot[0] as f32 * scale).round() as i64;
+        let top = (y - self.hotspot[1] as f32 * scale).round() as i64;
+        let drawn_w = ((self.width as f32 * scale).round() as i64).max(1);
+        let drawn_h = ((self.height as f32 * scale).round() as i64).max(1);
+        let x_range = left.max(0)..(left + drawn_w).min(width as i64);
+        for frame_y in top.max(0)..(top + drawn_h).min(height as i64) {
+            let sprite_y = (((frame_y - top) as f32 / scale) as u32).min(self.height - 1);
+            for frame_x in x_range.clone() {
+                let sprite_x = (((frame_x - left) as f32 / scale) as u32).min(self.width - 1);
+                let from = ((sprite_y * self.width + sprite_x) * 4) as usize;
+                let src = &self.pixels[from..from + 4];
+                let alpha = src[3] as u32;
+                if alpha == 0 {
+                    continue;
+                }
+                let at = ((frame_y as u32 * width + frame_x as u32) * 4) as usize;
+                for channel in 0..3 {
+                    let under = frame[at + channel] as u32;
+                    frame[at + channel] =
+                        ((src[channel] as u32 * alpha + under * (255 - alpha) + 127) / 255) as u8;
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn pixel_at(frame: &[u8], width: u32, x: u32, y: u32) -> [u8; 4] {
+        let at = ((y * width + x) * 4) as usize;
+        frame[at..at + 4].try_into().unwrap()
+    }
+
+    #[test]
+    fn built_in_sprites_have_expected_shape() {
+        let arrow = CursorSprite::arrow();
+        assert_eq!((arrow.width, arrow.height, arrow.hotspot), (12, 19, [0, 0]));
+        assert_eq!(arrow.pixels.len(), 12 * 19 * 4);
+        assert!(ARROW.iter().all(|row| row.len() == 12));
+
+        let crosshair = CursorSprite::crosshair();
+        assert_eq!((crosshair.width, crosshair.height, crosshair.hotspot), (15, 15, [7, 7]));
+        assert_eq!(pixel_at(&crosshair.pixels, 15, 7, 7), [255, 255, 255, 255]);
+        assert_eq!(pixel_at(&crosshair.pixels, 15, 8, 7), [255, 255, 255, 255]);
+        assert_eq!(pixel_at(&crosshair.pixels, 15, 8, 8), [0, 0, 0, 255]);
+        assert_eq!(pixel_at(&crosshair.pixels, 15, 0, 0)[3], 0);
+    }
+
+    #[test]
+    fn draw_places_hotspot_and_skips_transparent_pixels() {
+        let mut frame = vec![100; 32 * 32 * 4];
+        CursorSprite::arrow().draw(&mut frame, 32, 32, 10.0, 5.0, 1.0);
+        // Tip outline, fill and a transparent cell next to the tip.
+        assert_eq!(pixel_at(&frame, 32, 10, 5), [0, 0, 0, 100]);
+        assert_eq!(pixel_at(&frame, 32, 11, 7), [255, 255, 255, 100]);
+        assert_eq!(pixel_at(&frame, 32, 11, 5), [100, 100, 100, 100]);
+
+        let mut frame = vec![100; 32 * 32 * 4];
+        CursorSprite::crosshair().draw(&mut frame, 32, 32, 16.0, 16.0, 1.0);
+        assert_eq!(pixel_at(&frame, 32, 16, 16), [255, 255, 255, 100]);
+        assert_eq!(pixel_at(&frame, 32, 9, 9), [100, 100, 100, 100]);
+    }
+
+    #[test]
+    fn draw_blends_alpha_and_clips_at_edges() {
+        let sprite = CursorSprite {
+            width: 2,
+            height: 2,
+            hotspot: [1, 1],
+            pixels: [[255, 0, 0, 128]; 4].concat(),
+        };
+        let mut frame = vec![0; 4 * 4 * 4];
+        sprite.draw(&mut frame, 4, 4, 0.0, 0.0, 1.0);
+        assert_eq!(pixel_at(&frame, 4, 0, 0), [128, 0, 0, 0]);
+        assert_eq!(pixel_at(&frame, 4, 1, 0), [0, 0, 0, 0]);
+        sprite.draw(&mut frame, 4, 4, 4.0, 4.0, 1.0);
+        assert_eq!(pixel_at(&frame, 4, 3, 3), [128, 0, 0, 0]);
+        // Far outside the frame, or a frame too short: nothing happens.
+        sprite.draw(&mut frame, 4, 4, -50.0, 90.0, 1.0);
+        sprite.draw(&mut frame[..8], 4, 4, 1.0, 1.0, 1.0);
+    }
+
+    #[test]
+    fn draw_scales_the_sprite() {
+        let mut frame = vec![100; 64 * 64 * 4];
+        CursorSprite::arrow().draw(&mut frame, 64, 64, 0.0, 0.0, 2.0);
+        let covered = |x, y| pixel_at(&frame, 64, x, y) != [100, 100, 100, 100];
+        // Sprite row 2 ("BWB") covers frame rows 4 and 5, two pixels per cell.
+        assert_eq!(pixel_at(&frame, 64, 2, 4), [255, 255, 255, 100]);
+        assert_eq!(pixel_at(&frame, 64, 3, 5), [255, 255, 255, 100]);
+        assert_eq!(pixel_at(&frame, 64, 4, 4), [0, 0, 0, 100]);
+        assert!(!covered(6, 4));
+        assert!(covered(16, 37) && !covered(16, 38));
+    }
+
+    #[test]
+    fn loads_png_as_bgra() {
+        let path = std::env::temp_dir().join(format!("cursor_sprite_{}.png", std::process::id()));
+        {
+            let mut encoder = png::Encoder::new(File::create(&path).unwrap(), 2, 1);
+            encoder.set_color(png::ColorType::Rgba);
+            encoder.set_depth(png::BitDepth::Eight);
+            let mut writer = encoder.write_header().unwrap();
+            writer
+                .write_image_data(&[10, 20, 30, 255, 40, 50, 60, 0])
+                .unwrap();
+        }
+        let sprite = CursorSprite::load_png(&path, [1, 0]).unwrap();
+        assert_eq!((sprite.width, sprite.height, sprite.hotspot), (2, 1, [1, 0]));
+        assert_eq!(sprite.pixels, vec![30, 20, 10, 255, 60, 50, 40, 0]);
+        assert!(CursorSprite::load_png(&path, [2, 0]).is_err());
+        std::fs::remove_file(&path).unwrap();
+        assert!(CursorSprite::load_png(&path, [0, 0]).is_err());
+    }
+}
@@ -0,0 +1,617 @@
+use std::thread;
+
+use collector_core::{ResizeFilter, ResizeMode, SourceCrop};
+
+// Below this many destination pixels a frame is resized on the calling thread.
+const PARALLEL_MIN_PIXELS: usize = 256 * 256;
+
+// A rectangle in pixels.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Rect {
+    pub x: u32,
+    pub y: u32,
+    pub width: u32,
+    pub height: u32,
+}
+
+// Which part of the source is used (`src`) and where it lands in the
+// destination (`dst`). Shared by the resize and cursor normalization so both
+// agree to the pixel.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Placement {
+    pub src: Rect,
+    pub dst: Rect,
+}
+
+impl Placement {
+    // Maps a source position (in pixels, fractional) into the destination.
+    pub fn map(&self, x: f32, y: f32) -> (f32, f32) {
+        (
+            self.dst.x as f32 + (x - self.src.x as f32) * self.dst.width as f32 / self.src.width as f32,
+            self.dst.y as f32 + (y - self.src.y as f32) * self.dst.height as f32 / self.src.height as f32,
+        )
+    }
+
+    // Inverse of `map`.
+    pub fn unmap(&self, x: f32, y: f32) -> (f32, f32) {
+        (
+            self.src.x as f32 + (x - self.dst.x as f32) * self.src.width as f32 / self.dst.width as f32,
+            self.src.y as f32 + (y - self.dst.y as f32) * self.src.height as f32 / self.dst.height as f32,
+        )
+    }
+}
+
+pub fn placement(src_w: u32, src_h: u32, dst_w: u32, dst_h: u32, mode: ResizeMode) -> Placement {
+    placement_in(full_rect(src_w.max(1), src_h.max(1)), dst_w, dst_h, mode)
+}
+
+// Like `placement`, for the `src` region of a larger image.
+pub fn placement_in(src: Rect, dst_w: u32, dst_h: u32, mode: ResizeMode) -> Placement {
+    let src = Rect {
+        width: src.width.max(1),
+        height: src.height.max(1),
+        ..src
+    };
+    let dst = full_rect(dst_w.max(1), dst_h.max(1));
+    let scale_x = dst.width as f32 / src.width as f32;
+    let scale_y = dst.height as f32 / src.height as f32;
+    match mode {
+        ResizeMode::Letterbox { .. } => {
+            let scale = scale_x.min(scale_y);
+            let width = ((src.width as f32 * scale).round() as u32).clamp(1, dst.width);
+            let height = ((src.height as f32 * scale).round() as u32).clamp(1, dst.height);
+            Placement {
+                src,
+                dst: centered(dst, width, height),
+            }
+        }
+        ResizeMode::CenterCrop => {
+            let scale = scale_x.max(scale_y);
+            let width = ((dst.width as f32 / scale).round() as u32).clamp(1, src.width);
+            let height = ((dst.height as f32 / scale).round() as u32).clamp(1, src.height);
+            Placement {
+                src: centered(src, width, height),
+                dst,
+            }
+        }
+        ResizeMode::Stretch => Placement { src, dst },
+    }
+}
+
+fn centered(outer: Rect, width: u32, height: u32) -> Rect {
+    Rect {
+        x: outer.x + (outer.width - width) / 2,
+        y: outer.y + (outer.height - height) / 2,
+        width,
+        height,
+    }
+}
+
+// The part of a `src_w` x `src_h` capture that `crop` keeps; all of it when
+// there is no crop or the crop lies outside the capture.
+pub fn crop_rect(crop: Option<&SourceCrop>, src_w: u32, src_h: u32) -> Rect {
+    match crop.and_then(|crop| crop.resolve(src_w, src_h)) {
+        Some([x, y, width, height]) => Rect {
+            x,
+            y,
+            width,
+            height,
+        },
+        None => full_rect(src_w, src_h),
+    }
+}
+
+// Fits a BGRA image into `dst` according to `mode`. Letterbox bars are
+// filled with the pad color; invalid sizes leave `dst` black.
+#[allow(clippy::too_many_arguments)]
+pub fn resize_frame(
+    src: &[u8],
+    src_w: u32,
+    src_h: u32,
+    dst: &mut [u8],
+    dst_w: u32,
+    dst_h: u32,
+    mode: ResizeMode,
+    filter: ResizeFilter,
+) {
+    let region = full_rect(src_w, src_h);
+    resize_frame_region(src, src_w, src_h, region, dst, dst_w, dst_h, mode, filter);
+}
+
+// Like `resize_frame`, fitting only the `region` of `src`.
+#[allow(clippy::too_many_arguments)]
+pub fn resize_frame_region(
+    src: &[u8],
+    src_w: u32,
+    src_h: u32,
+    region: Rect,
+    dst: &mut [u8],
+    dst_w: u32,
+    dst_h: u32,
+    mode: ResizeMode,
+    filter: ResizeFilter,
+) {
+    if !valid_sizes(src, src_w, src_h, dst, dst_w, dst_h)
+        || region.width == 0
+        || region.height == 0
+        || region.x as u64 + region.width as u64 > src_w as u64
+        || region.y as u64 + region.height as u64 > src_h as u64
+    {
+        dst.fill(0);
+        return;
+    }
+    let placement = placement_in(region, dst_w, dst_h, mode);
+    if let ResizeMode::Letterbox { pad_color: [r, g, b] } = mode
+        && placement.dst != full_rect(dst_w, dst_h)
+    {
+        for pixel in dst[..dst_w as usize * dst_h as usize * 4].chunks_exact_mut(4) {
+            pixel.copy_from_slice(&[b, g, r, 255]);
+        }
+    }
+    resize_rect(src, src_w, placement.src, dst, dst_w, placement.dst, filter, threads());
+}
+
+fn valid_sizes(src: &[u8], src_w: u32, src_h: u32, dst: &[u8], dst_w: u32, dst_h: u32) -> bool {
+    src_w > 0
+        && src_h > 0
+        && dst_w > 0
+        && dst_h > 0
+        && src.len() >= src_w as usize * src_h as usize * 4
+        && dst.len() >= dst_w as usize * dst_h as usize * 4
+}
+
+fn full_rect(width: u32, height: u32) -> Rect {
+    Rect {
+        x: 0,
+        y: 0,
+        width,
+        height,
+    }
+}
+
+fn threads() -> usize {
+    thread::available_parallelism().map_or(1, |count| count.get())
+}
+
+// Source pixels feeding one destination row or column, with their weights
+// (summing to 1).
+#[derive(Debug, Clone, PartialEq)]
+struct Taps {
+    start: usize,
+    weights: Vec<f32>,
+}
+
+// Taps for every destination coordinate along one axis, mapping
+// `dst_len` pixels onto source pixels `src_start..src_start + src_len`.
+fn axis_taps(src_start: u32, src_len: u32, dst_len: u32, filter: ResizeFilter) -> Vec<Taps> {
+    let scale = src_len as f64 / dst_len as f64;
+    let last = src_len as usize - 1;
+    (0..dst_len as usize)
+        .map(|dst| {
+            let taps = match filter {
+                ResizeFilter::Nearest => {
+                    let src = (((dst as f64 + 0.5) * scale) as usize).min(last);
+                    Taps {
+                        start: src,
+                        weights: vec![1.0],
+                    }
+                }
+                ResizeFilter::Bilinear => {
+                    let center = ((dst as f64 + 0.5) * scale - 0.5).clamp(0.0, last as f64);
+                    let left = (center.floor() as usize).min(last);
+                    let frac = (center - left as f64) as f32;
+                    if left == last || frac == 0.0 {
+                        Taps {
+                            start: left,
+                            weights: vec![1.0],
+                        }
+                    } else {
+                        Taps {
+                            start: left,
+                            weights: vec![1.0 - frac, frac],
+                        }
+                    }
+                }
+                ResizeFilter::Area => {
+                    // Each source pixel weighs by how much of it the
+                    // destination pixel covers.
+                    let begin = dst as f64 * scale;
+                    let end = ((dst + 1) as f64 * scale).min(src_len as f64);
+                    let first = (begin.floor() as usize).min(last);
+                    let stop = (end.ceil() as usize).clamp(first + 1, src_len as usize);
+                    let weights = (first..stop)
+                        .map(|src| {
+                            let covered = end.min(src as f64 + 1.0) - begin.max(src as f64);
+                            (covered.max(0.0) / (end - begin)) as f32
+                        })
+                        .collect();
+                    Taps {
+                        start: first,
+                        weights,
+                    }
+                }
+            };
+            Taps {
+                start: taps.start + src_start as usize,
+                weights: taps.weights,
+            }
+        })
+        .collect()
+}
+
+// Resamples `src_rect` of `src` into `dst_rect` of `dst`, leaving the rest of
+// `dst` untouched. Rows are split across up to `threads` threads.
+#[allow(clippy::too_many_arguments)]
+fn resize_rect(
+    src: &[u8],
+    src_w: u32,
+    src_rect: Rect,
+    dst: &mut [u8],
+    dst_w: u32,
+    dst_rect: Rect,
+    filter: ResizeFilter,
+    threads: usize,
+) {
+    let src_stride = src_w as usize * 4;
+    let dst_stride = dst_w as usize * 4;
+    let rows = dst_rect.height as usize;
+    let region = &mut dst[dst_rect.y as usize * dst_stride..(dst_rect.y as usize + rows) * dst_stride];
+
+    if src_rect.width == dst_rect.width && src_rect.height == dst_rect.height {
+        let len = dst_rect.width as usize * 4;
+        for (row, out) in region.chunks_exact_mut(dst_stride).enumerate() {
+            let src_at = (src_rect.y as usize + row) * src_stride + src_rect.x as usize * 4;
+            let dst_at = dst_rect.x as usize * 4;
+            out[dst_at..dst_at + len].copy_from_slice(&src[src_at..src_at + len]);
+        }
+        return;
+    }
+
+    let columns = axis_taps(src_rect.x, src_rect.width, dst_rect.width, filter);
+    let row_taps = axis_taps(src_rect.y, src_rect.height, dst_rect.height, filter);
+    let pixels = rows * dst_rect.width as usize;
+    let bands = if pixels < PARALLEL_MIN_PIXELS {
+        1
+    } else {
+        threads.clamp(1, rows)
+    };
+    let band_rows = rows.div_ceil(bands);
+    let resize_band = |first_row: usize, band: &mut [u8]| {
+        let mut scratch = vec![0f32; src_rect.width as usize * 4];
+        for (offset, out) in band.chunks_exact_mut(dst_stride).enumerate() {
+            let out = &mut out[dst_rect.x as usize * 4..(dst_rect.x + dst_rect.width) as usize * 4];
+            let taps = &row_taps[first_row + offset];
+            if filter == ResizeFilter::Nearest {
+                let line = &src[taps.start * src_stride..(taps.start + 1) * src_stride];
+                for (pixel, column) in out.chunks_exact_mut(4).zip(&columns) {
+                    pixel.copy_from_slice(&line[column.start * 4..column.start * 4 + 4]);
+                }
+                continue;
+            }
+            blend_rows(src, src_stride, src_rect, taps, &mut scratch);
+            blend_columns(&scratch, src_rect.x as usize, &columns, out);
+        }
+    };
+    if bands == 1 {
+        resize_band(0, region);
+        return;
+    }
+    thread::scope(|scope| {
+        for (index, band) in region.chunks_mut(band_rows * dst_stride).enumerate() {
+            let resize_band = &resize_band;
+            scope.spawn(move || resize_band(index * band_rows, band));
+        }
+    });
+}
+
+// Weighted sum of the source rows in `taps`, over the columns of `src_rect`.
+fn blend_rows(src: &[u8], src_stride: usize, src_rect: Rect, taps: &Taps, scratch: &mut [f32]) {
+    scratch.fill(0.0);
+    let begin = src_rect.x as usize * 4;
+    for (index, weight) in taps.weights.iter().enumerate() {
+        let row = (taps.start + index) * src_stride + begin;
+        let line = &src[row..row + scratch.len()];
+        for (acc, value) in scratch.iter_mut().zip(line) {
+            *acc += *value as f32 * weight;
+        }
+    }
+}
+
+// Resamples one blended row horizontally into `out`.
+fn blend_columns(scratch: &[f32], src_x: usize, columns: &[Taps], out: &mut [u8]) {
+    for (pixel, column) in out.chunks_exact_mut(4).zip(columns) {
+        let mut acc = [0f32; 4];
+        for (index, weight) in column.weights.iter().enumerate() {
+            let at = (column.start - src_x + index) * 4;
+            for channel in 0..4 {
+                acc[channel] += scratch[at + channel] * weight;
+            }
+        }
+        for (value, sum) in pixel.iter_mut().zip(acc) {
+            *value = (sum + 0.5).clamp(0.0, 255.0) as u8;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const FILTERS: [ResizeFilter; 3] = [
+        ResizeFilter::Nearest,
+        ResizeFilter::Bilinear,
+        ResizeFilter::Area,
+    ];
+
+    fn image(width: u32, height: u32, pixel: impl Fn(u32, u32) -> [u8; 4]) -> Vec<u8> {
+        let mut out = Vec::with_capacity((width * height * 4) as usize);
+        for y in 0..height {
+            for x in 0..width {
+                out.extend_from_slice(&pixel(x, y));
+            }
+        }
+        out
+    }
+
+    fn pixel_at(data: &[u8], width: u32, x: u32, y: u32) -> [u8; 4] {
+        let at = ((y * width + x) * 4) as usize;
+        data[at..at + 4].try_into().unwrap()
+    }
+
+    #[test]
+    fn same_size_copies_with_every_filter() {
+        let src = image(5, 3, |x, y| [x as u8, y as u8, (x * y) as u8, 255]);
+        for filter in FILTERS {
+            let mut dst = vec![0; src.len()];
+            resize_frame(&src, 5, 3, &mut dst, 5, 3, ResizeMode::Stretch, filter);
+            assert_eq!(dst, src, "{:?}", filter);
+        }
+    }
+
+    #[test]
+    fn uniform_image_stays_uniform() {
+        let src = image(7, 5, |_, _| [10, 20, 30, 255]);
+        for filter in FILTERS {
+            for (width, height) in [(3, 2), (16, 9), (7, 1)] {
+                let mut dst = vec![0; (width * height * 4) as usize];
+                resize_frame(&src, 7, 5, &mut dst, width, height, ResizeMode::Stretch, filter);
+                assert!(
+                    dst.chunks_exact(4).all(|pixel| pixel == [10, 20, 30, 255]),
+                    "{:?} {}x{}",
+                    filter,
+                    width,
+                    height
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn area_averages_each_block_on_downscale() {
+        // 4x2 -> 2x1: every output pixel averages a 2x2 block.
+        let src = image(4, 2, |x, y| [(x * 40 + y * 20) as u8, 0, 0, 255]);
+        let mut dst = vec![0; 8];
+        resize_frame(&src, 4, 2, &mut dst, 2, 1, ResizeMode::Stretch, ResizeFilter::Area);
+        assert_eq!(pixel_at(&dst, 2, 0, 0), [30, 0, 0, 255]);
+        assert_eq!(pixel_at(&dst, 2, 1, 0), [110, 0, 0, 255]);
+
+        // 3 -> 2 splits the middle pixel between both outputs.
+        let src = image(3, 1, |x, _| [[0, 90, 180][x as usize], 0, 0, 255]);
+        let mut dst = vec![0; 8];
+        resize_frame(&src, 3, 1, &mut dst, 2, 1, ResizeMode::Stretch, ResizeFilter::Area);
+        assert_eq!(pixel_at(&dst, 2, 0, 0)[0], 30);
+        assert_eq!(pixel_at(&dst, 2, 1, 0)[0], 150);
+    }
+
+    #[test]
+    fn nearest_picks_pixel_centers() {
+        let src = image(4, 1, |x, _| [x as u8 * 10, 0, 0, 255]);
+        let mut dst = vec![0; 8];
+        resize_frame(&src, 4, 1, &mut dst, 2, 1, ResizeMode::Stretch, ResizeFilter::Nearest);
+        assert_eq!((dst[0], dst[4]), (10, 30));
+
+        let src = image(2, 1, |x, _| [x as u8 * 100, 0, 0, 255]);
+        let mut dst = vec![0; 16];
+        resize_frame(&src, 2, 1, &mut dst, 4, 1, ResizeMode::Stretch, ResizeFilter::Nearest);
+        assert_eq!([dst[0], dst[4], dst[8], dst[12]], [0, 0, 100, 100]);
+    }
+
+    #[test]
+    fn bilinear_interpolates_between_centers() {
+        let src = image(2, 1, |x, _| [x as u8 * 200, 0, 0, 255]);
+        let mut dst = vec![0; 16];
+        resize_frame(&src, 2, 1, &mut dst, 4, 1, ResizeMode::Stretch, ResizeFilter::Bilinear);
+        // Output centers map to source x = 0 (clamped), 0.25, 0.75, 1.
+        assert_eq!([dst[0], dst[4], dst[8], dst[12]], [0, 50, 150, 200]);
+    }
+
+    #[test]
+    fn area_taps_cover_the_source_exactly() {
+        for (src_len, dst_len) in [(1280, 720), (7, 3), (3, 7), (1, 5), (5, 1)] {
+            let taps = axis_taps(0, src_len, dst_len, ResizeFilter::Area);
+            let mut coverage = vec![0f64; src_len as usize];
+            for tap in &taps {
+                let sum: f32 = tap.weights.iter().sum();
+                assert!((sum - 1.0).abs() < 1e-4, "{} -> {}", src_len, dst_len);
+                assert!(tap.start + tap.weights.len() <= src_len as usize);
+                for (index, weight) in tap.weights.iter().enumerate() {
+                    coverage[tap.start + index] += *weight as f64;
+                }
+            }
+            let scale = dst_len as f64 / src_len as f64;
+            for covered in coverage {
+                assert!((covered - scale).abs() < 1e-3, "{} -> {}", src_len, dst_len);
+            }
+        }
+    }
+
+    #[test]
+    fn letterbox_centers_on_black_bars() {
+        let src = image(4, 2, |_, _| [255, 255, 255, 255]);
+        for filter in FILTERS {
+            let mut dst = vec![7; 4 * 4 * 4];
+            resize_frame(&src, 4, 2, &mut dst, 4, 4, ResizeMode::BLACK_LETTERBOX, filter);
+            for y in 0..4 {
+                let expected = if (1..3).contains(&y) { 255 } else { 0 };
+                for x in 0..4 {
+                    assert_eq!(pixel_at(&dst, 4, x, y)[0], expected, "{:?}", filter);
+                }
+            }
+        }
+
+        let src = image(2, 4, |_, _| [255, 255, 255, 255]);
+        let mut dst = vec![0; 6 * 4 * 4];
+        resize_frame(&src, 2, 4, &mut dst, 6, 4, ResizeMode::BLACK_LETTERBOX, ResizeFilter::Area);
+        let row: Vec<u8> = (0..6).map(|x| pixel_at(&dst, 6, x, 2)[0]).collect();
+        assert_eq!(row, vec![0, 0, 255, 255, 0, 0]);
+    }
+
+    #[test]
+    fn placement_fits_each_mode() {
+        // 16:10 source into a 16:9 frame.
+        let letterbox = placement(1920, 1200, 1280, 720, ResizeMode::BLACK_LETTERBOX);
+        assert_eq!(letterbox.src, full_rect(1920, 1200));
+        assert_eq!(
+            letterbox.dst,
+            Rect {
+                x: 64,
+                y: 0,
+                width: 1152,
+                height: 720
+            }
+        );
+        let crop = placement(1920, 1200, 1280, 720, ResizeMode::CenterCrop);
+        assert_eq!(
+            crop.src,
+            Rect {
+                x: 0,
+                y: 60,
+                width: 1920,
+                height: 1080
+            }
+        );
+        assert_eq!(crop.dst, full_rect(1280, 720));
+        // Ultrawide source: crop cuts the sides instead.
+        let crop = placement(3440, 1440, 1280, 720, ResizeMode::CenterCrop);
+        assert_eq!((crop.src.x, crop.src.width, crop.src.height), (440, 2560, 1440));
+        let stretch = placement(1920, 1200, 1280, 720, ResizeMode::Stretch);
+        assert_eq!(stretch.src, full_rect(1920, 1200));
+        assert_eq!(stretch.dst, full_rect(1280, 720));
+    }
+
+    #[test]
+    fn region_fills_the_frame() {
+        // A white 4x2 play area inside red UI, recorded at 8x4.
+        let src = image(8, 6, |x, y| {
+            if (2..6).contains(&x) && (2..4).contains(&y) {
+                [255, 255, 255, 255]
+            } else {
+                [0, 0, 255, 255]
+            }
+        });
+        let region = crop_rect(Some(&SourceCrop::Client([2, 2, 4, 2])), 8, 6);
+        for filter in FILTERS {
+            let mut dst = vec![0; 8 * 4 * 4];
+            let mode = ResizeMode::BLACK_LETTERBOX;
+            resize_frame_region(&src, 8, 6, region, &mut dst, 8, 4, mode, filter);
+            assert!(dst.iter().all(|&value| value == 255), "{:?}", filter);
+        }
+    }
+
+    #[test]
+    fn crop_rect_resolves_and_clips() {
+        let rect = |x, y, width, height| Rect {
+            x,
+            y,
+            width,
+            height,
+        };
+        assert_eq!(crop_rect(None, 1920, 1080), full_rect(1920, 1080));
+        let normalized = SourceCrop::Normalized([0.25, 0.0, 0.5, 1.0]);
+        assert_eq!(crop_rect(Some(&normalized), 1920, 1080), rect(480, 0, 960, 1080));
+        let overhanging = SourceCrop::Client([1800, 1000, 400, 400]);
+        assert_eq!(crop_rect(Some(&overhanging), 1920, 1080), rect(1800, 1000, 120, 80));
+        // Nothing left of the crop: the whole capture is used.
+        let outside = SourceCrop::Client([2000, 0, 100, 100]);
+        assert_eq!(crop_rect(Some(&outside), 1920, 1080), full_rect(1920, 1080));
+        let empty = SourceCrop::Normalized([0.5, 0.5, 0.0, 0.5]);
+        assert_eq!(crop_rect(Some(&empty), 1920, 1080), full_rect(1920, 1080));
+    }
+
+    #[test]
+    fn placement_in_offsets_by_the_region() {
+        let region = Rect {
+            x: 100,
+            y: 40,
+            width: 1920,
+            height: 1200,
+        };
+        let crop = placement_in(region, 1280, 720, ResizeMode::CenterCrop);
+        assert_eq!((crop.src.x, crop.src.y), (100, 100));
+        assert_eq!(crop.map(1060.0, 640.0), (640.0, 360.0));
+        let stretch = placement_in(region, 1280, 720, ResizeMode::Stretch);
+        assert_eq!(stretch.map(100.0, 40.0), (0.0, 0.0));
+        assert!(stretch.map(50.0, 40.0).0 < 0.0);
+        let letterbox = placement_in(region, 1280, 720, ResizeMode::BLACK_LETTERBOX);
+        assert_eq!(letterbox.map(100.0, 40.0), (64.0, 0.0));
+    }
+
+    #[test]
+    fn placement_maps_source_points() {
+        let letterbox = placement(1920, 1200, 1280, 720, ResizeMode::BLACK_LETTERBOX);
+        assert_eq!(letterbox.map(0.0, 0.0), (64.0, 0.0));
+        assert_eq!(letterbox.map(1920.0, 1200.0), (1216.0, 720.0));
+        let crop = placement(1920, 1200, 1280, 720, ResizeMode::CenterCrop);
+        assert_eq!(crop.map(960.0, 600.0), (640.0, 360.0));
+        assert_eq!(crop.map(0.0, 60.0), (0.0, 0.0));
+        // Outside the cropped band maps outside the frame.
+        assert!(crop.map(0.0, 0.0).1 < 0.0);
+        let stretch = placement(1920, 1200, 1280, 720, ResizeMode::Stretch);
+        assert_eq!(stretch.map(960.0, 1200.0), (640.0, 720.0));
+        for placement in [letterbox, crop, stretch] {
+            let (x, y) = placement.map(480.0, 300.0);
+            assert_eq!(placement.unmap(x, y), (480.0, 300.0));
+        }
+    }
+
+    #[test]
+    fn center_crop_and_pad_color_fill_the_frame() {
+        // Columns 0 and 3 are cut away when 4x2 is cropped into 2x2.
+        let src = image(4, 2, |x, _| [x as u8 * 50, 0, 0, 255]);
+        let mut dst = vec![0; 16];
+        resize_frame(&src, 4, 2, &mut dst, 2, 2, ResizeMode::CenterCrop, ResizeFilter::Nearest);
+        assert_eq!([dst[0], dst[4], dst[8], dst[12]], [50, 100, 50, 100]);
+
+        let src = image(4, 2, |_, _| [1, 2, 3, 255]);
+        let mut dst = vec![0; 4 * 4 * 4];
+        let mode = ResizeMode::Letterbox {
+            pad_color: [200, 100, 50],
+        };
+        resize_frame(&src, 4, 2, &mut dst, 4, 4, mode, ResizeFilter::Area);
+        assert_eq!(pixel_at(&dst, 4, 0, 0), [50, 100, 200, 255]);
+        assert_eq!(pixel_at(&dst, 4, 3, 1), [1, 2, 3, 255]);
+        assert_eq!(pixel_at(&dst, 4, 2, 3), [50, 100, 200, 255]);
+    }
+
+    #[test]
+    fn short_buffers_leave_black_output() {
+        let mut dst = vec![9; 16];
+        resize_frame(&[1; 8], 2, 2, &mut dst, 2, 2, ResizeMode::Stretch, ResizeFilter::Area);
+        assert!(dst.iter().all(|value| *value == 0));
+        resize_frame(&[1; 16], 0, 2, &mut dst, 2, 2, ResizeMode::BLACK_LETTERBOX, ResizeFilter::Area);
+        assert!(dst.iter().all(|value| *value == 0));
+    }
+
+    #[test]
+    fn threaded_bands_match_single_thread() {
+        let src = image(640, 480, |x, y| [(x * 7) as u8, (y * 3) as u8, (x ^ y) as u8, 255]);
+        for filter in FILTERS {
+            let mut single = vec![0; 400 * 300 * 4];
+            let mut banded = single.clone();
+            let (from, to) = (full_rect(640, 480), full_rect(400, 300));
+            resize_rect(&src, 640, from, &mut single, 400, to, filter, 1);
+            resize_rect(&src, 640, from, &mut banded, 400, to, filter, 7);
+            assert_eq!(single, banded, "{:?}", filter);
+        }
+    }
+}
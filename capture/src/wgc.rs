@@ -1,11 +1,17 @@
 use std::io;
-use collector_core::{CaptureOptions, FrameRecord, QpcTimestamp, StepIndex};
+use collector_core::{CaptureOptions, FrameRecord};
+#[cfg(windows)]
+use collector_core::{FramePacing, QpcTimestamp, StepIndex};
 
 #[cfg(windows)]
 use std::sync::mpsc::{self, Receiver};
 #[cfg(windows)]
+use std::sync::Arc;
+#[cfg(windows)]
 use std::time::Duration;
 
+#[cfg(windows)]
+use crate::resize::{crop_rect, resize_frame_region};
 #[cfg(windows)]
 use windows::core::{Interface, Result as WinResult};
 #[cfg(windows)]
@@ -74,6 +80,10 @@ pub struct WgcCaptureImpl {
     qpc_frequency: u64,
     next_capture_qpc: QpcTimestamp,
     step_ticks: u64,
+    skipped_source_frames: u32,
+    // `SystemRelativeTime` of the last frame returned, to spot repeats.
+    last_frame_time: Option<i64>,
+    keep_source: bool,
 }
 
 #[cfg(windows)]
@@ -103,6 +113,8 @@ impl WgcCaptureImpl {
         let session = frame_pool
             .CreateCaptureSession(&item)
             .map_err(map_win_err)?;
+        // The pipeline draws the cursor at its sampled position instead
+        // (`include_cursor_in_video`).
         session.SetIsCursorCaptureEnabled(false).map_err(map_win_err)?;
         session.StartCapture().map_err(map_win_err)?;
 
@@ -124,13 +136,20 @@ impl WgcCaptureImpl {
             staging: None,
             src_buffer: Vec::new(),
             output_buffer: Vec::new(),
+            keep_source: false,
             step_index: 0,
             qpc_frequency,
             next_capture_qpc: 0,
             step_ticks,
+            skipped_source_frames: 0,
+            last_frame_time: None,
         })
     }
 
+    pub fn set_keep_source(&mut self, keep: bool) {
+        self.keep_source = keep;
+    }
+
     pub fn next_frame(&mut self) -> io::Result<FrameRecord> {
         loop {
             ensure_window_ready(self.target_hwnd)?;
@@ -169,12 +188,27 @@ impl WgcCaptureImpl {
                 self.next_capture_qpc = now;
             }
             if now < self.next_capture_qpc {
+                self.skipped_source_frames = self.skipped_source_frames.saturating_add(1);
                 continue;
             }
+            let mut missed_slots = 0u32;
             while now.saturating_sub(self.next_capture_qpc) >= self.step_ticks {
                 self.next_capture_qpc = self.next_capture_qpc.saturating_add(self.step_ticks);
+                missed_slots = missed_slots.saturating_add(1);
             }
+            let lateness_ticks = now.saturating_sub(self.next_capture_qpc);
             self.next_capture_qpc = self.next_capture_qpc.saturating_add(self.step_ticks);
+            // A frame carrying the previous frame's timestamp repeats its image.
+            let frame_time = frame.SystemRelativeTime().map_err(map_win_err)?.Duration;
+            let duplicated = self.last_frame_time == Some(frame_time);
+            self.last_frame_time = Some(frame_time);
+            let pacing = FramePacing {
+                lateness_ms: (lateness_ticks as f64 * 1000.0 / self.qpc_frequency.max(1) as f64)
+                    as f32,
+                skipped_source_frames: std::mem::take(&mut self.skipped_source_frames),
+                missed_slots,
+                duplicated,
+            };
 
             let texture = get_frame_texture(&frame).map_err(map_win_err)?;
             let (src_w, src_h) = (content_size.Width as u32, content_size.Height as u32);
@@ -191,13 +225,17 @@ impl WgcCaptureImpl {
             let dst_w = self.options.record_resolution[0];
             let dst_h = self.options.record_resolution[1];
             ensure_buffer_size(&mut self.output_buffer, dst_w, dst_h);
-            letterbox_bgra(
+            let region = crop_rect(self.options.source_crop.as_ref(), src_w, src_h);
+            resize_frame_region(
                 src_bytes,
                 src_w,
                 src_h,
+                region,
                 &mut self.output_buffer,
                 dst_w,
                 dst_h,
+                self.options.resize_mode,
+                self.options.resize_filter,
             );
 
             let record = FrameRecord {
@@ -208,6 +246,8 @@ impl WgcCaptureImpl {
                 width: dst_w,
                 height: dst_h,
                 data: self.output_buffer.clone(),
+                pacing,
+                source: self.keep_source.then(|| Arc::new(src_bytes.to_vec())),
             };
             self.step_index = self.step_index.saturating_add(1);
             return Ok(record);
@@ -288,12 +328,12 @@ fn read_texture<'a>(
                 .CreateTexture2D(&desc, None, Some(&mut staging_tex))
                 .map_err(map_win_err)?;
             let staging_tex = staging_tex.ok_or_else(|| {
-                io::Error::new(io::ErrorKind::Other, "staging texture unavailable")
+                io::Error::other("staging texture unavailable")
             })?;
             *staging = Some(staging_tex);
         }
         let staging_tex = staging.as_ref().ok_or_else(|| {
-            io::Error::new(io::ErrorKind::Other, "staging texture unavailable")
+            io::Error::other("staging texture unavailable")
         })?;
 
         context.CopyResource(staging_tex, texture);
@@ -321,47 +361,6 @@ fn read_texture<'a>(
     Ok(buffer.as_slice())
 }
 
-#[cfg(windows)]
-fn letterbox_bgra(
-    src: &[u8],
-    src_w: u32,
-    src_h: u32,
-    dst: &mut [u8],
-    dst_w: u32,
-    dst_h: u32,
-) {
-    dst.fill(0);
-    if src_w == 0 || src_h == 0 || dst_w == 0 || dst_h == 0 {
-        return;
-    }
-
-    let scale_w = dst_w as f32 / src_w as f32;
-    let scale_h = dst_h as f32 / src_h as f32;
-    let scale = scale_w.min(scale_h);
-    let mut scaled_w = (src_w as f32 * scale).round() as u32;
-    let mut scaled_h = (src_h as f32 * scale).round() as u32;
-    if scaled_w == 0 {
-        scaled_w = 1;
-    }
-    if scaled_h == 0 {
-        scaled_h = 1;
-    }
-    let pad_x = (dst_w.saturating_sub(scaled_w)) / 2;
-    let pad_y = (dst_h.saturating_sub(scaled_h)) / 2;
-
-    for y in 0..scaled_h {
-        let src_y = (y as u64 * src_h as u64 / scaled_h as u64) as u32;
-        for x in 0..scaled_w {
-            let src_x = (x as u64 * src_w as u64 / scaled_w as u64) as u32;
-            let src_idx = ((src_y * src_w + src_x) * 4) as usize;
-            let dst_idx = (((y + pad_y) * dst_w + (x + pad_x)) * 4) as usize;
-            if src_idx + 4 <= src.len() && dst_idx + 4 <= dst.len() {
-                dst[dst_idx..dst_idx + 4].copy_from_slice(&src[src_idx..src_idx + 4]);
-            }
-        }
-    }
-}
-
 #[cfg(windows)]
 fn ensure_buffer_size(buffer: &mut Vec<u8>, width: u32, height: u32) {
     let size = (width as usize)
@@ -377,44 +376,26 @@ fn ensure_buffer_size(buffer: &mut Vec<u8>, width: u32, height: u32) {
 fn ensure_window_ready(hwnd: HWND) -> io::Result<()> {
     unsafe {
         if !IsWindow(hwnd).as_bool() {
-            return Err(io::Error::new(
-                io::ErrorKind::Other,
-                "target window is not valid",
-            ));
+            return Err(io::Error::other("target window is not valid"));
         }
         if !IsWindowVisible(hwnd).as_bool() {
-            return Err(io::Error::new(
-                io::ErrorKind::Other,
-                "target window is not visible",
-            ));
+            return Err(io::Error::other("target window is not visible"));
         }
         if IsIconic(hwnd).as_bool() {
-            return Err(io::Error::new(
-                io::ErrorKind::Other,
-                "target window is minimized",
-            ));
+            return Err(io::Error::other("target window is minimized"));
         }
         if is_window_cloaked(hwnd) {
-            return Err(io::Error::new(
-                io::ErrorKind::Other,
-                "target window is cloaked",
-            ));
+            return Err(io::Error::other("target window is cloaked"));
         }
         let mut rect = RECT::default();
         GetWindowRect(hwnd, &mut rect).map_err(map_win_err)?;
         let width = rect.right - rect.left;
         let height = rect.bottom - rect.top;
         if width <= 0 || height <= 0 {
-            return Err(io::Error::new(
-                io::ErrorKind::Other,
-                "target window has empty bounds",
-            ));
+            return Err(io::Error::other("target window has empty bounds"));
         }
         if is_fullscreen_like(hwnd, rect)? {
-            return Err(io::Error::new(
-                io::ErrorKind::Other,
-                "target window looks fullscreen; use windowed mode",
-            ));
+            return Err(io::Error::other("target window looks fullscreen; use windowed mode"));
         }
     }
     Ok(())
@@ -499,7 +480,7 @@ fn qpc_now() -> io::Result<QpcTimestamp> {
 
 #[cfg(windows)]
 fn map_win_err(err: windows::core::Error) -> io::Error {
-    io::Error::new(io::ErrorKind::Other, format!("{:?}", err))
+    io::Error::other(format!("{:?}", err))
 }
 
 #[cfg(not(windows))]
@@ -508,16 +489,12 @@ pub struct WgcCaptureImpl;
 #[cfg(not(windows))]
 impl WgcCaptureImpl {
     pub fn new(_options: &CaptureOptions, _target_hwnd: isize) -> io::Result<Self> {
-        Err(io::Error::new(
-            io::ErrorKind::Other,
-            "WGC capture requires Windows",
-        ))
+        Err(io::Error::other("WGC capture requires Windows"))
     }
 
+    pub fn set_keep_source(&mut self, _keep: bool) {}
+
     pub fn next_frame(&mut self) -> io::Result<FrameRecord> {
-        Err(io::Error::new(
-            io::ErrorKind::Other,
-            "WGC capture requires Windows",
-        ))
+        Err(io::Error::other("WGC capture requires Windows"))
     }
 }
@@ -1,3 +1,5 @@
+use std::sync::Arc;
+
 use serde::{Deserialize, Serialize};
 
 pub type QpcTimestamp = u64;
@@ -15,6 +17,16 @@ pub struct Options {
     pub input: InputOptions,
     pub timing: TimingOptions,
     pub auto_events: AutoEventsOptions,
+    #[serde(default)]
+    pub markers: MarkerOptions,
+    #[serde(default)]
+    pub idle: IdleOptions,
+    #[serde(default)]
+    pub video: VideoOutputOptions,
+    #[serde(default)]
+    pub segment: SegmentOptions,
+    #[serde(default)]
+    pub privacy: PrivacyOptions,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -23,8 +35,15 @@ pub struct CaptureOptions {
     pub fps: u32,
     pub record_resolution: [u32; 2],
     pub resize_mode: ResizeMode,
+    #[serde(default)]
+    pub resize_filter: ResizeFilter,
+    // Part of the captured window that is recorded; None records all of it.
+    #[serde(default)]
+    pub source_crop: Option<SourceCrop>,
     pub color_format: ColorFormat,
     pub include_cursor_in_video: bool,
+    #[serde(default)]
+    pub cursor_style: CursorStyle,
     pub target: CaptureTarget,
 }
 
@@ -41,10 +60,127 @@ pub enum CaptureApi {
     WindowsGraphicsCapture,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+// How a captured frame is fitted to the record resolution when the aspect
+// ratios differ.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case", from = "ResizeModeRepr")]
 pub enum ResizeMode {
-    #[serde(rename = "letterbox")]
+    // Scale to fit, centered on bars of `pad_color` (RGB).
+    Letterbox { pad_color: [u8; 3] },
+    // Scale to cover, cutting the overflow equally from both sides.
+    CenterCrop,
+    // Scale each axis independently to fill the frame.
+    Stretch,
+}
+
+impl ResizeMode {
+    pub const BLACK_LETTERBOX: ResizeMode = ResizeMode::Letterbox {
+        pad_color: [0, 0, 0],
+    };
+}
+
+// Accepts the plain names written by older options files ("letterbox") as
+// well as the tagged form (`{"letterbox": {"pad_color": [r, g, b]}}`).
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum ResizeModeRepr {
+    Name(ResizeModeName),
+    Letterbox { letterbox: LetterboxRepr },
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "snake_case")]
+enum ResizeModeName {
     Letterbox,
+    CenterCrop,
+    Stretch,
+}
+
+#[derive(Deserialize)]
+struct LetterboxRepr {
+    #[serde(default)]
+    pad_color: [u8; 3],
+}
+
+impl From<ResizeModeRepr> for ResizeMode {
+    fn from(repr: ResizeModeRepr) -> Self {
+        match repr {
+            ResizeModeRepr::Name(ResizeModeName::Letterbox) => ResizeMode::BLACK_LETTERBOX,
+            ResizeModeRepr::Name(ResizeModeName::CenterCrop) => ResizeMode::CenterCrop,
+            ResizeModeRepr::Name(ResizeModeName::Stretch) => ResizeMode::Stretch,
+            ResizeModeRepr::Letterbox { letterbox } => ResizeMode::Letterbox {
+                pad_color: letterbox.pad_color,
+            },
+        }
+    }
+}
+
+// A region of interest in the captured window, `[x, y, width, height]`.
+// Only this part is resized to the record resolution.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SourceCrop {
+    // Client-area pixels as captured (physical pixels).
+    Client([u32; 4]),
+    // Fractions of the captured width and height.
+    Normalized([f32; 4]),
+}
+
+impl SourceCrop {
+    // The crop in pixels of a `width` x `height` capture, clipped to it. None
+    // when nothing of it is left.
+    pub fn resolve(&self, width: u32, height: u32) -> Option<[u32; 4]> {
+        let (left, top, right, bottom) = match *self {
+            SourceCrop::Client([x, y, w, h]) => {
+                let (x, y) = (x as u64, y as u64);
+                (x, y, x + w as u64, y + h as u64)
+            }
+            SourceCrop::Normalized([x, y, w, h]) => {
+                let edge = |value: f32, size: u32| {
+                    (value.clamp(0.0, 1.0) * size as f32).round() as u64
+                };
+                (edge(x, width), edge(y, height), edge(x + w, width), edge(y + h, height))
+            }
+        };
+        let right = right.min(width as u64);
+        let bottom = bottom.min(height as u64);
+        (right > left && bottom > top).then(|| {
+            [
+                left as u32,
+                top as u32,
+                (right - left) as u32,
+                (bottom - top) as u32,
+            ]
+        })
+    }
+}
+
+// Cursor sprite drawn into the video when `include_cursor_in_video` is set.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CursorStyle {
+    #[default]
+    Arrow,
+    Crosshair,
+    // A PNG (relative paths resolve against the dataset root); `hotspot` is
+    // the pixel placed on the cursor position.
+    Png {
+        path: String,
+        #[serde(default)]
+        hotspot: [u32; 2],
+    },
+}
+
+// Sampling used when a captured frame is scaled to the record resolution.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ResizeFilter {
+    Nearest,
+    Bilinear,
+    // Box filter weighted by pixel coverage; keeps text and HUD edges clean
+    // when downscaling.
+    #[default]
+    Area,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -85,6 +221,21 @@ pub struct TimingOptions {
     pub clock: ClockType,
     pub step_ms: u64,
     pub fps: u32,
+    #[serde(default)]
+    pub alignment: ActionAlignment,
+    #[serde(default = "default_action_steps_per_frame")]
+    pub action_steps_per_frame: u32,
+}
+
+fn default_action_steps_per_frame() -> u32 {
+    1
+}
+
+// Length of one action step at `fps` video frames per second (the capture
+// rate). Recording and rule replay both use this, so duration rules fire on
+// the same step.
+pub fn action_step_ms(fps: u32, action_steps_per_frame: u32) -> f64 {
+    1000.0 / (fps.max(1) as f64 * action_steps_per_frame.max(1) as f64)
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -93,11 +244,467 @@ pub enum ClockType {
     Qpc,
 }
 
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ActionAlignment {
+    #[default]
+    Preceding,
+    Following,
+    OffsetMs(i64),
+}
+
+impl ActionAlignment {
+    pub fn offset_ms(&self, step_ms: u64) -> i64 {
+        match self {
+            ActionAlignment::Preceding => 0,
+            ActionAlignment::Following => step_ms as i64,
+            ActionAlignment::OffsetMs(offset) => *offset,
+        }
+    }
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct AutoEventsOptions {
     pub enabled: bool,
     pub roi_config: String,
     pub stability_frames: u32,
+    #[serde(default)]
+    pub rules: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MarkerOptions {
+    pub enabled: bool,
+    pub strip_from_actions: bool,
+    pub bindings: Vec<MarkerBinding>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MarkerBinding {
+    pub key: String,
+    pub marker: MarkerKind,
+    #[serde(default)]
+    pub discard_secs: f32,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum MarkerKind {
+    EpisodeStart,
+    EpisodeEnd,
+    Interesting,
+    Discard,
+}
+
+impl Default for MarkerOptions {
+    // F8..F11 (the input crate names function keys "Eight".."Eleven").
+    fn default() -> Self {
+        let binding = |key: &str, marker, discard_secs| MarkerBinding {
+            key: key.to_string(),
+            marker,
+            discard_secs,
+        };
+        Self {
+            enabled: false,
+            strip_from_actions: true,
+            bindings: vec![
+                binding("Eight", MarkerKind::EpisodeStart, 0.0),
+                binding("Nine", MarkerKind::EpisodeEnd, 0.0),
+                binding("Ten", MarkerKind::Interesting, 0.0),
+                binding("Eleven", MarkerKind::Discard, 10.0),
+            ],
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct IdleOptions {
+    pub enabled: bool,
+    pub idle_secs: f32,
+    pub static_threshold: f32,
+}
+
+impl Default for IdleOptions {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            idle_secs: 30.0,
+            static_threshold: 2.0,
+        }
+    }
+}
+
+// Splits a session into `chunk_0000/`, `chunk_0001/`, ... with their own video
+// and JSONL files. A chunk is closed after `every_frames` video frames or
+// `every_minutes` of recording, whichever comes first; 0 disables a limit and
+// both 0 keeps the session in one directory.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SegmentOptions {
+    pub every_frames: u64,
+    pub every_minutes: u32,
+}
+
+impl SegmentOptions {
+    pub fn enabled(&self) -> bool {
+        self.every_frames > 0 || self.every_minutes > 0
+    }
+}
+
+// Regions hidden in every video frame before it is encoded.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct PrivacyOptions {
+    pub masks: Vec<MaskRegion>,
+}
+
+impl PrivacyOptions {
+    // Masks that apply to a session of `game` (`Meta.game`).
+    pub fn masks_for(&self, game: &str) -> Vec<MaskRegion> {
+        self.masks
+            .iter()
+            .filter(|mask| {
+                mask.game
+                    .as_deref()
+                    .is_none_or(|name| name.eq_ignore_ascii_case(game))
+            })
+            .cloned()
+            .collect()
+    }
+}
+
+// `rect` is `[x, y, width, height]` in record-resolution pixels. A mask with
+// a `game` only applies to sessions of that game.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct MaskRegion {
+    pub rect: [u32; 4],
+    #[serde(flatten)]
+    pub style: MaskStyle,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub game: Option<String>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "style", rename_all = "snake_case")]
+pub enum MaskStyle {
+    // Solid RGB color.
+    Fill {
+        #[serde(default)]
+        color: [u8; 3],
+    },
+    // Box blur, `radius` record pixels.
+    Blur {
+        #[serde(default = "default_blur_radius")]
+        radius: u32,
+    },
+    // Averaged `block` x `block` cells.
+    Pixelate {
+        #[serde(default = "default_pixelate_block")]
+        block: u32,
+    },
+}
+
+fn default_blur_radius() -> u32 {
+    12
+}
+
+fn default_pixelate_block() -> u32 {
+    16
+}
+
+// Where recorded frames go. `image_format` and `quality` only apply to the
+// image sequence sink; `quality` is the JPEG quality (1-100), WebP is always
+// lossless. `encode` only applies to the ffmpeg sink.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct VideoOutputOptions {
+    pub sink: VideoSinkKind,
+    pub image_format: ImageFormat,
+    pub quality: u8,
+    pub encode: EncodeOptions,
+    pub queue: EncodeQueueOptions,
+    pub renditions: Vec<Rendition>,
+}
+
+impl Default for VideoOutputOptions {
+    fn default() -> Self {
+        Self {
+            sink: VideoSinkKind::Ffmpeg,
+            image_format: ImageFormat::Png,
+            quality: 90,
+            encode: EncodeOptions::default(),
+            queue: EncodeQueueOptions::default(),
+            renditions: Vec::new(),
+        }
+    }
+}
+
+// One of several outputs encoded from the same source frames, written as
+// `video_<name>.<ext>` with its own encoder. When any are declared they
+// replace the single `video.<ext>` output. `encode` defaults to
+// `video.encode`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Rendition {
+    pub name: String,
+    pub resolution: [u32; 2],
+    #[serde(default)]
+    pub encode: Option<EncodeOptions>,
+}
+
+// Frames wait here between the capture thread and the encoder thread.
+// `capacity` is in frames; `full_policy` decides what a full queue does.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct EncodeQueueOptions {
+    pub capacity: u32,
+    pub full_policy: QueueFullPolicy,
+}
+
+impl Default for EncodeQueueOptions {
+    fn default() -> Self {
+        Self {
+            capacity: 8,
+            full_policy: QueueFullPolicy::Block,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum QueueFullPolicy {
+    // Wait for the encoder; the wait shows up as `queue_stall_ms`.
+    #[default]
+    Block,
+    // Evict the oldest queued frame so capture never waits.
+    DropOldest,
+    // Fail the session.
+    Error,
+}
+
+// ffmpeg encoder settings. Lossy codecs take exactly one of `crf` and
+// `bitrate_kbps`; FFV1 takes neither. For MJPEG `crf` is the -q:v scale.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct EncodeOptions {
+    pub codec: VideoCodec,
+    pub preset: Option<String>,
+    pub crf: Option<u32>,
+    pub bitrate_kbps: Option<u32>,
+    pub gop: u32,
+    pub pixel_format: PixelFormat,
+    pub tune: Option<String>,
+}
+
+impl Default for EncodeOptions {
+    fn default() -> Self {
+        Self {
+            codec: VideoCodec::X264,
+            preset: None,
+            crf: Some(16),
+            bitrate_kbps: None,
+            gop: 10,
+            pixel_format: PixelFormat::Yuv420p,
+            tune: None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum VideoCodec {
+    X264,
+    X265,
+    Av1,
+    Ffv1,
+    Mjpeg,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum PixelFormat {
+    Yuv420p,
+    Yuv422p,
+    Yuv444p,
+    Nv12,
+    Yuvj420p,
+    Yuvj444p,
+    Bgr0,
+}
+
+impl VideoCodec {
+    pub fn encoder(self) -> &'static str {
+        match self {
+            VideoCodec::X264 => "libx264",
+            VideoCodec::X265 => "libx265",
+            VideoCodec::Av1 => "libsvtav1",
+            VideoCodec::Ffv1 => "ffv1",
+            VideoCodec::Mjpeg => "mjpeg",
+        }
+    }
+
+    // MP4 cannot carry FFV1, so lossless recordings go to Matroska.
+    pub fn container_extension(self) -> &'static str {
+        match self {
+            VideoCodec::Ffv1 => "mkv",
+            _ => "mp4",
+        }
+    }
+
+    fn presets(self) -> &'static [&'static str] {
+        match self {
+            VideoCodec::X264 | VideoCodec::X265 => &[
+                "ultrafast",
+                "superfast",
+                "veryfast",
+                "faster",
+                "fast",
+                "medium",
+                "slow",
+                "slower",
+                "veryslow",
+                "placebo",
+            ],
+            VideoCodec::Av1 => &[
+                "0", "1", "2", "3", "4", "5", "6", "7", "8", "9", "10", "11", "12", "13",
+            ],
+            VideoCodec::Ffv1 | VideoCodec::Mjpeg => &[],
+        }
+    }
+
+    fn tunes(self) -> &'static [&'static str] {
+        match self {
+            VideoCodec::X264 => &[
+                "film",
+                "animation",
+                "grain",
+                "stillimage",
+                "fastdecode",
+                "zerolatency",
+                "psnr",
+                "ssim",
+            ],
+            VideoCodec::X265 => &[
+                "animation",
+                "grain",
+                "fastdecode",
+                "zerolatency",
+                "psnr",
+                "ssim",
+            ],
+            VideoCodec::Av1 | VideoCodec::Ffv1 | VideoCodec::Mjpeg => &[],
+        }
+    }
+
+    fn crf_range(self) -> Option<(u32, u32)> {
+        match self {
+            VideoCodec::X264 | VideoCodec::X265 => Some((0, 51)),
+            VideoCodec::Av1 => Some((0, 63)),
+            VideoCodec::Mjpeg => Some((2, 31)),
+            VideoCodec::Ffv1 => None,
+        }
+    }
+
+    fn pixel_formats(self) -> &'static [PixelFormat] {
+        match self {
+            VideoCodec::X264 => &[
+                PixelFormat::Yuv420p,
+                PixelFormat::Yuv422p,
+                PixelFormat::Yuv444p,
+                PixelFormat::Nv12,
+            ],
+            VideoCodec::X265 => &[
+                PixelFormat::Yuv420p,
+                PixelFormat::Yuv422p,
+                PixelFormat::Yuv444p,
+            ],
+            VideoCodec::Av1 => &[PixelFormat::Yuv420p],
+            VideoCodec::Ffv1 => &[
+                PixelFormat::Yuv420p,
+                PixelFormat::Yuv444p,
+                PixelFormat::Bgr0,
+            ],
+            VideoCodec::Mjpeg => &[PixelFormat::Yuvj420p, PixelFormat::Yuvj444p],
+        }
+    }
+}
+
+impl PixelFormat {
+    pub fn name(self) -> &'static str {
+        match self {
+            PixelFormat::Yuv420p => "yuv420p",
+            PixelFormat::Yuv422p => "yuv422p",
+            PixelFormat::Yuv444p => "yuv444p",
+            PixelFormat::Nv12 => "nv12",
+            PixelFormat::Yuvj420p => "yuvj420p",
+            PixelFormat::Yuvj444p => "yuvj444p",
+            PixelFormat::Bgr0 => "bgr0",
+        }
+    }
+}
+
+impl EncodeOptions {
+    pub fn validate(&self) -> Result<(), String> {
+        let codec = self.codec;
+        let name = codec.encoder();
+        if self.gop == 0 {
+            return Err("gop must be at least 1".to_string());
+        }
+        if let Some(preset) = self.preset.as_deref()
+            && !codec.presets().contains(&preset)
+        {
+            return Err(format!("preset {:?} is not supported by {}", preset, name));
+        }
+        if let Some(tune) = self.tune.as_deref()
+            && !codec.tunes().contains(&tune)
+        {
+            return Err(format!("tune {:?} is not supported by {}", tune, name));
+        }
+        if !codec.pixel_formats().contains(&self.pixel_format) {
+            return Err(format!(
+                "pixel format {} is not supported by {}",
+                self.pixel_format.name(),
+                name
+            ));
+        }
+        match (codec.crf_range(), self.crf, self.bitrate_kbps) {
+            (None, None, None) => Ok(()),
+            (None, _, _) => Err(format!("{} is lossless and takes no crf or bitrate", name)),
+            (Some(_), Some(_), Some(_)) => Err("set either crf or bitrate_kbps, not both".to_string()),
+            (Some(_), None, None) => Err(format!("{} needs crf or bitrate_kbps", name)),
+            (Some((lo, hi)), Some(crf), None) if crf < lo || crf > hi => Err(format!(
+                "crf {} is outside {}..={} for {}",
+                crf, lo, hi, name
+            )),
+            (Some(_), None, Some(0)) => Err("bitrate_kbps must be positive".to_string()),
+            _ => Ok(()),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum VideoSinkKind {
+    Ffmpeg,
+    ImageSequence,
+    RawBgra,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ImageFormat {
+    Png,
+    Jpeg,
+    Webp,
+}
+
+impl ImageFormat {
+    pub fn extension(self) -> &'static str {
+        match self {
+            ImageFormat::Png => "png",
+            ImageFormat::Jpeg => "jpg",
+            ImageFormat::Webp => "webp",
+        }
+    }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -111,6 +718,20 @@ pub struct Meta {
     pub record_fps: u32,
     pub build: BuildInfo,
     pub notes: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub encoder_command: Option<Vec<String>>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub renditions: Vec<RenditionMeta>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RenditionMeta {
+    pub name: String,
+    pub width: u32,
+    pub height: u32,
+    pub video: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub encoder_command: Option<Vec<String>>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -128,11 +749,108 @@ pub struct FrameRecord {
     pub width: u32,
     pub height: u32,
     pub data: Vec<u8>,
+    #[serde(default)]
+    pub pacing: FramePacing,
+    // The unscaled BGRA source image, when the capture keeps it for
+    // renditions that resize from the source.
+    #[serde(skip)]
+    pub source: Option<Arc<Vec<u8>>>,
+}
+
+// How the capture source arrived at this frame: `lateness_ms` is the delay
+// past the scheduled pacing slot, `skipped_source_frames` counts source frames
+// discarded because they came before the slot, `missed_slots` counts slots
+// that passed without a frame, and `duplicated` marks a repeated image.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+pub struct FramePacing {
+    pub lateness_ms: f32,
+    pub skipped_source_frames: u32,
+    pub missed_slots: u32,
+    pub duplicated: bool,
+}
+
+// `dropped` means pacing slots were missed before this frame; the frame itself
+// was captured. `duplicated` means the capture repeated the previous image
+// and takes precedence.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum FrameStatus {
+    #[default]
+    Captured,
+    Dropped,
+    Duplicated,
+}
+
+// A frame on its way to the encoder: its action step and the position it
+// was given in the chunk's video.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct FrameRef {
+    pub step_index: StepIndex,
+    pub video_frame: u64,
+}
+
+// One line of frames.jsonl per frame written to the video. `video_frame` is
+// the position in the video (the chunk's video for segmented sessions), which
+// differs from `frame_index` after pauses.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct FrameTiming {
+    pub step_index: StepIndex,
+    pub frame_index: StepIndex,
+    pub video_frame: u64,
+    pub qpc_ts: QpcTimestamp,
+    pub src_width: u32,
+    pub src_height: u32,
+    pub lateness_ms: f32,
+    pub skipped_source_frames: u32,
+    pub missed_slots: u32,
+    pub status: FrameStatus,
+    // Encoder queue state right after this frame was queued.
+    #[serde(default)]
+    pub queue_depth: u32,
+    #[serde(default)]
+    pub queue_stall_ms: f32,
+    #[serde(default)]
+    pub queue_evicted: u32,
+    // Earlier frames the queue dropped to make room for this one; they never
+    // reached at least one output.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub evicted_frames: Vec<FrameRef>,
+}
+
+impl FrameTiming {
+    pub fn from_frame(frame: &FrameRecord, step_index: StepIndex, video_frame: u64) -> Self {
+        let pacing = frame.pacing;
+        let status = if pacing.duplicated {
+            FrameStatus::Duplicated
+        } else if pacing.missed_slots > 0 {
+            FrameStatus::Dropped
+        } else {
+            FrameStatus::Captured
+        };
+        Self {
+            step_index,
+            frame_index: frame.step_index,
+            video_frame,
+            qpc_ts: frame.qpc_ts,
+            src_width: frame.src_width,
+            src_height: frame.src_height,
+            lateness_ms: pacing.lateness_ms,
+            skipped_source_frames: pacing.skipped_source_frames,
+            missed_slots: pacing.missed_slots,
+            status,
+            queue_depth: 0,
+            queue_stall_ms: 0.0,
+            queue_evicted: 0,
+            evicted_frames: Vec::new(),
+        }
+    }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ActionSnapshot {
     pub step_index: StepIndex,
+    #[serde(default)]
+    pub frame_index: StepIndex,
     pub qpc_ts: QpcTimestamp,
     pub window: WindowState,
     pub mouse: MouseSnapshot,
@@ -153,7 +871,7 @@ pub struct MouseSnapshot {
     pub cursor: CursorSample,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
 pub struct MouseButtons {
     pub left: bool,
     pub right: bool,
@@ -169,7 +887,7 @@ pub struct CursorSample {
     pub y_norm: f32,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
 pub struct KeyboardSnapshot {
     pub down: Vec<String>,
     pub pressed: Vec<String>,
@@ -184,9 +902,12 @@ impl Options {
                 api: CaptureApi::WindowsGraphicsCapture,
                 fps: CAPTURE_FPS,
                 record_resolution: [RECORD_WIDTH, RECORD_HEIGHT],
-                resize_mode: ResizeMode::Letterbox,
+                resize_mode: ResizeMode::BLACK_LETTERBOX,
+                resize_filter: ResizeFilter::Area,
+                source_crop: None,
                 color_format: ColorFormat::Bgra8,
                 include_cursor_in_video: true,
+                cursor_style: CursorStyle::Arrow,
                 target: CaptureTarget {
                     method: "gui".to_string(),
                     window_title: None,
@@ -204,24 +925,20 @@ impl Options {
                 clock: ClockType::Qpc,
                 step_ms: STEP_MS,
                 fps: CAPTURE_FPS,
+                alignment: ActionAlignment::Preceding,
+                action_steps_per_frame: 1,
             },
             auto_events: AutoEventsOptions {
                 enabled: false,
                 roi_config: "rois_config_1280x720.json".to_string(),
                 stability_frames: 3,
+                rules: None,
             },
-        }
-    }
-}
-
-impl Default for MouseButtons {
-    fn default() -> Self {
-        Self {
-            left: false,
-            right: false,
-            middle: false,
-            x1: false,
-            x2: false,
+            markers: MarkerOptions::default(),
+            idle: IdleOptions::default(),
+            video: VideoOutputOptions::default(),
+            segment: SegmentOptions::default(),
+            privacy: PrivacyOptions::default(),
         }
     }
 }
@@ -236,16 +953,6 @@ impl Default for CursorSample {
     }
 }
 
-impl Default for KeyboardSnapshot {
-    fn default() -> Self {
-        Self {
-            down: Vec::new(),
-            pressed: Vec::new(),
-            released: Vec::new(),
-        }
-    }
-}
-
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct InputEvent {
     pub qpc_ts: QpcTimestamp,
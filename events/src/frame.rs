@@ -0,0 +1,79 @@
+use collector_core::FrameRecord;
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Rect {
+    pub x: u32,
+    pub y: u32,
+    pub w: u32,
+    pub h: u32,
+}
+
+impl Rect {
+    pub fn scaled(&self, from_w: u32, from_h: u32, to_w: u32, to_h: u32) -> Rect {
+        if from_w == 0 || from_h == 0 || (from_w == to_w && from_h == to_h) {
+            return self.clamped(to_w, to_h);
+        }
+        let sx = to_w as f64 / from_w as f64;
+        let sy = to_h as f64 / from_h as f64;
+        Rect {
+            x: (self.x as f64 * sx).round() as u32,
+            y: (self.y as f64 * sy).round() as u32,
+            w: (self.w as f64 * sx).round().max(1.0) as u32,
+            h: (self.h as f64 * sy).round().max(1.0) as u32,
+        }
+        .clamped(to_w, to_h)
+    }
+
+    pub fn clamped(&self, width: u32, height: u32) -> Rect {
+        let x = self.x.min(width);
+        let y = self.y.min(height);
+        Rect {
+            x,
+            y,
+            w: self.w.min(width - x),
+            h: self.h.min(height - y),
+        }
+    }
+
+    pub fn area(&self) -> usize {
+        self.w as usize * self.h as usize
+    }
+}
+
+pub(crate) fn bgra_luma(pixel: &[u8]) -> u8 {
+    let b = pixel[0] as u32;
+    let g = pixel[1] as u32;
+    let r = pixel[2] as u32;
+    ((29 * b + 150 * g + 77 * r) >> 8) as u8
+}
+
+pub(crate) fn roi_luma(frame: &FrameRecord, rect: Rect) -> Vec<u8> {
+    let rect = rect.clamped(frame.width, frame.height);
+    let mut out = Vec::with_capacity(rect.area());
+    let stride = frame.width as usize * 4;
+    for row in rect.y..rect.y + rect.h {
+        let start = row as usize * stride + rect.x as usize * 4;
+        let end = start + rect.w as usize * 4;
+        if end > frame.data.len() {
+            break;
+        }
+        out.extend(frame.data[start..end].chunks_exact(4).map(bgra_luma));
+    }
+    out
+}
+
+pub(crate) fn mean_abs_diff(a: &[u8], b: &[u8]) -> f32 {
+    if a.len() != b.len() {
+        return f32::MAX;
+    }
+    if a.is_empty() {
+        return 0.0;
+    }
+    let total: u64 = a
+        .iter()
+        .zip(b.iter())
+        .map(|(x, y)| x.abs_diff(*y) as u64)
+        .sum();
+    total as f32 / a.len() as f32
+}
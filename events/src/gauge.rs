@@ -0,0 +1,262 @@
+use std::collections::BTreeMap;
+
+use collector_core::{FrameRecord, QpcTimestamp, StepIndex};
+use serde::{Deserialize, Serialize};
+
+use crate::frame::Rect;
+use crate::roi::RoiConfig;
+use crate::{AutoEvent, AutoEventKind};
+
+const DEFAULT_SMOOTHING: f32 = 0.5;
+const DEFAULT_HYSTERESIS: f32 = 0.02;
+const DEFAULT_LINE_COVERAGE: f32 = 0.5;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum GaugeOrientation {
+    Horizontal,
+    Vertical,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct GaugeSpec {
+    pub name: String,
+    pub roi: String,
+    pub orientation: GaugeOrientation,
+    pub fill_min: [u8; 3],
+    pub fill_max: [u8; 3],
+    #[serde(default = "default_smoothing")]
+    pub smoothing: f32,
+    #[serde(default)]
+    pub thresholds: Vec<f32>,
+    #[serde(default = "default_hysteresis")]
+    pub hysteresis: f32,
+    #[serde(default)]
+    pub stream: bool,
+}
+
+fn default_smoothing() -> f32 {
+    DEFAULT_SMOOTHING
+}
+
+fn default_hysteresis() -> f32 {
+    DEFAULT_HYSTERESIS
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CrossingDirection {
+    Up,
+    Down,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct GaugeSample {
+    pub step_index: StepIndex,
+    pub frame_index: StepIndex,
+    pub qpc_ts: QpcTimestamp,
+    pub values: BTreeMap<String, f32>,
+}
+
+impl GaugeSpec {
+    fn matches(&self, pixel: &[u8]) -> bool {
+        let rgb = [pixel[2], pixel[1], pixel[0]];
+        (0..3).all(|c| rgb[c] >= self.fill_min[c] && rgb[c] <= self.fill_max[c])
+    }
+}
+
+// Fraction of lines across the bar (columns for horizontal bars, rows for
+// vertical ones) where at least half of the pixels fall in the fill range.
+pub fn fill_fraction(frame: &FrameRecord, rect: Rect, spec: &GaugeSpec) -> f32 {
+    let rect = rect.clamped(frame.width, frame.height);
+    if rect.w == 0 || rect.h == 0 {
+        return 0.0;
+    }
+    let stride = frame.width as usize * 4;
+    let pixel = |x: u32, y: u32| {
+        let idx = y as usize * stride + x as usize * 4;
+        &frame.data[idx..idx + 4]
+    };
+    let (lines, span) = match spec.orientation {
+        GaugeOrientation::Horizontal => (rect.w, rect.h),
+        GaugeOrientation::Vertical => (rect.h, rect.w),
+    };
+    let mut filled = 0u32;
+    for line in 0..lines {
+        let mut hits = 0u32;
+        for along in 0..span {
+            let (x, y) = match spec.orientation {
+                GaugeOrientation::Horizontal => (rect.x + line, rect.y + along),
+                GaugeOrientation::Vertical => (rect.x + along, rect.y + line),
+            };
+            if spec.matches(pixel(x, y)) {
+                hits += 1;
+            }
+        }
+        if hits as f32 >= span as f32 * DEFAULT_LINE_COVERAGE {
+            filled += 1;
+        }
+    }
+    filled as f32 / lines as f32
+}
+
+struct GaugeTrack {
+    spec: GaugeSpec,
+    smoothed: Option<f32>,
+    above: Vec<bool>,
+}
+
+pub struct GaugeDetector {
+    config: RoiConfig,
+    tracks: Vec<GaugeTrack>,
+}
+
+impl GaugeDetector {
+    pub fn new(config: RoiConfig) -> Self {
+        let tracks = config
+            .gauges
+            .iter()
+            .cloned()
+            .map(|spec| GaugeTrack {
+                above: vec![false; spec.thresholds.len()],
+                spec,
+                smoothed: None,
+            })
+            .collect();
+        Self { config, tracks }
+    }
+
+    pub fn process_frame(
+        &mut self,
+        frame: &FrameRecord,
+        out: &mut Vec<AutoEvent>,
+    ) -> Option<GaugeSample> {
+        let mut values = BTreeMap::new();
+        for track in self.tracks.iter_mut() {
+            let Some(roi) = self.config.find(&track.spec.roi) else {
+                continue;
+            };
+            let rect = self.config.rect_for_frame(roi, frame.width, frame.height);
+            let raw = fill_fraction(frame, rect, &track.spec);
+            let alpha = track.spec.smoothing.clamp(0.0, 1.0);
+            let first = track.smoothed.is_none();
+            let value = match track.smoothed {
+                Some(prev) => prev + alpha * (raw - prev),
+                None => raw,
+            };
+            track.smoothed = Some(value);
+            if track.spec.stream {
+                values.insert(track.spec.name.clone(), value);
+            }
+
+            for (threshold, above) in track.spec.thresholds.iter().zip(track.above.iter_mut()) {
+                if first {
+                    *above = value >= *threshold;
+                    continue;
+                }
+                let direction = if !*above && value >= threshold + track.spec.hysteresis {
+                    CrossingDirection::Up
+                } else if *above && value < threshold - track.spec.hysteresis {
+                    CrossingDirection::Down
+                } else {
+                    continue;
+                };
+                *above = direction == CrossingDirection::Up;
+                out.push(AutoEvent {
+                    step_index: frame.step_index,
+                    frame_index: frame.step_index,
+                    qpc_ts: frame.qpc_ts,
+                    kind: AutoEventKind::GaugeCrossed {
+                        gauge: track.spec.name.clone(),
+                        threshold: *threshold,
+                        direction,
+                        value,
+                    },
+                });
+            }
+        }
+        if values.is_empty() {
+            return None;
+        }
+        Some(GaugeSample {
+            step_index: frame.step_index,
+            frame_index: frame.step_index,
+            qpc_ts: frame.qpc_ts,
+            values,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_frames::{fill_rect, solid_frame};
+
+    const RED: [u8; 4] = [20, 20, 220, 255];
+
+    fn config(orientation: &str) -> RoiConfig {
+        serde_json::from_str(&format!(
+            r#"{{"width":100,"height":100,"rois":[{{"name":"bar","x":0,"y":0,"w":100,"h":100}}],
+                "gauges":[{{"name":"hp","roi":"bar","orientation":"{}",
+                    "fill_min":[180,0,0],"fill_max":[255,60,60],
+                    "smoothing":1.0,"thresholds":[0.5],"stream":true}}]}}"#,
+            orientation
+        ))
+        .unwrap()
+    }
+
+    fn bar_frame(step: u64, fill: u32, vertical: bool) -> FrameRecord {
+        let mut frame = solid_frame(step, 100, 100, [0, 0, 0, 255]);
+        if vertical {
+            fill_rect(&mut frame, 0, 100 - fill, 100, fill, RED);
+        } else {
+            fill_rect(&mut frame, 0, 0, fill, 100, RED);
+        }
+        frame
+    }
+
+    #[test]
+    fn measures_horizontal_and_vertical_fill() {
+        let config = config("horizontal");
+        let rect = config.rect_for_frame(&config.rois[0], 100, 100);
+        let value = fill_fraction(&bar_frame(0, 30, false), rect, &config.gauges[0]);
+        assert!((value - 0.3).abs() < 1e-6);
+
+        let config = self::config("vertical");
+        let value = fill_fraction(&bar_frame(0, 75, true), rect, &config.gauges[0]);
+        assert!((value - 0.75).abs() < 1e-6);
+    }
+
+    #[test]
+    fn emits_threshold_crossings_and_samples() {
+        let mut detector = GaugeDetector::new(config("horizontal"));
+        let mut events = Vec::new();
+        let fills = [80, 70, 51, 40, 49, 60];
+        let mut samples = Vec::new();
+        for (step, fill) in fills.iter().enumerate() {
+            let frame = bar_frame(step as u64, *fill, false);
+            samples.push(detector.process_frame(&frame, &mut events).unwrap());
+        }
+        assert_eq!(events.len(), 2);
+        assert!(matches!(events[0].kind, AutoEventKind::GaugeCrossed {
+            direction: CrossingDirection::Down, .. }));
+        assert_eq!(events[0].step_index, 3);
+        assert!(matches!(events[1].kind, AutoEventKind::GaugeCrossed {
+            direction: CrossingDirection::Up, .. }));
+        assert_eq!(events[1].step_index, 5);
+        assert!((samples[2].values["hp"] - 0.51).abs() < 1e-6);
+    }
+
+    #[test]
+    fn smoothing_averages_frames() {
+        let mut config = config("horizontal");
+        config.gauges[0].smoothing = 0.5;
+        let mut detector = GaugeDetector::new(config);
+        let mut events = Vec::new();
+        detector.process_frame(&bar_frame(0, 100, false), &mut events);
+        let sample = detector
+            .process_frame(&bar_frame(1, 0, false), &mut events)
+            .unwrap();
+        assert!((sample.values["hp"] - 0.5).abs() < 1e-6);
+    }
+}
@@ -1,14 +1,225 @@
-pub fn add(left: u64, right: u64) -> u64 {
-    left + right
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use collector_core::{AutoEventsOptions, FrameRecord, MarkerKind, QpcTimestamp, StepIndex};
+use serde::{Deserialize, Serialize};
+
+mod frame;
+mod gauge;
+mod marker;
+mod roi;
+mod rules;
+mod scene;
+mod template;
+
+pub use frame::Rect;
+pub use gauge::{
+    fill_fraction, CrossingDirection, GaugeDetector, GaugeOrientation, GaugeSample, GaugeSpec,
+};
+pub use marker::MarkerMap;
+pub use roi::{RoiChangeDetector, RoiConfig, RoiRect};
+pub use rules::{Condition, Rule, RuleEngine, RuleKind, RuleSet};
+pub use scene::{FrameSignature, SceneClass, SceneDetector, ScenePhase, SceneSpec};
+pub use template::{match_template, GrayImage, TemplateDetector, TemplateSpec};
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct AutoEvent {
+    pub step_index: StepIndex,
+    pub frame_index: StepIndex,
+    pub qpc_ts: QpcTimestamp,
+    #[serde(flatten)]
+    pub kind: AutoEventKind,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum AutoEventKind {
+    RoiChanged {
+        roi: String,
+        since_step: StepIndex,
+        diff: f32,
+    },
+    Appeared {
+        template: String,
+        roi: String,
+        score: f32,
+    },
+    Disappeared {
+        template: String,
+        roi: String,
+        score: f32,
+    },
+    GaugeCrossed {
+        gauge: String,
+        threshold: f32,
+        direction: CrossingDirection,
+        value: f32,
+    },
+    Scene {
+        class: SceneClass,
+        phase: ScenePhase,
+        since_step: StepIndex,
+        score: f32,
+    },
+    Marker {
+        marker: MarkerKind,
+        key: String,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        discard_from_step: Option<StepIndex>,
+    },
+    Rule {
+        rule: String,
+        since_step: StepIndex,
+    },
+    Paused {
+        reason: PauseReason,
+        since_step: StepIndex,
+    },
+    Resumed {
+        reason: PauseReason,
+        paused_step: StepIndex,
+    },
+}
+
+impl AutoEvent {
+    // Frame detectors count in frames. Moves `step_index` and any
+    // `since_step` to action steps of `steps_per_frame` per frame.
+    pub fn frames_to_action_steps(&mut self, steps_per_frame: u64) {
+        self.step_index = self.frame_index.saturating_mul(steps_per_frame);
+        if let AutoEventKind::RoiChanged { since_step, .. } | AutoEventKind::Scene { since_step, .. } =
+            &mut self.kind
+        {
+            *since_step = since_step.saturating_mul(steps_per_frame);
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PauseReason {
+    Idle,
+    Operator,
+}
+
+pub trait FrameDetector {
+    fn process_frame(&mut self, frame: &FrameRecord, out: &mut Vec<AutoEvent>);
+}
+
+#[derive(Debug, Default)]
+pub struct FrameEvents {
+    pub events: Vec<AutoEvent>,
+    pub gauge_sample: Option<GaugeSample>,
+}
+
+#[derive(Default)]
+pub struct AutoEventEngine {
+    detectors: Vec<Box<dyn FrameDetector>>,
+    gauges: Option<GaugeDetector>,
+}
+
+impl AutoEventEngine {
+    pub fn from_options(options: &AutoEventsOptions, base_dir: &Path) -> io::Result<Self> {
+        let config_path = resolve_config_path(base_dir, &options.roi_config);
+        let config = RoiConfig::load(&config_path)?;
+        let config_dir = config_path
+            .parent()
+            .map(Path::to_path_buf)
+            .unwrap_or_default();
+        let mut engine = Self::default();
+        if !config.templates.is_empty() {
+            engine.push_detector(TemplateDetector::load(
+                &config,
+                &config_dir,
+                options.stability_frames,
+            )?);
+        }
+        if !config.gauges.is_empty() {
+            engine.gauges = Some(GaugeDetector::new(config.clone()));
+        }
+        if let Some(scene) = config.scene.clone() {
+            engine.push_detector(SceneDetector::new(scene, options.stability_frames));
+        }
+        engine.push_detector(RoiChangeDetector::new(config, options.stability_frames));
+        Ok(engine)
+    }
+
+    pub fn push_detector<D: FrameDetector + 'static>(&mut self, detector: D) {
+        self.detectors.push(Box::new(detector));
+    }
+
+    pub fn set_gauges(&mut self, gauges: GaugeDetector) {
+        self.gauges = Some(gauges);
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.detectors.is_empty() && self.gauges.is_none()
+    }
+
+    pub fn process_frame(&mut self, frame: &FrameRecord) -> FrameEvents {
+        let mut out = FrameEvents::default();
+        for detector in self.detectors.iter_mut() {
+            detector.process_frame(frame, &mut out.events);
+        }
+        if let Some(gauges) = self.gauges.as_mut() {
+            out.gauge_sample = gauges.process_frame(frame, &mut out.events);
+        }
+        out
+    }
+}
+
+pub fn resolve_config_path(base_dir: &Path, name: &str) -> PathBuf {
+    let path = PathBuf::from(name);
+    if path.is_absolute() {
+        return path;
+    }
+    let candidate = base_dir.join(&path);
+    if candidate.exists() { candidate } else { path }
+}
+
+pub(crate) fn load_json<T: for<'de> Deserialize<'de>>(path: &Path) -> io::Result<T> {
+    let text = fs::read_to_string(path).map_err(|err| {
+        io::Error::new(
+            err.kind(),
+            format!("failed to read {}: {}", path.display(), err),
+        )
+    })?;
+    serde_json::from_str(&text).map_err(|err| {
+        io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!("invalid config {}: {}", path.display(), err),
+        )
+    })
 }
 
 #[cfg(test)]
-mod tests {
-    use super::*;
+pub(crate) mod test_frames {
+    use collector_core::{FramePacing, FrameRecord};
+
+    pub fn solid_frame(step_index: u64, width: u32, height: u32, bgra: [u8; 4]) -> FrameRecord {
+        let mut data = Vec::with_capacity((width * height * 4) as usize);
+        for _ in 0..width * height {
+            data.extend_from_slice(&bgra);
+        }
+        FrameRecord {
+            step_index,
+            qpc_ts: step_index * 100,
+            src_width: width,
+            src_height: height,
+            width,
+            height,
+            data,
+            pacing: FramePacing::default(),
+            source: None,
+        }
+    }
 
-    #[test]
-    fn it_works() {
-        let result = add(2, 2);
-        assert_eq!(result, 4);
+    pub fn fill_rect(frame: &mut FrameRecord, x: u32, y: u32, w: u32, h: u32, bgra: [u8; 4]) {
+        for row in y..(y + h).min(frame.height) {
+            for col in x..(x + w).min(frame.width) {
+                let idx = ((row * frame.width + col) * 4) as usize;
+                frame.data[idx..idx + 4].copy_from_slice(&bgra);
+            }
+        }
     }
 }
@@ -0,0 +1,163 @@
+use std::collections::HashSet;
+
+use collector_core::{
+    InputEvent, InputEventKind, MarkerBinding, MarkerOptions, QpcTimestamp, StepIndex,
+};
+
+use crate::{AutoEvent, AutoEventKind};
+
+pub struct MarkerMap {
+    bindings: Vec<MarkerBinding>,
+    strip_from_actions: bool,
+    held: HashSet<String>,
+}
+
+impl MarkerMap {
+    pub fn new(options: &MarkerOptions) -> Self {
+        Self {
+            bindings: options.bindings.clone(),
+            strip_from_actions: options.strip_from_actions,
+            held: HashSet::new(),
+        }
+    }
+
+    pub fn strips_actions(&self) -> bool {
+        self.strip_from_actions
+    }
+
+    pub fn is_marker_key(&self, key: &str) -> bool {
+        self.bindings.iter().any(|binding| binding.key == key)
+    }
+
+    // Marker presses inside a frame window are assigned to the action step
+    // whose sub-window contains them. Held keys only fire once.
+    pub fn detect(
+        &mut self,
+        events: &[InputEvent],
+        window_start: QpcTimestamp,
+        window_end: QpcTimestamp,
+        frame_index: StepIndex,
+        action_steps_per_frame: u32,
+        action_step_ms: f64,
+    ) -> Vec<AutoEvent> {
+        let steps = action_steps_per_frame.max(1) as u64;
+        let span = window_end.saturating_sub(window_start).max(1);
+        let mut out = Vec::new();
+        for event in events {
+            match &event.kind {
+                InputEventKind::KeyDown { key } => {
+                    let Some(binding) = self.bindings.iter().find(|binding| &binding.key == key)
+                    else {
+                        continue;
+                    };
+                    if !self.held.insert(key.clone()) {
+                        continue;
+                    }
+                    let offset = event.qpc_ts.saturating_sub(window_start);
+                    let sub_step = (offset.saturating_mul(steps) / span).min(steps - 1);
+                    let step_index = frame_index.saturating_mul(steps) + sub_step;
+                    let discard_from_step = (binding.discard_secs > 0.0).then(|| {
+                        let discard_steps =
+                            (binding.discard_secs as f64 * 1000.0 / action_step_ms.max(1.0)).ceil();
+                        step_index.saturating_sub(discard_steps as u64)
+                    });
+                    out.push(AutoEvent {
+                        step_index,
+                        frame_index,
+                        qpc_ts: event.qpc_ts,
+                        kind: AutoEventKind::Marker {
+                            marker: binding.marker,
+                            key: key.clone(),
+                            discard_from_step,
+                        },
+                    });
+                }
+                InputEventKind::KeyUp { key } => {
+                    self.held.remove(key);
+                }
+                _ => {}
+            }
+        }
+        out
+    }
+
+    pub fn strip(&self, events: &[InputEvent]) -> Vec<InputEvent> {
+        events
+            .iter()
+            .filter(|event| match &event.kind {
+                InputEventKind::KeyDown { key } | InputEventKind::KeyUp { key } => {
+                    !self.is_marker_key(key)
+                }
+                _ => true,
+            })
+            .cloned()
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use collector_core::MarkerKind;
+
+    fn key(qpc_ts: u64, key: &str, down: bool) -> InputEvent {
+        let key = key.to_string();
+        InputEvent {
+            qpc_ts,
+            kind: if down {
+                InputEventKind::KeyDown { key }
+            } else {
+                InputEventKind::KeyUp { key }
+            },
+        }
+    }
+
+    #[test]
+    fn marker_press_maps_to_action_step() {
+        let mut markers = MarkerMap::new(&MarkerOptions::default());
+        let events = vec![
+            key(150, "W", true),
+            key(160, "Ten", true),
+            key(170, "Ten", true),
+            key(180, "Ten", false),
+        ];
+        let found = markers.detect(&events, 100, 200, 3, 2, 100.0);
+        assert_eq!(found.len(), 1);
+        assert_eq!(found[0].step_index, 7);
+        assert_eq!(found[0].frame_index, 3);
+        assert_eq!(
+            found[0].kind,
+            AutoEventKind::Marker {
+                marker: MarkerKind::Interesting,
+                key: "Ten".to_string(),
+                discard_from_step: None,
+            }
+        );
+    }
+
+    #[test]
+    fn discard_marker_records_first_discarded_step() {
+        let mut markers = MarkerMap::new(&MarkerOptions::default());
+        let found = markers.detect(&[key(100, "Eleven", true)], 100, 300, 80, 1, 200.0);
+        match &found[0].kind {
+            AutoEventKind::Marker {
+                marker,
+                discard_from_step,
+                ..
+            } => {
+                assert_eq!(*marker, MarkerKind::Discard);
+                assert_eq!(*discard_from_step, Some(30));
+            }
+            other => panic!("unexpected event {:?}", other),
+        }
+    }
+
+    #[test]
+    fn strip_removes_marker_keys_only() {
+        let markers = MarkerMap::new(&MarkerOptions::default());
+        let events = vec![key(1, "Eight", true), key(2, "W", true), key(3, "Eight", false)];
+        let kept = markers.strip(&events);
+        assert_eq!(kept.len(), 1);
+        assert_eq!(kept[0].qpc_ts, 2);
+    }
+}
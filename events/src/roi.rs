@@ -0,0 +1,293 @@
+use std::io;
+use std::path::Path;
+
+use collector_core::{FrameRecord, StepIndex};
+use serde::{Deserialize, Serialize};
+
+use crate::frame::{mean_abs_diff, roi_luma, Rect};
+use crate::gauge::GaugeSpec;
+use crate::scene::SceneSpec;
+use crate::template::TemplateSpec;
+use crate::{load_json, AutoEvent, AutoEventKind, FrameDetector};
+
+const DEFAULT_CHANGE_THRESHOLD: f32 = 12.0;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RoiConfig {
+    #[serde(default)]
+    pub width: u32,
+    #[serde(default)]
+    pub height: u32,
+    #[serde(default)]
+    pub rois: Vec<RoiRect>,
+    #[serde(default)]
+    pub templates: Vec<TemplateSpec>,
+    #[serde(default)]
+    pub gauges: Vec<GaugeSpec>,
+    #[serde(default)]
+    pub scene: Option<SceneSpec>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RoiRect {
+    pub name: String,
+    pub x: u32,
+    pub y: u32,
+    pub w: u32,
+    pub h: u32,
+    #[serde(default = "default_change_threshold")]
+    pub threshold: f32,
+}
+
+fn default_change_threshold() -> f32 {
+    DEFAULT_CHANGE_THRESHOLD
+}
+
+impl RoiConfig {
+    pub fn load(path: &Path) -> io::Result<Self> {
+        let config: RoiConfig = load_json(path)?;
+        config.validate()?;
+        Ok(config)
+    }
+
+    pub fn validate(&self) -> io::Result<()> {
+        for (idx, roi) in self.rois.iter().enumerate() {
+            if roi.name.trim().is_empty() {
+                return Err(invalid(format!("roi #{} has an empty name", idx)));
+            }
+            if roi.w == 0 || roi.h == 0 {
+                return Err(invalid(format!("roi {} has an empty size", roi.name)));
+            }
+            if self.rois[..idx].iter().any(|other| other.name == roi.name) {
+                return Err(invalid(format!("duplicate roi name {}", roi.name)));
+            }
+            if self.width > 0
+                && self.height > 0
+                && (roi.x as u64 + roi.w as u64 > self.width as u64
+                    || roi.y as u64 + roi.h as u64 > self.height as u64)
+            {
+                return Err(invalid(format!(
+                    "roi {} is outside the {}x{} record space",
+                    roi.name, self.width, self.height
+                )));
+            }
+        }
+        for template in &self.templates {
+            if self.find(&template.roi).is_none() {
+                return Err(invalid(format!(
+                    "template {} references unknown roi {}",
+                    template.name, template.roi
+                )));
+            }
+        }
+        for gauge in &self.gauges {
+            if self.find(&gauge.roi).is_none() {
+                return Err(invalid(format!(
+                    "gauge {} references unknown roi {}",
+                    gauge.name, gauge.roi
+                )));
+            }
+            if (0..3).any(|c| gauge.fill_min[c] > gauge.fill_max[c]) {
+                return Err(invalid(format!(
+                    "gauge {} has fill_min above fill_max",
+                    gauge.name
+                )));
+            }
+            if !(gauge.smoothing > 0.0 && gauge.smoothing <= 1.0) {
+                return Err(invalid(format!(
+                    "gauge {} smoothing must be in (0, 1]",
+                    gauge.name
+                )));
+            }
+        }
+        Ok(())
+    }
+
+    pub fn find(&self, name: &str) -> Option<&RoiRect> {
+        self.rois.iter().find(|roi| roi.name == name)
+    }
+
+    pub fn rect_for_frame(&self, roi: &RoiRect, frame_w: u32, frame_h: u32) -> Rect {
+        roi.rect().scaled(self.width, self.height, frame_w, frame_h)
+    }
+}
+
+impl RoiRect {
+    pub fn rect(&self) -> Rect {
+        Rect {
+            x: self.x,
+            y: self.y,
+            w: self.w,
+            h: self.h,
+        }
+    }
+}
+
+fn invalid(message: String) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message)
+}
+
+struct RoiTrack {
+    reference: Option<Vec<u8>>,
+    candidate: Option<Vec<u8>>,
+    candidate_since: StepIndex,
+    candidate_frames: u32,
+}
+
+pub struct RoiChangeDetector {
+    config: RoiConfig,
+    stability_frames: u32,
+    tracks: Vec<RoiTrack>,
+}
+
+impl RoiChangeDetector {
+    pub fn new(config: RoiConfig, stability_frames: u32) -> Self {
+        let tracks = config
+            .rois
+            .iter()
+            .map(|_| RoiTrack {
+                reference: None,
+                candidate: None,
+                candidate_since: 0,
+                candidate_frames: 0,
+            })
+            .collect();
+        Self {
+            config,
+            stability_frames: stability_frames.max(1),
+            tracks,
+        }
+    }
+}
+
+impl FrameDetector for RoiChangeDetector {
+    fn process_frame(&mut self, frame: &FrameRecord, out: &mut Vec<AutoEvent>) {
+        for (roi, track) in self.config.rois.iter().zip(self.tracks.iter_mut()) {
+            let rect = self.config.rect_for_frame(roi, frame.width, frame.height);
+            let luma = roi_luma(frame, rect);
+            let reference = match track.reference.as_ref() {
+                Some(reference) => reference,
+                None => {
+                    track.reference = Some(luma);
+                    continue;
+                }
+            };
+
+            let diff = mean_abs_diff(&luma, reference);
+            if diff <= roi.threshold {
+                track.candidate = None;
+                track.candidate_frames = 0;
+                continue;
+            }
+
+            let same_candidate = track
+                .candidate
+                .as_ref()
+                .map(|candidate| mean_abs_diff(&luma, candidate) <= roi.threshold)
+                .unwrap_or(false);
+            if same_candidate {
+                track.candidate_frames = track.candidate_frames.saturating_add(1);
+            } else {
+                track.candidate_since = frame.step_index;
+                track.candidate_frames = 1;
+            }
+            track.candidate = Some(luma);
+
+            if track.candidate_frames >= self.stability_frames {
+                out.push(AutoEvent {
+                    step_index: frame.step_index,
+                    frame_index: frame.step_index,
+                    qpc_ts: frame.qpc_ts,
+                    kind: AutoEventKind::RoiChanged {
+                        roi: roi.name.clone(),
+                        since_step: track.candidate_since,
+                        diff,
+                    },
+                });
+                track.reference = track.candidate.take();
+                track.candidate_frames = 0;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_frames::{fill_rect, solid_frame};
+
+    fn config() -> RoiConfig {
+        serde_json::from_str(
+            r#"{"width":64,"height":32,"rois":[
+                {"name":"left","x":0,"y":0,"w":16,"h":16},
+                {"name":"right","x":32,"y":0,"w":16,"h":16,"threshold":4.0}
+            ]}"#,
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn change_is_reported_after_stability_frames() {
+        let mut detector = RoiChangeDetector::new(config(), 3);
+        let mut events = Vec::new();
+        for step in 0..8 {
+            let mut frame = solid_frame(step, 64, 32, [0, 0, 0, 255]);
+            if step >= 3 {
+                fill_rect(&mut frame, 0, 0, 16, 16, [255, 255, 255, 255]);
+            }
+            detector.process_frame(&frame, &mut events);
+        }
+        assert_eq!(events.len(), 1);
+        assert_eq!(events[0].step_index, 5);
+        match &events[0].kind {
+            AutoEventKind::RoiChanged {
+                roi, since_step, ..
+            } => {
+                assert_eq!(roi, "left");
+                assert_eq!(*since_step, 3);
+            }
+            other => panic!("unexpected event {:?}", other),
+        }
+        // Four action steps per frame.
+        let mut event = events[0].clone();
+        event.frames_to_action_steps(4);
+        assert_eq!((event.step_index, event.frame_index), (20, 5));
+        assert!(matches!(event.kind, AutoEventKind::RoiChanged { since_step: 12, .. }));
+    }
+
+    #[test]
+    fn flicker_shorter_than_stability_is_ignored() {
+        let mut detector = RoiChangeDetector::new(config(), 3);
+        let mut events = Vec::new();
+        for step in 0..10 {
+            let mut frame = solid_frame(step, 64, 32, [0, 0, 0, 255]);
+            if step % 2 == 1 {
+                fill_rect(&mut frame, 32, 0, 16, 16, [200, 200, 200, 255]);
+            }
+            detector.process_frame(&frame, &mut events);
+        }
+        assert!(events.is_empty());
+    }
+
+    #[test]
+    fn rois_are_scaled_to_frame_size() {
+        let config = config();
+        let rect = config.rect_for_frame(&config.rois[1], 128, 64);
+        assert_eq!(rect, Rect { x: 64, y: 0, w: 32, h: 32 });
+    }
+
+    #[test]
+    fn rejects_roi_outside_record_space() {
+        let config: RoiConfig = serde_json::from_str(
+            r#"{"width":64,"height":32,"rois":[{"name":"bad","x":60,"y":0,"w":16,"h":16}]}"#,
+        )
+        .unwrap();
+        assert!(config.validate().is_err());
+        // Would wrap around in u32.
+        let config: RoiConfig = serde_json::from_str(
+            r#"{"width":64,"height":32,"rois":[{"name":"bad","x":4294967295,"y":0,"w":2,"h":16}]}"#,
+        )
+        .unwrap();
+        assert!(config.validate().is_err());
+    }
+}
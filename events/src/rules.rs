@@ -0,0 +1,502 @@
+use std::collections::{BTreeMap, VecDeque};
+use std::fs;
+use std::io;
+use std::path::Path;
+
+use collector_core::{ActionSnapshot, MouseButtons, StepIndex};
+use serde::{Deserialize, Serialize};
+
+use crate::{load_json, AutoEvent, AutoEventKind};
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RuleSet {
+    pub rules: Vec<Rule>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Rule {
+    pub name: String,
+    #[serde(flatten)]
+    pub kind: RuleKind,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum RuleKind {
+    // `when` holds on every step for at least `min_ms`.
+    Sustained { when: Condition, min_ms: u64 },
+    // `when` holds on at least `at_least` steps of the last `window_ms`.
+    Count {
+        when: Condition,
+        window_ms: u64,
+        at_least: u32,
+    },
+    // Each condition matches on a later step, all within `within_ms`.
+    Sequence {
+        steps: Vec<Condition>,
+        within_ms: u64,
+    },
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum Condition {
+    KeyDown { key: String },
+    KeyPressed { key: String },
+    ButtonDown { button: String },
+    ButtonPressed { button: String },
+    MouseStill {
+        #[serde(default)]
+        max_delta: i32,
+    },
+    MouseMoved { min_delta: i32 },
+    NoInput,
+    Foreground,
+    AutoEvent {
+        event: String,
+        #[serde(default)]
+        name: Option<String>,
+    },
+    All { of: Vec<Condition> },
+    Any { of: Vec<Condition> },
+    Not { of: Box<Condition> },
+}
+
+impl RuleSet {
+    pub fn load(path: &Path) -> io::Result<Self> {
+        let rules: RuleSet = match path.extension().and_then(|ext| ext.to_str()) {
+            Some("toml") => {
+                let text = fs::read_to_string(path).map_err(|err| {
+                    io::Error::new(
+                        err.kind(),
+                        format!("failed to read {}: {}", path.display(), err),
+                    )
+                })?;
+                toml::from_str(&text).map_err(|err| {
+                    io::Error::new(
+                        io::ErrorKind::InvalidData,
+                        format!("invalid rules {}: {}", path.display(), err),
+                    )
+                })?
+            }
+            _ => load_json(path)?,
+        };
+        rules.validate()?;
+        Ok(rules)
+    }
+
+    pub fn validate(&self) -> io::Result<()> {
+        for (idx, rule) in self.rules.iter().enumerate() {
+            if rule.name.trim().is_empty() {
+                return Err(invalid(format!("rule #{} has an empty name", idx)));
+            }
+            if self.rules[..idx].iter().any(|other| other.name == rule.name) {
+                return Err(invalid(format!("duplicate rule name {}", rule.name)));
+            }
+            match &rule.kind {
+                RuleKind::Count { at_least: 0, .. } => {
+                    return Err(invalid(format!("rule {} needs at_least > 0", rule.name)));
+                }
+                RuleKind::Sequence { steps, .. } if steps.is_empty() => {
+                    return Err(invalid(format!("rule {} has an empty sequence", rule.name)));
+                }
+                _ => {}
+            }
+        }
+        Ok(())
+    }
+}
+
+fn invalid(message: String) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message)
+}
+
+struct StepContext<'a> {
+    snapshot: &'a ActionSnapshot,
+    prev_buttons: Option<&'a MouseButtons>,
+    events: &'a [AutoEvent],
+}
+
+impl Condition {
+    fn eval(&self, ctx: &StepContext) -> bool {
+        let snapshot = ctx.snapshot;
+        let mouse = &snapshot.mouse;
+        match self {
+            Condition::KeyDown { key } => snapshot.keyboard.down.iter().any(|k| k == key),
+            Condition::KeyPressed { key } => snapshot.keyboard.pressed.iter().any(|k| k == key),
+            Condition::ButtonDown { button } => button_state(&mouse.buttons, button),
+            Condition::ButtonPressed { button } => {
+                button_state(&mouse.buttons, button)
+                    && !ctx
+                        .prev_buttons
+                        .map(|prev| button_state(prev, button))
+                        .unwrap_or(false)
+            }
+            Condition::MouseStill { max_delta } => mouse.dx.abs() + mouse.dy.abs() <= *max_delta,
+            Condition::MouseMoved { min_delta } => mouse.dx.abs() + mouse.dy.abs() >= *min_delta,
+            Condition::NoInput => {
+                snapshot.keyboard.down.is_empty()
+                    && snapshot.keyboard.pressed.is_empty()
+                    && mouse.dx == 0
+                    && mouse.dy == 0
+                    && mouse.wheel == 0
+                    && !any_button(&mouse.buttons)
+            }
+            Condition::Foreground => snapshot.window.is_foreground,
+            Condition::AutoEvent { event, name } => ctx
+                .events
+                .iter()
+                .any(|auto| event_matches(auto, event, name.as_deref())),
+            Condition::All { of } => of.iter().all(|cond| cond.eval(ctx)),
+            Condition::Any { of } => of.iter().any(|cond| cond.eval(ctx)),
+            Condition::Not { of } => !of.eval(ctx),
+        }
+    }
+}
+
+fn button_state(buttons: &MouseButtons, button: &str) -> bool {
+    match button {
+        "left" => buttons.left,
+        "right" => buttons.right,
+        "middle" => buttons.middle,
+        "x1" => buttons.x1,
+        "x2" => buttons.x2,
+        _ => false,
+    }
+}
+
+fn any_button(buttons: &MouseButtons) -> bool {
+    buttons.left || buttons.right || buttons.middle || buttons.x1 || buttons.x2
+}
+
+fn event_matches(auto: &AutoEvent, event: &str, name: Option<&str>) -> bool {
+    let (kind, event_name) = match &auto.kind {
+        AutoEventKind::RoiChanged { roi, .. } => ("roi_changed", roi.as_str()),
+        AutoEventKind::Appeared { template, .. } => ("appeared", template.as_str()),
+        AutoEventKind::Disappeared { template, .. } => ("disappeared", template.as_str()),
+        AutoEventKind::GaugeCrossed { gauge, .. } => ("gauge_crossed", gauge.as_str()),
+        AutoEventKind::Scene { .. } => ("scene", ""),
+        AutoEventKind::Marker { .. } => ("marker", ""),
+        AutoEventKind::Rule { rule, .. } => ("rule", rule.as_str()),
+        AutoEventKind::Paused { .. } => ("paused", ""),
+        AutoEventKind::Resumed { .. } => ("resumed", ""),
+    };
+    kind == event && name.map(|name| name == event_name).unwrap_or(true)
+}
+
+fn ms_to_steps(ms: u64, action_step_ms: f64) -> u64 {
+    ((ms as f64 / action_step_ms.max(1.0)).ceil() as u64).max(1)
+}
+
+enum RuleState {
+    Sustained {
+        min_steps: u64,
+        run_start: Option<StepIndex>,
+        fired: bool,
+    },
+    Count {
+        window_steps: u64,
+        hits: VecDeque<StepIndex>,
+        fired: bool,
+    },
+    Sequence {
+        within_steps: u64,
+        next: usize,
+        started: StepIndex,
+    },
+}
+
+pub struct RuleEngine {
+    rules: Vec<(Rule, RuleState)>,
+    pending: BTreeMap<StepIndex, Vec<AutoEvent>>,
+    prev_buttons: Option<MouseButtons>,
+}
+
+impl RuleEngine {
+    pub fn new(rules: RuleSet, action_step_ms: f64) -> Self {
+        let rules = rules
+            .rules
+            .into_iter()
+            .map(|rule| {
+                let state = match &rule.kind {
+                    RuleKind::Sustained { min_ms, .. } => RuleState::Sustained {
+                        min_steps: ms_to_steps(*min_ms, action_step_ms),
+                        run_start: None,
+                        fired: false,
+                    },
+                    RuleKind::Count { window_ms, .. } => RuleState::Count {
+                        window_steps: ms_to_steps(*window_ms, action_step_ms),
+                        hits: VecDeque::new(),
+                        fired: false,
+                    },
+                    RuleKind::Sequence { within_ms, .. } => RuleState::Sequence {
+                        within_steps: ms_to_steps(*within_ms, action_step_ms),
+                        next: 0,
+                        started: 0,
+                    },
+                };
+                (rule, state)
+            })
+            .collect();
+        Self {
+            rules,
+            pending: BTreeMap::new(),
+            prev_buttons: None,
+        }
+    }
+
+    // Auto events are usually known before the snapshot of their step is
+    // aggregated; they are held until that snapshot arrives.
+    pub fn push_auto_event(&mut self, event: &AutoEvent) {
+        self.pending
+            .entry(event.step_index)
+            .or_default()
+            .push(event.clone());
+    }
+
+    pub fn process_snapshot(&mut self, snapshot: &ActionSnapshot) -> Vec<AutoEvent> {
+        let later = self.pending.split_off(&(snapshot.step_index + 1));
+        let events: Vec<AutoEvent> = std::mem::replace(&mut self.pending, later)
+            .into_values()
+            .flatten()
+            .collect();
+        let ctx = StepContext {
+            snapshot,
+            prev_buttons: self.prev_buttons.as_ref(),
+            events: &events,
+        };
+        let step = snapshot.step_index;
+        let mut out = Vec::new();
+        for (rule, state) in self.rules.iter_mut() {
+            let since = match (&rule.kind, state) {
+                (
+                    RuleKind::Sustained { when, .. },
+                    RuleState::Sustained {
+                        min_steps,
+                        run_start,
+                        fired,
+                    },
+                ) => {
+                    if !when.eval(&ctx) {
+                        *run_start = None;
+                        *fired = false;
+                        continue;
+                    }
+                    let start = *run_start.get_or_insert(step);
+                    if *fired || step + 1 - start < *min_steps {
+                        continue;
+                    }
+                    *fired = true;
+                    start
+                }
+                (
+                    RuleKind::Count { when, at_least, .. },
+                    RuleState::Count {
+                        window_steps,
+                        hits,
+                        fired,
+                    },
+                ) => {
+                    if when.eval(&ctx) {
+                        hits.push_back(step);
+                    }
+                    while hits.front().is_some_and(|hit| hit + *window_steps <= step) {
+                        hits.pop_front();
+                    }
+                    if hits.len() < *at_least as usize {
+                        *fired = false;
+                        continue;
+                    }
+                    if *fired {
+                        continue;
+                    }
+                    *fired = true;
+                    hits[0]
+                }
+                (
+                    RuleKind::Sequence { steps, .. },
+                    RuleState::Sequence {
+                        within_steps,
+                        next,
+                        started,
+                    },
+                ) => {
+                    if *next > 0 && step - *started >= *within_steps {
+                        *next = 0;
+                    }
+                    if !steps[*next].eval(&ctx) {
+                        continue;
+                    }
+                    if *next == 0 {
+                        *started = step;
+                    }
+                    *next += 1;
+                    if *next < steps.len() {
+                        continue;
+                    }
+                    *next = 0;
+                    *started
+                }
+                _ => continue,
+            };
+            out.push(AutoEvent {
+                step_index: step,
+                frame_index: snapshot.frame_index,
+                qpc_ts: snapshot.qpc_ts,
+                kind: AutoEventKind::Rule {
+                    rule: rule.name.clone(),
+                    since_step: since,
+                },
+            });
+        }
+        self.prev_buttons = Some(snapshot.mouse.buttons.clone());
+        out
+    }
+
+    // Runs the rules over a recorded session. Earlier rule output in
+    // `auto_events` is ignored so replays can be repeated.
+    pub fn replay(&mut self, snapshots: &[ActionSnapshot], auto_events: &[AutoEvent]) -> Vec<AutoEvent> {
+        for event in auto_events {
+            if !matches!(event.kind, AutoEventKind::Rule { .. }) {
+                self.push_auto_event(event);
+            }
+        }
+        snapshots
+            .iter()
+            .flat_map(|snapshot| self.process_snapshot(snapshot))
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use collector_core::{CursorSample, KeyboardSnapshot, MouseSnapshot, WindowState};
+
+    fn snapshot(step: u64, keys: &[&str], dx: i32, left: bool) -> ActionSnapshot {
+        ActionSnapshot {
+            step_index: step,
+            frame_index: step,
+            qpc_ts: step * 100,
+            window: WindowState {
+                is_foreground: true,
+            },
+            mouse: MouseSnapshot {
+                dx,
+                dy: 0,
+                wheel: 0,
+                buttons: MouseButtons {
+                    left,
+                    ..MouseButtons::default()
+                },
+                cursor: CursorSample {
+                    visible: false,
+                    x_norm: 0.0,
+                    y_norm: 0.0,
+                },
+            },
+            keyboard: KeyboardSnapshot {
+                down: keys.iter().map(|key| key.to_string()).collect(),
+                ..KeyboardSnapshot::default()
+            },
+        }
+    }
+
+    fn fired(events: &[AutoEvent]) -> Vec<(String, StepIndex, StepIndex)> {
+        events
+            .iter()
+            .filter_map(|event| match &event.kind {
+                AutoEventKind::Rule { rule, since_step } => {
+                    Some((rule.clone(), *since_step, event.step_index))
+                }
+                _ => None,
+            })
+            .collect()
+    }
+
+    #[test]
+    fn sustained_rule_fires_once_per_run() {
+        let rules: RuleSet = toml::from_str(
+            r#"
+            [[rules]]
+            name = "sprint"
+            kind = "sustained"
+            min_ms = 300
+            when = { type = "all", of = [
+                { type = "key_down", key = "Shift" },
+                { type = "key_down", key = "W" },
+            ] }
+            "#,
+        )
+        .unwrap();
+        let mut engine = RuleEngine::new(rules, 100.0);
+        let mut snapshots: Vec<_> = (0..6)
+            .map(|step| snapshot(step, &["Shift", "W"], 0, false))
+            .collect();
+        snapshots.push(snapshot(6, &["W"], 0, false));
+        snapshots.extend((7..10).map(|step| snapshot(step, &["Shift", "W"], 0, false)));
+        let out = engine.replay(&snapshots, &[]);
+        assert_eq!(
+            fired(&out),
+            vec![("sprint".to_string(), 0, 2), ("sprint".to_string(), 7, 9)]
+        );
+    }
+
+    #[test]
+    fn count_rule_detects_repeated_clicks_without_movement() {
+        let rules: RuleSet = serde_json::from_str(
+            r#"{"rules":[{"name":"spam_click","kind":"count","window_ms":600,"at_least":3,
+                "when":{"type":"all","of":[
+                    {"type":"button_pressed","button":"left"},
+                    {"type":"mouse_still","max_delta":2}]}}]}"#,
+        )
+        .unwrap();
+        let mut engine = RuleEngine::new(rules, 100.0);
+        let snapshots: Vec<_> = (0..10)
+            .map(|step| snapshot(step, &[], if step < 6 { 0 } else { 30 }, step % 2 == 0))
+            .collect();
+        let out = engine.replay(&snapshots, &[]);
+        assert_eq!(fired(&out), vec![("spam_click".to_string(), 0, 4)]);
+    }
+
+    #[test]
+    fn sequence_rule_uses_auto_events_and_window() {
+        let rules: RuleSet = serde_json::from_str(
+            r#"{"rules":[{"name":"death_then_respawn","kind":"sequence","within_ms":500,
+                "steps":[
+                    {"type":"auto_event","event":"appeared","name":"death_screen"},
+                    {"type":"key_pressed","key":"Enter"}]}]}"#,
+        )
+        .unwrap();
+        let appeared = |step| AutoEvent {
+            step_index: step,
+            frame_index: step,
+            qpc_ts: step * 100,
+            kind: AutoEventKind::Appeared {
+                template: "death_screen".to_string(),
+                roi: "center".to_string(),
+                score: 0.9,
+            },
+        };
+        let mut snapshots: Vec<_> = (0..20).map(|step| snapshot(step, &[], 0, false)).collect();
+        snapshots[8].keyboard.pressed.push("Enter".to_string());
+        snapshots[14].keyboard.pressed.push("Enter".to_string());
+        let mut engine = RuleEngine::new(rules, 100.0);
+        let out = engine.replay(&snapshots, &[appeared(2), appeared(12)]);
+        assert_eq!(
+            fired(&out),
+            vec![("death_then_respawn".to_string(), 12, 14)]
+        );
+    }
+
+    #[test]
+    fn rejects_duplicate_rule_names() {
+        let rules: RuleSet = serde_json::from_str(
+            r#"{"rules":[
+                {"name":"idle","kind":"sustained","min_ms":1000,"when":{"type":"no_input"}},
+                {"name":"idle","kind":"sustained","min_ms":2000,"when":{"type":"no_input"}}]}"#,
+        )
+        .unwrap();
+        assert!(rules.validate().is_err());
+    }
+}
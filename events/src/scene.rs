@@ -0,0 +1,348 @@
+use collector_core::{FrameRecord, StepIndex};
+use serde::{Deserialize, Serialize};
+
+use crate::frame::{bgra_luma, mean_abs_diff};
+use crate::{AutoEvent, AutoEventKind, FrameDetector};
+
+const GRID_W: usize = 32;
+const GRID_H: usize = 18;
+const HIST_BINS: usize = 32;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SceneSpec {
+    pub black_level: f32,
+    pub cut_threshold: f32,
+    pub cut_min_diff: f32,
+    pub fade_step: f32,
+    pub fade_frames: u32,
+    pub static_threshold: f32,
+    pub static_frames: u32,
+}
+
+impl Default for SceneSpec {
+    fn default() -> Self {
+        Self {
+            black_level: 16.0,
+            cut_threshold: 0.5,
+            cut_min_diff: 12.0,
+            fade_step: 3.0,
+            fade_frames: 3,
+            static_threshold: 1.0,
+            static_frames: 10,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SceneClass {
+    Cut,
+    FadeIn,
+    FadeOut,
+    Black,
+    Static,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ScenePhase {
+    Start,
+    End,
+    Instant,
+}
+
+#[derive(Debug, Clone)]
+pub struct FrameSignature {
+    pub grid: Vec<u8>,
+    pub hist: Vec<f32>,
+    pub mean: f32,
+    pub max_cell: u8,
+}
+
+impl FrameSignature {
+    pub fn from_frame(frame: &FrameRecord) -> Self {
+        let (w, h) = (frame.width as usize, frame.height as usize);
+        let mut sums = vec![0u64; GRID_W * GRID_H];
+        let mut counts = vec![0u64; GRID_W * GRID_H];
+        let mut hist = vec![0f32; HIST_BINS];
+        let mut total = 0u64;
+        for y in 0..h {
+            let cell_y = y * GRID_H / h.max(1);
+            let row = &frame.data[y * w * 4..(y + 1) * w * 4];
+            for (x, pixel) in row.chunks_exact(4).enumerate() {
+                let luma = bgra_luma(pixel);
+                let cell = cell_y * GRID_W + x * GRID_W / w.max(1);
+                sums[cell] += luma as u64;
+                counts[cell] += 1;
+                hist[luma as usize * HIST_BINS / 256] += 1.0;
+                total += luma as u64;
+            }
+        }
+        let pixels = (w * h).max(1) as f32;
+        for bin in hist.iter_mut() {
+            *bin /= pixels;
+        }
+        let grid: Vec<u8> = sums
+            .iter()
+            .zip(counts.iter())
+            .map(|(sum, count)| (sum / (*count).max(1)) as u8)
+            .collect();
+        let max_cell = grid.iter().copied().max().unwrap_or(0);
+        Self {
+            grid,
+            hist,
+            mean: total as f32 / pixels,
+            max_cell,
+        }
+    }
+
+    pub fn hist_distance(&self, other: &FrameSignature) -> f32 {
+        0.5 * self
+            .hist
+            .iter()
+            .zip(other.hist.iter())
+            .map(|(a, b)| (a - b).abs())
+            .sum::<f32>()
+    }
+}
+
+#[derive(Default)]
+struct RunState {
+    active: bool,
+    run: u32,
+    since: StepIndex,
+}
+
+impl RunState {
+    // Returns the phase to emit when the debounced state flips.
+    fn update(&mut self, present: bool, step: StepIndex, frames: u32) -> Option<ScenePhase> {
+        if present != self.active {
+            if self.run == 0 {
+                self.since = step;
+            }
+            self.run += 1;
+            if self.run >= frames.max(1) {
+                self.active = present;
+                self.run = 0;
+                return Some(if present {
+                    ScenePhase::Start
+                } else {
+                    ScenePhase::End
+                });
+            }
+        } else {
+            self.run = 0;
+        }
+        None
+    }
+}
+
+pub struct SceneDetector {
+    spec: SceneSpec,
+    stability_frames: u32,
+    prev: Option<FrameSignature>,
+    black: RunState,
+    still: RunState,
+    fade: Option<(SceneClass, u32, StepIndex)>,
+    fade_active: bool,
+}
+
+impl SceneDetector {
+    pub fn new(spec: SceneSpec, stability_frames: u32) -> Self {
+        Self {
+            spec,
+            stability_frames: stability_frames.max(1),
+            prev: None,
+            black: RunState::default(),
+            still: RunState::default(),
+            fade: None,
+            fade_active: false,
+        }
+    }
+
+    fn push(
+        &self,
+        out: &mut Vec<AutoEvent>,
+        frame: &FrameRecord,
+        class: SceneClass,
+        phase: ScenePhase,
+        since_step: StepIndex,
+        score: f32,
+    ) {
+        out.push(AutoEvent {
+            step_index: frame.step_index,
+            frame_index: frame.step_index,
+            qpc_ts: frame.qpc_ts,
+            kind: AutoEventKind::Scene {
+                class,
+                phase,
+                since_step,
+                score,
+            },
+        });
+    }
+}
+
+impl FrameDetector for SceneDetector {
+    fn process_frame(&mut self, frame: &FrameRecord, out: &mut Vec<AutoEvent>) {
+        let signature = FrameSignature::from_frame(frame);
+        let Some(prev) = self.prev.take() else {
+            self.prev = Some(signature);
+            return;
+        };
+        let step = frame.step_index;
+        let diff = mean_abs_diff(&signature.grid, &prev.grid);
+        let hist_distance = signature.hist_distance(&prev);
+        let is_black = signature.mean <= self.spec.black_level
+            && (signature.max_cell as f32) <= self.spec.black_level * 2.0;
+
+        if hist_distance >= self.spec.cut_threshold && diff >= self.spec.cut_min_diff {
+            self.push(
+                out,
+                frame,
+                SceneClass::Cut,
+                ScenePhase::Instant,
+                step,
+                hist_distance,
+            );
+        }
+
+        let delta = signature.mean - prev.mean;
+        let fading = delta.abs() >= self.spec.fade_step && hist_distance < self.spec.cut_threshold;
+        let direction = if delta > 0.0 {
+            SceneClass::FadeIn
+        } else {
+            SceneClass::FadeOut
+        };
+        match self.fade {
+            Some((class, run, since)) if fading && class == direction => {
+                self.fade = Some((class, run + 1, since));
+            }
+            _ => {
+                if let Some((class, _, since)) = self.fade.take()
+                    && self.fade_active
+                {
+                    self.push(out, frame, class, ScenePhase::End, since, signature.mean);
+                }
+                self.fade_active = false;
+                if fading {
+                    self.fade = Some((direction, 1, prev_step(step)));
+                }
+            }
+        }
+        if let Some((class, run, since)) = self.fade
+            && !self.fade_active
+            && run >= self.spec.fade_frames.max(1)
+        {
+            self.fade_active = true;
+            self.push(out, frame, class, ScenePhase::Start, since, signature.mean);
+        }
+
+        if let Some(phase) = self.black.update(is_black, step, self.stability_frames) {
+            let since = self.black.since;
+            self.push(out, frame, SceneClass::Black, phase, since, signature.mean);
+        }
+
+        let still = diff <= self.spec.static_threshold && !is_black;
+        let frames = if self.still.active {
+            self.stability_frames
+        } else {
+            self.spec.static_frames
+        };
+        if let Some(phase) = self.still.update(still, step, frames) {
+            let since = self.still.since;
+            self.push(out, frame, SceneClass::Static, phase, since, diff);
+        }
+
+        self.prev = Some(signature);
+    }
+}
+
+fn prev_step(step: StepIndex) -> StepIndex {
+    step.saturating_sub(1)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_frames::solid_frame;
+
+    fn gradient_frame(step: u64, brightness: f32) -> FrameRecord {
+        let mut frame = solid_frame(step, 64, 36, [0, 0, 0, 255]);
+        for y in 0..36usize {
+            for x in 0..64usize {
+                let value = ((x * 4) as f32 * brightness).min(255.0) as u8;
+                let idx = (y * 64 + x) * 4;
+                frame.data[idx..idx + 3].copy_from_slice(&[value, value, value]);
+            }
+        }
+        frame
+    }
+
+    fn run(frames: Vec<FrameRecord>) -> Vec<AutoEvent> {
+        let mut detector = SceneDetector::new(SceneSpec::default(), 2);
+        let mut events = Vec::new();
+        for frame in frames {
+            detector.process_frame(&frame, &mut events);
+        }
+        events
+    }
+
+    fn classes(events: &[AutoEvent]) -> Vec<(SceneClass, ScenePhase, StepIndex)> {
+        events
+            .iter()
+            .filter_map(|event| match event.kind {
+                AutoEventKind::Scene { class, phase, .. } => Some((class, phase, event.step_index)),
+                _ => None,
+            })
+            .collect()
+    }
+
+    #[test]
+    fn detects_hard_cut() {
+        let mut frames: Vec<_> = (0..3).map(|step| gradient_frame(step, 1.0)).collect();
+        frames.push(solid_frame(3, 64, 36, [255, 255, 255, 255]));
+        let events = run(frames);
+        assert_eq!(
+            classes(&events),
+            vec![(SceneClass::Cut, ScenePhase::Instant, 3)]
+        );
+    }
+
+    #[test]
+    fn detects_fade_out_into_black() {
+        let mut frames = Vec::new();
+        let mut brightness = 1.0f32;
+        for step in 0..16 {
+            frames.push(gradient_frame(step, brightness));
+            brightness *= 0.7;
+        }
+        let found = classes(&run(frames));
+        assert!(found.contains(&(SceneClass::FadeOut, ScenePhase::Start, 3)));
+        assert!(found
+            .iter()
+            .any(|(class, phase, _)| *class == SceneClass::Black && *phase == ScenePhase::Start));
+        assert!(!found.iter().any(|(class, _, _)| *class == SceneClass::Cut));
+    }
+
+    #[test]
+    fn detects_static_screen_start_and_end() {
+        let mut frames: Vec<_> = (0..14).map(|step| gradient_frame(step, 1.0)).collect();
+        for step in 14..17 {
+            frames.push(gradient_frame(step, 0.5 + step as f32 * 0.1));
+        }
+        let found = classes(&run(frames));
+        assert_eq!(found[0], (SceneClass::Static, ScenePhase::Start, 10));
+        assert!(found.contains(&(SceneClass::Static, ScenePhase::End, 15)));
+    }
+
+    #[test]
+    fn black_frames_are_not_static() {
+        let frames: Vec<_> = (0..14)
+            .map(|step| solid_frame(step, 64, 36, [0, 0, 0, 255]))
+            .collect();
+        let found = classes(&run(frames));
+        assert_eq!(found, vec![(SceneClass::Black, ScenePhase::Start, 2)]);
+    }
+}
@@ -0,0 +1,361 @@
+use std::fs::File;
+use std::io::{self, BufReader, Read};
+use std::path::Path;
+
+use collector_core::FrameRecord;
+use serde::{Deserialize, Serialize};
+
+use crate::frame::{roi_luma, Rect};
+use crate::roi::RoiConfig;
+use crate::{AutoEvent, AutoEventKind, FrameDetector};
+
+const DEFAULT_MATCH_THRESHOLD: f32 = 0.8;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TemplateSpec {
+    pub name: String,
+    pub roi: String,
+    pub path: String,
+    #[serde(default = "default_match_threshold")]
+    pub threshold: f32,
+    #[serde(default)]
+    pub stability_frames: Option<u32>,
+}
+
+fn default_match_threshold() -> f32 {
+    DEFAULT_MATCH_THRESHOLD
+}
+
+#[derive(Debug, Clone)]
+pub struct GrayImage {
+    pub width: u32,
+    pub height: u32,
+    pub data: Vec<u8>,
+}
+
+impl GrayImage {
+    pub fn load_png(path: &Path) -> io::Result<Self> {
+        let file = File::open(path).map_err(|err| {
+            io::Error::new(
+                err.kind(),
+                format!("failed to open template {}: {}", path.display(), err),
+            )
+        })?;
+        Self::decode_png(BufReader::new(file))
+    }
+
+    pub fn decode_png<R: Read>(reader: R) -> io::Result<Self> {
+        let mut decoder = png::Decoder::new(reader);
+        decoder.set_transformations(png::Transformations::EXPAND | png::Transformations::STRIP_16);
+        let mut reader = decoder
+            .read_info()
+            .map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err))?;
+        let mut buffer = vec![0u8; reader.output_buffer_size()];
+        let info = reader
+            .next_frame(&mut buffer)
+            .map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err))?;
+        let pixels = &buffer[..info.buffer_size()];
+        let data: Vec<u8> = match info.color_type {
+            png::ColorType::Grayscale => pixels.to_vec(),
+            png::ColorType::GrayscaleAlpha => pixels.chunks_exact(2).map(|px| px[0]).collect(),
+            png::ColorType::Rgb => pixels.chunks_exact(3).map(rgb_luma).collect(),
+            png::ColorType::Rgba => pixels.chunks_exact(4).map(rgb_luma).collect(),
+            png::ColorType::Indexed => {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    "indexed png was not expanded",
+                ));
+            }
+        };
+        Ok(Self {
+            width: info.width,
+            height: info.height,
+            data,
+        })
+    }
+
+    pub fn resized(&self, width: u32, height: u32) -> GrayImage {
+        if width == self.width && height == self.height {
+            return self.clone();
+        }
+        let mut data = Vec::with_capacity(width as usize * height as usize);
+        for y in 0..height {
+            let src_y = (y as u64 * self.height as u64 / height.max(1) as u64) as u32;
+            for x in 0..width {
+                let src_x = (x as u64 * self.width as u64 / width.max(1) as u64) as u32;
+                data.push(self.data[(src_y * self.width + src_x) as usize]);
+            }
+        }
+        GrayImage {
+            width,
+            height,
+            data,
+        }
+    }
+}
+
+fn rgb_luma(pixel: &[u8]) -> u8 {
+    let r = pixel[0] as u32;
+    let g = pixel[1] as u32;
+    let b = pixel[2] as u32;
+    ((77 * r + 150 * g + 29 * b) >> 8) as u8
+}
+
+// Best zero-mean normalized cross-correlation of `template` over every
+// placement inside `region`. Flat windows (zero variance) score 0.
+pub fn match_template(region: &[u8], region_w: u32, region_h: u32, template: &GrayImage) -> f32 {
+    let (tw, th) = (template.width as usize, template.height as usize);
+    let (rw, rh) = (region_w as usize, region_h as usize);
+    if tw == 0 || th == 0 || tw > rw || th > rh || region.len() < rw * rh {
+        return 0.0;
+    }
+    let n = (tw * th) as f64;
+    let t_mean = template.data.iter().map(|v| *v as f64).sum::<f64>() / n;
+    let t_centered: Vec<f64> = template.data.iter().map(|v| *v as f64 - t_mean).collect();
+    let t_norm = t_centered.iter().map(|v| v * v).sum::<f64>().sqrt();
+    if t_norm == 0.0 {
+        return 0.0;
+    }
+
+    let stride = rw + 1;
+    let mut sum = vec![0f64; stride * (rh + 1)];
+    let mut sum_sq = vec![0f64; stride * (rh + 1)];
+    for y in 0..rh {
+        let mut row = 0f64;
+        let mut row_sq = 0f64;
+        for x in 0..rw {
+            let v = region[y * rw + x] as f64;
+            row += v;
+            row_sq += v * v;
+            sum[(y + 1) * stride + x + 1] = sum[y * stride + x + 1] + row;
+            sum_sq[(y + 1) * stride + x + 1] = sum_sq[y * stride + x + 1] + row_sq;
+        }
+    }
+    let window = |table: &[f64], x: usize, y: usize| {
+        table[(y + th) * stride + x + tw] - table[y * stride + x + tw] - table[(y + th) * stride + x]
+            + table[y * stride + x]
+    };
+
+    let mut best = 0f32;
+    for y in 0..=(rh - th) {
+        for x in 0..=(rw - tw) {
+            let s = window(&sum, x, y);
+            let s_sq = window(&sum_sq, x, y);
+            let var = s_sq - s * s / n;
+            if var <= f64::EPSILON {
+                continue;
+            }
+            let mut cross = 0f64;
+            for ty in 0..th {
+                let row = &region[(y + ty) * rw + x..(y + ty) * rw + x + tw];
+                let t_row = &t_centered[ty * tw..(ty + 1) * tw];
+                for (value, t) in row.iter().zip(t_row.iter()) {
+                    cross += *value as f64 * t;
+                }
+            }
+            let score = (cross / (var.sqrt() * t_norm)) as f32;
+            if score > best {
+                best = score;
+            }
+        }
+    }
+    best
+}
+
+struct TemplateTrack {
+    spec: TemplateSpec,
+    image: GrayImage,
+    scaled: Option<GrayImage>,
+    visible: bool,
+    pending_frames: u32,
+}
+
+pub struct TemplateDetector {
+    config: RoiConfig,
+    stability_frames: u32,
+    tracks: Vec<TemplateTrack>,
+}
+
+impl TemplateDetector {
+    pub fn load(config: &RoiConfig, config_dir: &Path, stability_frames: u32) -> io::Result<Self> {
+        let mut images = Vec::with_capacity(config.templates.len());
+        for spec in &config.templates {
+            let path = Path::new(&spec.path);
+            let path = if path.is_absolute() {
+                path.to_path_buf()
+            } else {
+                config_dir.join(path)
+            };
+            images.push(GrayImage::load_png(&path)?);
+        }
+        Ok(Self::new(config.clone(), images, stability_frames))
+    }
+
+    pub fn new(config: RoiConfig, images: Vec<GrayImage>, stability_frames: u32) -> Self {
+        let tracks = config
+            .templates
+            .iter()
+            .cloned()
+            .zip(images)
+            .map(|(spec, image)| TemplateTrack {
+                spec,
+                image,
+                scaled: None,
+                visible: false,
+                pending_frames: 0,
+            })
+            .collect();
+        Self {
+            config,
+            stability_frames: stability_frames.max(1),
+            tracks,
+        }
+    }
+
+    fn search_rect(&self, roi_name: &str, frame: &FrameRecord) -> Option<Rect> {
+        let roi = self.config.find(roi_name)?;
+        Some(self.config.rect_for_frame(roi, frame.width, frame.height))
+    }
+}
+
+impl FrameDetector for TemplateDetector {
+    fn process_frame(&mut self, frame: &FrameRecord, out: &mut Vec<AutoEvent>) {
+        let (config_w, config_h) = (self.config.width, self.config.height);
+        for idx in 0..self.tracks.len() {
+            let Some(rect) = self.search_rect(&self.tracks[idx].spec.roi, frame) else {
+                continue;
+            };
+            let stability = self.tracks[idx]
+                .spec
+                .stability_frames
+                .unwrap_or(self.stability_frames)
+                .max(1);
+            let track = &mut self.tracks[idx];
+            let template = track.scaled.get_or_insert_with(|| {
+                if config_w == 0 || config_h == 0 {
+                    return track.image.clone();
+                }
+                let w = (track.image.width as u64 * frame.width as u64 / config_w as u64).max(1);
+                let h = (track.image.height as u64 * frame.height as u64 / config_h as u64).max(1);
+                track.image.resized(w as u32, h as u32)
+            });
+            let region = roi_luma(frame, rect);
+            let score = match_template(&region, rect.w, rect.h, template);
+            let present = score >= track.spec.threshold;
+
+            if present == track.visible {
+                track.pending_frames = 0;
+                continue;
+            }
+            track.pending_frames = track.pending_frames.saturating_add(1);
+            if track.pending_frames < stability {
+                continue;
+            }
+            track.visible = present;
+            track.pending_frames = 0;
+            let kind = if present {
+                AutoEventKind::Appeared {
+                    template: track.spec.name.clone(),
+                    roi: track.spec.roi.clone(),
+                    score,
+                }
+            } else {
+                AutoEventKind::Disappeared {
+                    template: track.spec.name.clone(),
+                    roi: track.spec.roi.clone(),
+                    score,
+                }
+            };
+            out.push(AutoEvent {
+                step_index: frame.step_index,
+                frame_index: frame.step_index,
+                qpc_ts: frame.qpc_ts,
+                kind,
+            });
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_frames::{fill_rect, solid_frame};
+
+    fn checker_template() -> GrayImage {
+        let mut data = Vec::new();
+        for y in 0..8u32 {
+            for x in 0..8u32 {
+                data.push(if (x / 4 + y / 4) % 2 == 0 { 255 } else { 0 });
+            }
+        }
+        GrayImage {
+            width: 8,
+            height: 8,
+            data,
+        }
+    }
+
+    fn draw_checker(frame: &mut FrameRecord, x: u32, y: u32) {
+        fill_rect(frame, x, y, 8, 8, [0, 0, 0, 255]);
+        fill_rect(frame, x, y, 4, 4, [255, 255, 255, 255]);
+        fill_rect(frame, x + 4, y + 4, 4, 4, [255, 255, 255, 255]);
+    }
+
+    fn config() -> RoiConfig {
+        serde_json::from_str(
+            r#"{"width":64,"height":32,"rois":[{"name":"hud","x":16,"y":0,"w":32,"h":32}],
+                "templates":[{"name":"icon","roi":"hud","path":"icon.png","threshold":0.9}]}"#,
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn ncc_finds_template_anywhere_in_region() {
+        let mut frame = solid_frame(0, 32, 32, [40, 40, 40, 255]);
+        draw_checker(&mut frame, 13, 9);
+        let region = roi_luma(&frame, Rect { x: 0, y: 0, w: 32, h: 32 });
+        let score = match_template(&region, 32, 32, &checker_template());
+        assert!(score > 0.99, "score={}", score);
+
+        let flat = solid_frame(0, 32, 32, [40, 40, 40, 255]);
+        let region = roi_luma(&flat, Rect { x: 0, y: 0, w: 32, h: 32 });
+        assert_eq!(match_template(&region, 32, 32, &checker_template()), 0.0);
+    }
+
+    #[test]
+    fn appear_and_disappear_are_debounced() {
+        let mut detector = TemplateDetector::new(config(), vec![checker_template()], 2);
+        let mut events = Vec::new();
+        for step in 0..12 {
+            let mut frame = solid_frame(step, 64, 32, [40, 40, 40, 255]);
+            // Single-frame blip at step 1 must not trigger.
+            if step == 1 || (4..8).contains(&step) {
+                draw_checker(&mut frame, 20, 10);
+            }
+            detector.process_frame(&frame, &mut events);
+        }
+        assert_eq!(events.len(), 2);
+        assert_eq!(events[0].step_index, 5);
+        assert!(matches!(events[0].kind, AutoEventKind::Appeared { ref template, score, .. }
+            if template == "icon" && score > 0.9));
+        assert_eq!(events[1].step_index, 9);
+        assert!(matches!(events[1].kind, AutoEventKind::Disappeared { .. }));
+    }
+
+    #[test]
+    fn decodes_rgba_png_to_gray() {
+        let mut encoded = Vec::new();
+        {
+            let mut encoder = png::Encoder::new(&mut encoded, 2, 1);
+            encoder.set_color(png::ColorType::Rgba);
+            encoder.set_depth(png::BitDepth::Eight);
+            let mut writer = encoder.write_header().unwrap();
+            writer
+                .write_image_data(&[255, 255, 255, 255, 0, 0, 0, 255])
+                .unwrap();
+        }
+        let image = GrayImage::decode_png(encoded.as_slice()).unwrap();
+        assert_eq!((image.width, image.height), (2, 1));
+        assert_eq!(image.data, vec![255, 0]);
+    }
+}
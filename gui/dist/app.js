@@ -83,8 +83,11 @@ function buildOptions(sessionName, resolutionValue, fps) {
       fps: fps,
       record_resolution: resolution.size,
       resize_mode: "letterbox",
+      resize_filter: "area",
+      source_crop: null,
       color_format: "BGRA8",
       include_cursor_in_video: true,
+      cursor_style: "arrow",
       target: {
         method: "gui",
         window_title: null,
@@ -102,11 +105,54 @@ function buildOptions(sessionName, resolutionValue, fps) {
       clock: "QPC",
       step_ms: stepMs,
       fps: fps,
+      alignment: "preceding",
+      action_steps_per_frame: 1,
     },
     auto_events: {
       enabled: false,
       roi_config: resolution.roi,
       stability_frames: 3,
+    },
+    markers: {
+      enabled: false,
+      strip_from_actions: true,
+      bindings: [
+        { key: "Eight", marker: "episode_start" },
+        { key: "Nine", marker: "episode_end" },
+        { key: "Ten", marker: "interesting" },
+        { key: "Eleven", marker: "discard", discard_secs: 10 },
+      ],
+    },
+    idle: {
+      enabled: false,
+      idle_secs: 30,
+      static_threshold: 2.0,
+    },
+    video: {
+      sink: "ffmpeg",
+      image_format: "png",
+      quality: 90,
+      encode: {
+        codec: "x264",
+        preset: null,
+        crf: 16,
+        bitrate_kbps: null,
+        gop: 10,
+        pixel_format: "yuv420p",
+        tune: null,
+      },
+      queue: {
+        capacity: 8,
+        full_policy: "block",
+      },
+      renditions: [],
+    },
+    segment: {
+      every_frames: 0,
+      every_minutes: 0,
+    },
+    privacy: {
+      masks: [],
     }
   };
 }
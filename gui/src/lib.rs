@@ -1,6 +1,6 @@
 use std::io;
 use std::io::Write;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 use std::sync::mpsc;
 use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::{Arc, Mutex};
@@ -11,6 +11,7 @@ use std::io::Read;
 
 use collector_core::{Meta, Options};
 use serde::{Deserialize, Serialize};
+use writer::{read_chunk_index, CHUNK_INDEX};
 #[cfg(windows)]
 use app::pipeline::{PipelineConfig, SessionPipeline};
 #[cfg(windows)]
@@ -45,16 +46,14 @@ pub struct GuiSessionHandle {
     thought: Arc<Mutex<String>>,
     goals: Arc<Mutex<GoalState>>,
     stop: Arc<AtomicBool>,
+    paused: Arc<AtomicBool>,
 }
 
 impl GuiSessionHandle {
     pub fn join(self) -> io::Result<PathBuf> {
         match self.join.join() {
             Ok(result) => result,
-            Err(_) => Err(io::Error::new(
-                io::ErrorKind::Other,
-                "gui session thread panicked",
-            )),
+            Err(_) => Err(io::Error::other("gui session thread panicked")),
         }
     }
 
@@ -62,7 +61,7 @@ impl GuiSessionHandle {
         let mut guard = self
             .thought
             .lock()
-            .map_err(|_| io::Error::new(io::ErrorKind::Other, "thought lock poisoned"))?;
+            .map_err(|_| io::Error::other("thought lock poisoned"))?;
         *guard = text;
         Ok(())
     }
@@ -71,7 +70,7 @@ impl GuiSessionHandle {
         let mut guard = self
             .goals
             .lock()
-            .map_err(|_| io::Error::new(io::ErrorKind::Other, "goals lock poisoned"))?;
+            .map_err(|_| io::Error::other("goals lock poisoned"))?;
         guard.long = long_goal;
         guard.mid = mid_goal;
         Ok(())
@@ -80,6 +79,18 @@ impl GuiSessionHandle {
     pub fn stop(&self) {
         self.stop.store(true, Ordering::SeqCst);
     }
+
+    pub fn pause(&self) {
+        self.paused.store(true, Ordering::SeqCst);
+    }
+
+    pub fn resume(&self) {
+        self.paused.store(false, Ordering::SeqCst);
+    }
+
+    pub fn is_paused(&self) -> bool {
+        self.paused.load(Ordering::SeqCst)
+    }
 }
 
 #[derive(Debug, Default, Clone)]
@@ -105,10 +116,7 @@ impl GuiPackageHandle {
     pub fn join(self) -> io::Result<PathBuf> {
         match self.join.join() {
             Ok(result) => result,
-            Err(_) => Err(io::Error::new(
-                io::ErrorKind::Other,
-                "gui package thread panicked",
-            )),
+            Err(_) => Err(io::Error::other("gui package thread panicked")),
         }
     }
 }
@@ -118,24 +126,21 @@ impl GuiSessionRunner {
         #[cfg(not(windows))]
         {
             let _ = config;
-            return Err(io::Error::new(
-                io::ErrorKind::Other,
-                "GUI capture requires Windows",
-            ));
+            Err(io::Error::other("GUI capture requires Windows"))
         }
         #[cfg(windows)]
         {
-            let pipeline = SessionPipeline::create(PipelineConfig {
-                dataset_root: config.dataset_root.clone(),
-                session_name: config.session_name.clone(),
-                ffmpeg_path: config.ffmpeg_path.clone(),
-                record_width: config.options.capture.record_resolution[0],
-                record_height: config.options.capture.record_resolution[1],
-                fps: config.options.capture.fps,
-            })?;
+            let pipeline = SessionPipeline::create(PipelineConfig::from_options(
+                &config.options,
+                &config.meta,
+                &config.dataset_root,
+                &config.session_name,
+                &config.ffmpeg_path,
+            ))?;
             pipeline.write_options_meta(&config.options, &config.meta)?;
 
-            let capture = WgcCapture::new(config.options.capture.clone(), config.target_hwnd)?;
+            let mut capture = WgcCapture::new(config.options.capture.clone(), config.target_hwnd)?;
+            capture.keep_source_frames(!config.options.video.renditions.is_empty());
             let input = RawInputCollector::new()?;
 
             let layout = app::pipeline::run_realtime_with_hwnd(
@@ -154,10 +159,7 @@ impl GuiSessionRunner {
         #[cfg(not(windows))]
         {
             let _ = config;
-            return Err(io::Error::new(
-                io::ErrorKind::Other,
-                "GUI capture requires Windows",
-            ));
+            Err(io::Error::other("GUI capture requires Windows"))
         }
         #[cfg(windows)]
         {
@@ -168,25 +170,27 @@ impl GuiSessionRunner {
             let goals_state_thread = Arc::clone(&goals_state);
             let stop_flag = Arc::new(AtomicBool::new(false));
             let stop_flag_thread = Arc::clone(&stop_flag);
+            let pause_flag = Arc::new(AtomicBool::new(false));
+            let pause_flag_thread = Arc::clone(&pause_flag);
             let handle = std::thread::spawn(move || {
-                let pipeline = SessionPipeline::create(PipelineConfig {
-                    dataset_root: config.dataset_root.clone(),
-                    session_name: config.session_name.clone(),
-                    ffmpeg_path: config.ffmpeg_path.clone(),
-                    record_width: config.options.capture.record_resolution[0],
-                    record_height: config.options.capture.record_resolution[1],
-                    fps: config.options.capture.fps,
-                })?;
+                let pipeline = SessionPipeline::create(PipelineConfig::from_options(
+                    &config.options,
+                    &config.meta,
+                    &config.dataset_root,
+                    &config.session_name,
+                    &config.ffmpeg_path,
+                ))?;
                 pipeline.write_options_meta(&config.options, &config.meta)?;
                 let _ = tx.send(GuiStatus::Started {
                     session_name: config.session_name.clone(),
                 });
 
-                let capture = WgcCapture::new(config.options.capture.clone(), config.target_hwnd)?;
+                let mut capture = WgcCapture::new(config.options.capture.clone(), config.target_hwnd)?;
+                capture.keep_source_frames(!config.options.video.renditions.is_empty());
                 let input = RawInputCollector::new()?;
                 let tx_frame = tx.clone();
 
-                let result = app::pipeline::run_realtime_with_hwnd_and_hook_and_thought_with_stop(
+                let result = app::pipeline::run_realtime_with_hwnd_and_hook_and_thought_with_controls(
                     capture,
                     input,
                     config.target_hwnd,
@@ -212,6 +216,7 @@ impl GuiSessionRunner {
                             .unwrap_or_else(|_| (String::new(), String::new()))
                     },
                     &mut || stop_flag_thread.load(Ordering::SeqCst),
+                    &mut || pause_flag_thread.load(Ordering::SeqCst),
                     config.options.timing.step_ms,
                 );
 
@@ -236,6 +241,7 @@ impl GuiSessionRunner {
                 thought: thought_state,
                 goals: goals_state,
                 stop: stop_flag,
+                paused: pause_flag,
             })
         }
     }
@@ -260,6 +266,7 @@ pub fn package_sessions(request: PackageRequest) -> io::Result<PathBuf> {
         ));
     }
 
+    check_chunks(&targets)?;
     let files = collect_files(&request.dataset_root, &targets)?;
     let file = File::create(&request.output_zip)?;
     let mut zip = zip::ZipWriter::new(file);
@@ -267,18 +274,18 @@ pub fn package_sessions(request: PackageRequest) -> io::Result<PathBuf> {
 
     for (path, _) in &files {
         let rel = path.strip_prefix(&request.dataset_root).map_err(|_| {
-            io::Error::new(io::ErrorKind::Other, "failed to compute relative path")
+            io::Error::other("failed to compute relative path")
         })?;
         let rel_str = rel.to_string_lossy().replace('\\', "/");
         zip.start_file(rel_str, options)
-            .map_err(|err| io::Error::new(io::ErrorKind::Other, err))?;
+            .map_err(io::Error::other)?;
         let mut buffer = Vec::new();
         File::open(path)?.read_to_end(&mut buffer)?;
         zip.write_all(&buffer)?;
     }
 
     zip.finish()
-        .map_err(|err| io::Error::new(io::ErrorKind::Other, err))?;
+        .map_err(io::Error::other)?;
 
     if request.delete_after {
         for target in &targets {
@@ -304,6 +311,12 @@ pub fn start_package_async(request: PackageRequest) -> io::Result<GuiPackageHand
             return Err(err);
         }
 
+        if let Err(err) = check_chunks(&targets) {
+            let _ = tx.send(GuiPackageStatus::Error {
+                message: err.to_string(),
+            });
+            return Err(err);
+        }
         let files = collect_files(&request.dataset_root, &targets)?;
         let total_files = files.len() as u64;
         let total_bytes = files.iter().map(|(_, size)| *size).sum();
@@ -318,11 +331,11 @@ pub fn start_package_async(request: PackageRequest) -> io::Result<GuiPackageHand
 
         for (index, (path, size)) in files.iter().enumerate() {
             let rel = path.strip_prefix(&request.dataset_root).map_err(|_| {
-                io::Error::new(io::ErrorKind::Other, "failed to compute relative path")
+                io::Error::other("failed to compute relative path")
             })?;
             let rel_str = rel.to_string_lossy().replace('\\', "/");
             zip.start_file(rel_str, options)
-                .map_err(|err| io::Error::new(io::ErrorKind::Other, err))?;
+                .map_err(io::Error::other)?;
             let mut buffer = Vec::new();
             File::open(path)?.read_to_end(&mut buffer)?;
             zip.write_all(&buffer)?;
@@ -335,7 +348,7 @@ pub fn start_package_async(request: PackageRequest) -> io::Result<GuiPackageHand
         }
 
         zip.finish()
-            .map_err(|err| io::Error::new(io::ErrorKind::Other, err))?;
+            .map_err(io::Error::other)?;
 
         if request.delete_after {
             for target in &targets {
@@ -378,13 +391,34 @@ fn resolve_targets(root: &PathBuf, names: &[String]) -> io::Result<Vec<PathBuf>>
     }
 }
 
-fn is_tmp_dir(path: &PathBuf) -> bool {
+fn is_tmp_dir(path: &Path) -> bool {
     path.file_name()
         .and_then(|name| name.to_str())
         .map(|name| name.ends_with(".tmp"))
         .unwrap_or(false)
 }
 
+// A segmented session is only packaged when every chunk in its index is
+// present, so an archive never silently misses part of a recording.
+fn check_chunks(targets: &[PathBuf]) -> io::Result<()> {
+    for target in targets {
+        let index_path = target.join(CHUNK_INDEX);
+        if !index_path.exists() {
+            continue;
+        }
+        let index = read_chunk_index(&index_path)?;
+        for entry in &index.chunks {
+            if !target.join(&entry.dir).is_dir() {
+                return Err(io::Error::new(
+                    io::ErrorKind::NotFound,
+                    format!("{} is missing {}", target.display(), entry.dir),
+                ));
+            }
+        }
+    }
+    Ok(())
+}
+
 fn collect_files(_base: &PathBuf, targets: &[PathBuf]) -> io::Result<Vec<(PathBuf, u64)>> {
     let mut files = Vec::new();
     let mut stack = targets.to_vec();
@@ -3,8 +3,10 @@
 mod tauri_commands;
 
 use tauri_commands::{
-    join_package, join_session, list_windows, poll_package, poll_session, set_goals, set_thought,
-    start_package, start_session, stop_session, validate_ffmpeg, validate_session_name, GuiState,
+    join_package, join_session, list_orphaned_sessions, list_windows, pause_session,
+    poll_package, poll_session, recover_session, resume_session, set_goals, set_thought,
+    start_package, start_session, stop_session, validate_ffmpeg, validate_session_name,
+    GuiState,
 };
 use std::path::PathBuf;
 use tauri::{WindowBuilder, WindowUrl};
@@ -83,10 +85,14 @@ fn main() {
             poll_session,
             join_session,
             stop_session,
+            pause_session,
+            resume_session,
             set_thought,
             set_goals,
             validate_ffmpeg,
             validate_session_name,
+            list_orphaned_sessions,
+            recover_session,
             start_package,
             poll_package,
             join_package,
@@ -94,6 +94,22 @@ pub fn stop_session(id: u64, state: State<GuiState>) -> Result<(), String> {
     Ok(())
 }
 
+#[tauri::command]
+pub fn pause_session(id: u64, state: State<GuiState>) -> Result<(), String> {
+    let sessions = state.sessions.lock().map_err(|_| "lock poisoned")?;
+    let handle = sessions.get(&id).ok_or_else(|| "unknown session id".to_string())?;
+    handle.pause();
+    Ok(())
+}
+
+#[tauri::command]
+pub fn resume_session(id: u64, state: State<GuiState>) -> Result<(), String> {
+    let sessions = state.sessions.lock().map_err(|_| "lock poisoned")?;
+    let handle = sessions.get(&id).ok_or_else(|| "unknown session id".to_string())?;
+    handle.resume();
+    Ok(())
+}
+
 #[tauri::command]
 pub fn set_thought(id: u64, text: String, state: State<GuiState>) -> Result<(), String> {
     let sessions = state.sessions.lock().map_err(|_| "lock poisoned")?;
@@ -229,12 +245,47 @@ pub fn validate_session_name(datasetRoot: String, sessionName: String) -> Result
     }
     let sessions_dir = root.join("sessions");
     let candidate = sessions_dir.join(sessionName.trim());
-    if candidate.exists() {
+    let staging = sessions_dir.join(format!("{}.tmp", sessionName.trim()));
+    if candidate.exists() || staging.exists() {
         return Err("session directory already exists".to_string());
     }
     Ok(())
 }
 
+#[tauri::command]
+#[allow(non_snake_case)]
+pub fn list_orphaned_sessions(datasetRoot: String) -> Result<Vec<String>, String> {
+    writer::scan_orphaned_sessions(Path::new(datasetRoot.trim()))
+        .map(|paths| {
+            paths
+                .iter()
+                .map(|path| path.to_string_lossy().to_string())
+                .collect()
+        })
+        .map_err(|err| err.to_string())
+}
+
+#[tauri::command]
+#[allow(non_snake_case)]
+pub fn recover_session(
+    datasetRoot: String,
+    sessionName: String,
+    ffmpegPath: String,
+) -> Result<String, String> {
+    let report = app::recover::recover_session(
+        Path::new(datasetRoot.trim()),
+        sessionName.trim(),
+        Path::new(ffmpegPath.trim()),
+    )
+    .map_err(|err| err.to_string())?;
+    Ok(format!(
+        "{} steps in {} ({} bytes dropped)",
+        report.steps,
+        report.session_dir.to_string_lossy(),
+        report.dropped_bytes
+    ))
+}
+
 fn map_status(status: GuiStatus) -> GuiStatusDto {
     match status {
         GuiStatus::Started { session_name } => GuiStatusDto::Started { session_name },
@@ -78,7 +78,7 @@ impl RawInputCollectorImpl {
 
     let thread_id = ready_rx
         .recv()
-        .map_err(|_| io::Error::new(io::ErrorKind::Other, "rawinput thread failed"))??;
+        .map_err(|_| io::Error::other("rawinput thread failed"))??;
 
         Ok(Self {
             rx,
@@ -141,10 +141,7 @@ fn run_message_loop(
         if atom == 0 {
             let last = GetLastError();
             if last != ERROR_CLASS_ALREADY_EXISTS {
-                let _ = ready_tx.send(Err(io::Error::new(
-                    io::ErrorKind::Other,
-                    "RegisterClassW failed",
-                )));
+                let _ = ready_tx.send(Err(io::Error::other("RegisterClassW failed")));
                 return;
             }
         }
@@ -164,10 +161,7 @@ fn run_message_loop(
             None,
         );
         if hwnd.0 == 0 {
-            let _ = ready_tx.send(Err(io::Error::new(
-                io::ErrorKind::Other,
-                "CreateWindowExW failed",
-            )));
+            let _ = ready_tx.send(Err(io::Error::other("CreateWindowExW failed")));
             return;
         }
 
@@ -327,10 +321,7 @@ fn handle_raw_input(hwnd: HWND, lparam: LPARAM) -> io::Result<()> {
             size_of::<RAWINPUTHEADER>() as u32,
         );
         if read == 0 {
-            return Err(io::Error::new(
-                io::ErrorKind::Other,
-                "GetRawInputData failed",
-            ));
+            return Err(io::Error::other("GetRawInputData failed"));
         }
 
         let raw = &*(buffer.as_ptr() as *const RAWINPUT);
@@ -420,10 +411,7 @@ fn context_from_hwnd(hwnd: HWND) -> io::Result<&'static RawInputContext> {
     unsafe {
         let ptr = GetWindowLongPtrW(hwnd, GWLP_USERDATA) as *mut RawInputContext;
         if ptr.is_null() {
-            return Err(io::Error::new(
-                io::ErrorKind::Other,
-                "missing rawinput context",
-            ));
+            return Err(io::Error::other("missing rawinput context"));
         }
         Ok(&*ptr)
     }
@@ -440,7 +428,7 @@ fn qpc_now() -> io::Result<QpcTimestamp> {
 
 #[cfg(windows)]
 fn map_win_err(err: windows::core::Error) -> io::Error {
-    io::Error::new(io::ErrorKind::Other, format!("{:?}", err))
+    io::Error::other(format!("{:?}", err))
 }
 
 #[cfg(windows)]
@@ -454,16 +442,10 @@ pub struct RawInputCollectorImpl;
 #[cfg(not(windows))]
 impl RawInputCollectorImpl {
     pub fn new(_target_hwnd: Option<isize>) -> io::Result<Self> {
-        Err(io::Error::new(
-            io::ErrorKind::Other,
-            "RawInput requires Windows",
-        ))
+        Err(io::Error::other("RawInput requires Windows"))
     }
 
     pub fn drain_into(&mut self, _buffer: &mut VecDeque<InputEvent>) -> io::Result<()> {
-        Err(io::Error::new(
-            io::ErrorKind::Other,
-            "RawInput requires Windows",
-        ))
+        Err(io::Error::other("RawInput requires Windows"))
     }
 }
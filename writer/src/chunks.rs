@@ -0,0 +1,95 @@
+use std::fs::{self, File};
+use std::io::{self, BufWriter, Write};
+use std::path::{Path, PathBuf};
+
+use collector_core::StepIndex;
+use serde::{Deserialize, Serialize};
+
+pub const CHUNK_INDEX: &str = "chunks.json";
+const CHUNK_PREFIX: &str = "chunk_";
+
+// Session-level list of chunk directories, in recording order. Rewritten
+// each time a chunk is opened or closed, so a crash leaves it current.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct ChunkIndex {
+    pub chunks: Vec<ChunkEntry>,
+}
+
+// `first_step` is the first `step_index` in the chunk's actions.jsonl;
+// `steps` counts its lines and `frames` its video frames. `video` is the
+// chunk's video file (or image directory) relative to the chunk directory;
+// `renditions` lists every rendition's video when renditions are configured.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ChunkEntry {
+    pub index: u32,
+    pub dir: String,
+    pub video: String,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub renditions: Vec<String>,
+    pub first_step: StepIndex,
+    pub steps: u64,
+    pub frames: u64,
+    pub complete: bool,
+}
+
+pub fn chunk_dir_name(index: u32) -> String {
+    format!("{}{:04}", CHUNK_PREFIX, index)
+}
+
+pub fn parse_chunk_dir_name(name: &str) -> Option<u32> {
+    let digits = name.strip_prefix(CHUNK_PREFIX)?;
+    if digits.len() < 4 || !digits.bytes().all(|byte| byte.is_ascii_digit()) {
+        return None;
+    }
+    digits.parse().ok()
+}
+
+// Chunk directories present on disk, by index. Used when the index itself
+// cannot be trusted, e.g. by recovery.
+pub fn scan_chunk_dirs(session_dir: &Path) -> io::Result<Vec<(u32, PathBuf)>> {
+    let mut out = Vec::new();
+    for entry in fs::read_dir(session_dir)? {
+        let entry = entry?;
+        if !entry.file_type()?.is_dir() {
+            continue;
+        }
+        if let Some(index) = entry.file_name().to_str().and_then(parse_chunk_dir_name) {
+            out.push((index, entry.path()));
+        }
+    }
+    out.sort();
+    Ok(out)
+}
+
+pub fn read_chunk_index(path: &Path) -> io::Result<ChunkIndex> {
+    serde_json::from_str(&fs::read_to_string(path)?)
+        .map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err))
+}
+
+// Written to a temporary file and renamed over the old index.
+pub fn write_chunk_index(path: &Path, index: &ChunkIndex) -> io::Result<()> {
+    let tmp_path = path.with_extension("json.tmp");
+    {
+        let mut out = BufWriter::new(File::create(&tmp_path)?);
+        serde_json::to_writer_pretty(&mut out, index).map_err(io::Error::other)?;
+        out.flush()?;
+        out.get_ref().sync_all()?;
+    }
+    fs::rename(&tmp_path, path)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn chunk_names_round_trip() {
+        assert_eq!(chunk_dir_name(0), "chunk_0000");
+        assert_eq!(chunk_dir_name(12345), "chunk_12345");
+        assert_eq!(parse_chunk_dir_name("chunk_0042"), Some(42));
+        assert_eq!(parse_chunk_dir_name("chunk_12345"), Some(12345));
+        assert_eq!(parse_chunk_dir_name("chunk_42"), None);
+        assert_eq!(parse_chunk_dir_name("chunk_00a1"), None);
+        assert_eq!(parse_chunk_dir_name("images"), None);
+    }
+}
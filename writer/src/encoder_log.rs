@@ -0,0 +1,207 @@
+use std::collections::VecDeque;
+use std::fs::File;
+use std::io::{self, BufWriter, Read, Write};
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+use std::thread::{self, JoinHandle};
+
+use serde::Serialize;
+
+const TAIL_LINES: usize = 20;
+
+// Parsed from ffmpeg's periodic `frame=... fps=... time=... speed=...` line.
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct EncoderProgress {
+    pub frame: u64,
+    pub fps: f32,
+    pub size_kb: u64,
+    pub time_secs: f64,
+    pub bitrate_kbps: f32,
+    pub speed: f32,
+}
+
+#[derive(Default)]
+struct LogState {
+    tail: VecDeque<String>,
+    progress: Option<EncoderProgress>,
+}
+
+// Drains an encoder's stderr on a background thread: every line goes to the
+// optional log file, the last lines are kept for error messages and progress
+// lines update the latest `EncoderProgress`.
+pub struct EncoderLog {
+    state: Arc<Mutex<LogState>>,
+    handle: Option<JoinHandle<()>>,
+}
+
+impl EncoderLog {
+    pub fn spawn<R: Read + Send + 'static>(stderr: R, log_path: Option<&Path>) -> io::Result<Self> {
+        let mut file = match log_path {
+            Some(path) => Some(BufWriter::new(File::create(path)?)),
+            None => None,
+        };
+        let state = Arc::new(Mutex::new(LogState::default()));
+        let shared = Arc::clone(&state);
+        let handle = thread::spawn(move || {
+            let mut reader = stderr;
+            let mut buf = [0u8; 4096];
+            let mut line = Vec::new();
+            loop {
+                let read = match reader.read(&mut buf) {
+                    Ok(0) | Err(_) => break,
+                    Ok(read) => read,
+                };
+                if let Some(file) = file.as_mut() {
+                    let _ = file.write_all(&buf[..read]);
+                }
+                for &byte in &buf[..read] {
+                    if byte == b'\n' || byte == b'\r' {
+                        push_line(&shared, &line);
+                        line.clear();
+                    } else {
+                        line.push(byte);
+                    }
+                }
+            }
+            push_line(&shared, &line);
+            if let Some(file) = file.as_mut() {
+                let _ = file.flush();
+                let _ = file.get_ref().sync_all();
+            }
+        });
+        Ok(Self {
+            state,
+            handle: Some(handle),
+        })
+    }
+
+    pub fn progress(&self) -> Option<EncoderProgress> {
+        self.state.lock().ok().and_then(|state| state.progress.clone())
+    }
+
+    pub fn tail(&self) -> Vec<String> {
+        self.state
+            .lock()
+            .map(|state| state.tail.iter().cloned().collect())
+            .unwrap_or_default()
+    }
+
+    // Waits for the encoder to close stderr, so the log is complete.
+    pub fn join(&mut self) {
+        if let Some(handle) = self.handle.take() {
+            let _ = handle.join();
+        }
+    }
+
+    // Wraps `err` with the last stderr lines.
+    pub fn annotate(&self, err: io::Error) -> io::Error {
+        let tail = self.tail();
+        if tail.is_empty() {
+            return err;
+        }
+        io::Error::new(
+            err.kind(),
+            format!("{}; ffmpeg output:\n{}", err, tail.join("\n")),
+        )
+    }
+}
+
+fn push_line(state: &Mutex<LogState>, raw: &[u8]) {
+    let text = String::from_utf8_lossy(raw).trim().to_string();
+    if text.is_empty() {
+        return;
+    }
+    let Ok(mut state) = state.lock() else {
+        return;
+    };
+    if let Some(progress) = parse_progress(&text) {
+        state.progress = Some(progress);
+        return;
+    }
+    if state.tail.len() == TAIL_LINES {
+        state.tail.pop_front();
+    }
+    state.tail.push_back(text);
+}
+
+pub fn parse_progress(line: &str) -> Option<EncoderProgress> {
+    if !line.starts_with("frame=") {
+        return None;
+    }
+    // Values may be separated from their key by spaces ("frame=   12").
+    let mut normalized = line.to_string();
+    while normalized.contains("= ") {
+        normalized = normalized.replace("= ", "=");
+    }
+    let mut progress = EncoderProgress::default();
+    for field in normalized.split_whitespace() {
+        let Some((key, value)) = field.split_once('=') else {
+            continue;
+        };
+        match key {
+            "frame" => progress.frame = value.parse().ok()?,
+            "fps" => progress.fps = value.parse().unwrap_or(0.0),
+            "size" | "Lsize" => {
+                progress.size_kb = value
+                    .trim_end_matches("KiB")
+                    .trim_end_matches("kB")
+                    .parse()
+                    .unwrap_or(0)
+            }
+            "time" => progress.time_secs = parse_time(value).unwrap_or(0.0),
+            "bitrate" => {
+                progress.bitrate_kbps = value.trim_end_matches("kbits/s").parse().unwrap_or(0.0)
+            }
+            "speed" => progress.speed = value.trim_end_matches('x').parse().unwrap_or(0.0),
+            _ => {}
+        }
+    }
+    Some(progress)
+}
+
+fn parse_time(value: &str) -> Option<f64> {
+    let mut secs = 0.0;
+    for part in value.split(':') {
+        secs = secs * 60.0 + part.parse::<f64>().ok()?;
+    }
+    Some(secs)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_progress_lines() {
+        let line = "frame=  123 fps= 30 q=28.0 size=    1024kB time=00:01:04.10 bitrate=2046.5kbits/s speed=1.02x";
+        let progress = parse_progress(line).unwrap();
+        assert_eq!(progress.frame, 123);
+        assert_eq!(progress.fps, 30.0);
+        assert_eq!(progress.size_kb, 1024);
+        assert!((progress.time_secs - 64.1).abs() < 1e-9);
+        assert_eq!(progress.bitrate_kbps, 2046.5);
+        assert_eq!(progress.speed, 1.02);
+        assert!(parse_progress("Input #0, rawvideo, from 'pipe:':").is_none());
+    }
+
+    #[test]
+    fn keeps_tail_and_progress_from_stream() {
+        let mut text = String::new();
+        for i in 0..30 {
+            text.push_str(&format!("line {}\n", i));
+        }
+        text.push_str("frame=    5 fps=0.0 q=0.0 size=0kB time=00:00:01.00 bitrate=0.0kbits/s speed=2x\r");
+        text.push_str("frame=    9 fps=0.0 q=0.0 size=0kB time=00:00:01.80 bitrate=0.0kbits/s speed=2x\r");
+        text.push_str("Conversion failed!");
+        let mut log = EncoderLog::spawn(io::Cursor::new(text.into_bytes()), None).unwrap();
+        log.join();
+
+        let tail = log.tail();
+        assert_eq!(tail.len(), TAIL_LINES);
+        assert_eq!(tail.first().map(String::as_str), Some("line 11"));
+        assert_eq!(tail.last().map(String::as_str), Some("Conversion failed!"));
+        assert_eq!(log.progress().map(|p| p.frame), Some(9));
+        let err = log.annotate(io::Error::other("ffmpeg exited with 1"));
+        assert!(err.to_string().ends_with("Conversion failed!"));
+    }
+}
@@ -1,100 +1,273 @@
 use std::fs::{self, File};
-use std::io::{self, BufWriter, Write};
+use std::io::{self, BufRead, BufReader, BufWriter, Write};
 use std::path::{Path, PathBuf};
 use std::process::{Child, ChildStdin, Command, Stdio};
 use std::time::{Duration, Instant};
-use std::thread::sleep;
 
 use aggregator::AggregatedWindow;
-use collector_core::ActionSnapshot;
+use collector_core::{
+    ActionSnapshot, EncodeOptions, FrameRef, FrameTiming, RenditionMeta, SegmentOptions, StepIndex, VideoCodec,
+    VideoOutputOptions, VideoSinkKind,
+};
 use serde::Serialize;
 
+mod chunks;
+mod encoder_log;
+mod queue;
+mod sink;
+
+pub use chunks::{
+    chunk_dir_name, parse_chunk_dir_name, read_chunk_index, scan_chunk_dirs, write_chunk_index,
+    ChunkEntry, ChunkIndex, CHUNK_INDEX,
+};
+pub use encoder_log::{parse_progress, EncoderLog, EncoderProgress};
+pub use queue::{EncodeQueue, EnqueueReport, QueueStats};
+pub use sink::{ImageSequenceSink, RawBgraSink, VideoSink};
+
+#[derive(Debug, Clone)]
 pub struct SessionLayout {
     pub root_dir: PathBuf,
     pub temp_dir: PathBuf,
+    // Directory holding the video and JSONL files: the session directory, or
+    // a `chunk_NNNN` directory inside it for segmented sessions.
+    pub data_dir: PathBuf,
     pub video_path: PathBuf,
     pub actions_path: PathBuf,
     pub compiled_path: PathBuf,
     pub thoughts_path: PathBuf,
     pub goals_path: PathBuf,
     pub auto_events_path: PathBuf,
+    pub gauges_path: PathBuf,
+    pub frames_path: PathBuf,
+    pub images_dir: PathBuf,
+    pub raw_video_path: PathBuf,
+    pub encoder_log_path: PathBuf,
     pub options_path: PathBuf,
     pub meta_path: PathBuf,
+    pub chunk_index_path: PathBuf,
 }
 
+pub const COMPLETE_MARKER: &str = "session.complete";
+const TMP_SUFFIX: &str = ".tmp";
+
 impl SessionLayout {
+    // File paths point at the final directory; `staging` points them at the
+    // `<name>.tmp` directory the writer fills before the rename.
     pub fn new(dataset_root: &Path, session_name: &str) -> Self {
         let sessions_dir = dataset_root.join("sessions");
         let root_dir = sessions_dir.join(session_name);
-        let temp_dir = root_dir.clone();
+        let temp_dir = sessions_dir.join(format!("{}{}", session_name, TMP_SUFFIX));
+        Self::with_files_in(root_dir.clone(), temp_dir, &root_dir)
+    }
+
+    pub fn staging(&self) -> Self {
+        Self::with_files_in(self.root_dir.clone(), self.temp_dir.clone(), &self.temp_dir)
+    }
+
+    pub fn complete_marker_path(&self) -> PathBuf {
+        self.root_dir.join(COMPLETE_MARKER)
+    }
+
+    // The same session with its per-chunk files moved into `chunk_NNNN`.
+    // options.json, meta.json and the chunk index stay at the session level.
+    pub fn chunk(&self, index: u32) -> Self {
+        let session_dir = self
+            .chunk_index_path
+            .parent()
+            .map(Path::to_path_buf)
+            .unwrap_or_else(|| self.root_dir.clone());
+        let mut chunk = Self::with_files_in(
+            self.root_dir.clone(),
+            self.temp_dir.clone(),
+            &session_dir.join(chunk_dir_name(index)),
+        );
+        chunk.options_path = self.options_path.clone();
+        chunk.meta_path = self.meta_path.clone();
+        chunk.chunk_index_path = self.chunk_index_path.clone();
+        if let Some(ext) = self.video_path.extension() {
+            chunk.video_path.set_extension(ext);
+        }
+        chunk
+    }
+
+    // The same layout with the video outputs renamed for rendition `name`.
+    pub fn rendition(&self, name: &str) -> Self {
+        let mut out = self.clone();
+        let ext = self
+            .video_path
+            .extension()
+            .map(|ext| ext.to_string_lossy().to_string())
+            .unwrap_or_else(|| "mp4".to_string());
+        out.video_path = self.data_dir.join(format!("video_{}.{}", name, ext));
+        out.images_dir = self.data_dir.join(format!("images_{}", name));
+        out.raw_video_path = self.data_dir.join(format!("video_{}.bgra", name));
+        out.encoder_log_path = self.data_dir.join(format!("encoder_{}.log", name));
+        out
+    }
+
+    // One layout per chunk listed in the chunk index, or just this layout
+    // for a session recorded without segmenting.
+    pub fn chunk_layouts(&self) -> io::Result<Vec<SessionLayout>> {
+        if !self.chunk_index_path.exists() {
+            return Ok(vec![self.clone()]);
+        }
+        let index = read_chunk_index(&self.chunk_index_path)?;
+        Ok(index
+            .chunks
+            .iter()
+            .map(|entry| self.chunk(entry.index))
+            .collect())
+    }
+
+    fn with_files_in(root_dir: PathBuf, temp_dir: PathBuf, dir: &Path) -> Self {
         Self {
-            video_path: temp_dir.join("video.mp4"),
-            actions_path: temp_dir.join("actions.jsonl"),
-            compiled_path: temp_dir.join("compiled_actions.jsonl"),
-            thoughts_path: temp_dir.join("labeling_instruct.jsonl"),
-            goals_path: temp_dir.join("goal.jsonl"),
-            auto_events_path: temp_dir.join("auto_events.jsonl"),
-            options_path: temp_dir.join("options.json"),
-            meta_path: temp_dir.join("meta.json"),
+            video_path: dir.join("video.mp4"),
+            actions_path: dir.join("actions.jsonl"),
+            compiled_path: dir.join("compiled_actions.jsonl"),
+            thoughts_path: dir.join("labeling_instruct.jsonl"),
+            goals_path: dir.join("goal.jsonl"),
+            auto_events_path: dir.join("auto_events.jsonl"),
+            gauges_path: dir.join("gauges.jsonl"),
+            frames_path: dir.join("frames.jsonl"),
+            images_dir: dir.join("images"),
+            raw_video_path: dir.join("video.bgra"),
+            encoder_log_path: dir.join("encoder.log"),
+            options_path: dir.join("options.json"),
+            meta_path: dir.join("meta.json"),
+            chunk_index_path: dir.join(CHUNK_INDEX),
+            data_dir: dir.to_path_buf(),
             root_dir,
             temp_dir,
         }
     }
 }
 
+// Sessions left as `<name>.tmp` by a crash or kill before finalize.
+pub fn scan_orphaned_sessions(dataset_root: &Path) -> io::Result<Vec<PathBuf>> {
+    let sessions_dir = dataset_root.join("sessions");
+    let mut out = Vec::new();
+    if !sessions_dir.exists() {
+        return Ok(out);
+    }
+    for entry in fs::read_dir(&sessions_dir)? {
+        let path = entry?.path();
+        let is_tmp = path
+            .file_name()
+            .and_then(|name| name.to_str())
+            .map(|name| name.ends_with(TMP_SUFFIX))
+            .unwrap_or(false);
+        if is_tmp && path.is_dir() {
+            out.push(path);
+        }
+    }
+    out.sort();
+    Ok(out)
+}
+
 pub struct FfmpegConfig {
     pub ffmpeg_path: PathBuf,
     pub output_path: PathBuf,
     pub width: u32,
     pub height: u32,
     pub fps: u32,
-    pub crf: u32,
-    pub gop: u32,
+    pub encode: EncodeOptions,
+    pub log_path: Option<PathBuf>,
+}
+
+// Arguments between the ffmpeg executable and the output path.
+pub fn ffmpeg_args(config: &FfmpegConfig) -> Vec<String> {
+    let encode = &config.encode;
+    let mut args: Vec<String> = [
+        "-y",
+        "-f",
+        "rawvideo",
+        "-pix_fmt",
+        "bgra",
+        "-s",
+        &format!("{}x{}", config.width, config.height),
+        "-r",
+        &config.fps.to_string(),
+        "-i",
+        "-",
+        "-c:v",
+        encode.codec.encoder(),
+    ]
+    .iter()
+    .map(|arg| arg.to_string())
+    .collect();
+    if let Some(preset) = encode.preset.as_ref() {
+        args.extend(["-preset".to_string(), preset.clone()]);
+    }
+    if let Some(tune) = encode.tune.as_ref() {
+        args.extend(["-tune".to_string(), tune.clone()]);
+    }
+    if let Some(crf) = encode.crf {
+        let flag = if encode.codec == VideoCodec::Mjpeg { "-q:v" } else { "-crf" };
+        args.extend([flag.to_string(), crf.to_string()]);
+    }
+    if let Some(kbps) = encode.bitrate_kbps {
+        args.extend(["-b:v".to_string(), format!("{}k", kbps)]);
+    }
+    args.extend([
+        "-g".to_string(),
+        encode.gop.to_string(),
+        "-pix_fmt".to_string(),
+        encode.pixel_format.name().to_string(),
+    ]);
+    if encode.codec.container_extension() == "mp4" {
+        // Fragmented MP4 keeps everything written so far playable if the
+        // process dies before ffmpeg writes the trailer.
+        args.extend([
+            "-movflags".to_string(),
+            "+frag_keyframe+empty_moov+default_base_moof".to_string(),
+        ]);
+    }
+    args
+}
+
+// Full command line as recorded in meta.json.
+pub fn ffmpeg_command_line(config: &FfmpegConfig) -> Vec<String> {
+    let mut line = vec![config.ffmpeg_path.to_string_lossy().to_string()];
+    line.extend(ffmpeg_args(config));
+    line.push(config.output_path.to_string_lossy().to_string());
+    line
 }
 
 pub struct FfmpegWriter {
     child: Child,
     stdin: ChildStdin,
+    log: EncoderLog,
     frame_bytes: usize,
 }
 
 impl FfmpegWriter {
     pub fn spawn(config: &FfmpegConfig) -> io::Result<Self> {
+        config
+            .encode
+            .validate()
+            .map_err(|err| io::Error::new(io::ErrorKind::InvalidInput, err))?;
         let mut cmd = Command::new(&config.ffmpeg_path);
-        cmd.arg("-y")
-            .arg("-f")
-            .arg("rawvideo")
-            .arg("-pix_fmt")
-            .arg("bgra")
-            .arg("-s")
-            .arg(format!("{}x{}", config.width, config.height))
-            .arg("-r")
-            .arg(config.fps.to_string())
-            .arg("-i")
-            .arg("-")
-            .arg("-c:v")
-            .arg("libx264")
-            .arg("-crf")
-            .arg(config.crf.to_string())
-            .arg("-g")
-            .arg(config.gop.to_string())
-            .arg("-pix_fmt")
-            .arg("yuv420p")
+        cmd.args(ffmpeg_args(config))
             .arg(&config.output_path)
             .stdin(Stdio::piped())
             .stdout(Stdio::null())
-            .stderr(Stdio::null());
+            .stderr(Stdio::piped());
 
         let mut child = cmd.spawn()?;
         let stdin = child.stdin.take().ok_or_else(|| {
-            io::Error::new(io::ErrorKind::Other, "ffmpeg stdin unavailable")
+            io::Error::other("ffmpeg stdin unavailable")
+        })?;
+        let stderr = child.stderr.take().ok_or_else(|| {
+            io::Error::other("ffmpeg stderr unavailable")
         })?;
+        let log = EncoderLog::spawn(stderr, config.log_path.as_deref())?;
         let frame_bytes = (config.width as usize)
             .saturating_mul(config.height as usize)
             .saturating_mul(4);
         Ok(Self {
             child,
             stdin,
+            log,
             frame_bytes,
         })
     }
@@ -106,21 +279,77 @@ impl FfmpegWriter {
                 "frame buffer size does not match expected BGRA size",
             ));
         }
-        self.stdin.write_all(frame)
+        match self.stdin.write_all(frame) {
+            Ok(()) => Ok(()),
+            Err(err) => {
+                // A broken pipe means ffmpeg is gone; wait for it so the
+                // error carries its final output.
+                if err.kind() == io::ErrorKind::BrokenPipe {
+                    let _ = self.child.wait();
+                    self.log.join();
+                }
+                Err(self.log.annotate(err))
+            }
+        }
+    }
+
+    pub fn progress(&self) -> Option<EncoderProgress> {
+        self.log.progress()
     }
 
     pub fn finish(mut self) -> io::Result<()> {
-        self.stdin.flush()?;
+        let flushed = self.stdin.flush();
         drop(self.stdin);
         let status = self.child.wait()?;
+        self.log.join();
         if !status.success() {
-            return Err(io::Error::new(
-                io::ErrorKind::Other,
-                format!("ffmpeg exited with {}", status),
-            ));
+            return Err(self
+                .log
+                .annotate(io::Error::other(format!("ffmpeg exited with {}", status))));
         }
-        Ok(())
+        flushed.map_err(|err| self.log.annotate(err))
+    }
+}
+
+impl VideoSink for FfmpegWriter {
+    fn write_frame(&mut self, frame: &[u8]) -> io::Result<()> {
+        FfmpegWriter::write_frame(self, frame)
+    }
+
+    fn finish(self: Box<Self>) -> io::Result<()> {
+        FfmpegWriter::finish(*self)
     }
+
+    fn progress(&self) -> Option<EncoderProgress> {
+        FfmpegWriter::progress(self)
+    }
+}
+
+pub fn open_video_sink(
+    options: &VideoOutputOptions,
+    layout: &SessionLayout,
+    ffmpeg_path: &Path,
+    width: u32,
+    height: u32,
+    fps: u32,
+) -> io::Result<Box<dyn VideoSink>> {
+    Ok(match options.sink {
+        VideoSinkKind::Ffmpeg => {
+            let mut config =
+                default_ffmpeg_config(ffmpeg_path, &layout.video_path, width, height, fps);
+            config.encode = options.encode.clone();
+            config.log_path = Some(layout.encoder_log_path.clone());
+            Box::new(FfmpegWriter::spawn(&config)?)
+        }
+        VideoSinkKind::ImageSequence => Box::new(ImageSequenceSink::create(
+            &layout.images_dir,
+            options.image_format,
+            options.quality,
+            width,
+            height,
+        )?),
+        VideoSinkKind::RawBgra => Box::new(RawBgraSink::create(&layout.raw_video_path, width, height)?),
+    })
 }
 
 pub fn default_ffmpeg_config(
@@ -136,142 +365,683 @@ pub fn default_ffmpeg_config(
         width,
         height,
         fps,
-        crf: 16,
-        gop: 10,
+        encode: EncodeOptions::default(),
+        log_path: None,
     }
 }
 
-pub struct SessionWriter {
+// One encoded output of the session. Without renditions there is a single
+// unnamed output at the record resolution.
+#[derive(Debug, Clone)]
+pub struct VideoOutput {
+    pub name: Option<String>,
+    pub width: u32,
+    pub height: u32,
+    pub options: VideoOutputOptions,
+}
+
+impl VideoOutput {
+    // Where this output's files go inside `layout`.
+    pub fn layout(&self, layout: &SessionLayout) -> SessionLayout {
+        let mut out = match &self.name {
+            Some(name) => layout.rendition(name),
+            None => layout.clone(),
+        };
+        out.video_path
+            .set_extension(self.options.encode.codec.container_extension());
+        out
+    }
+
+    fn encoder_command(
+        &self,
+        layout: &SessionLayout,
+        ffmpeg_path: &Path,
+        fps: u32,
+    ) -> Option<Vec<String>> {
+        (self.options.sink == VideoSinkKind::Ffmpeg).then(|| {
+            let mut config = default_ffmpeg_config(
+                ffmpeg_path,
+                &self.layout(layout).video_path,
+                self.width,
+                self.height,
+                fps,
+            );
+            config.encode = self.options.encode.clone();
+            ffmpeg_command_line(&config)
+        })
+    }
+
+    // The video file or image directory, relative to its directory.
+    fn video_name(&self, layout: &SessionLayout) -> String {
+        let layout = self.layout(layout);
+        let path = match self.options.sink {
+            VideoSinkKind::Ffmpeg => &layout.video_path,
+            VideoSinkKind::ImageSequence => &layout.images_dir,
+            VideoSinkKind::RawBgra => &layout.raw_video_path,
+        };
+        path.file_name()
+            .map(|name| name.to_string_lossy().to_string())
+            .unwrap_or_default()
+    }
+}
+
+// Resolves `video.renditions` into outputs and validates them, including
+// every ffmpeg encoder configuration.
+pub fn video_outputs(
+    video: &VideoOutputOptions,
+    width: u32,
+    height: u32,
+) -> io::Result<Vec<VideoOutput>> {
+    let invalid = |message: String| io::Error::new(io::ErrorKind::InvalidInput, message);
+    let mut outputs = Vec::new();
+    if video.renditions.is_empty() {
+        outputs.push(VideoOutput {
+            name: None,
+            width,
+            height,
+            options: video.clone(),
+        });
+    }
+    for rendition in &video.renditions {
+        let valid_name = !rendition.name.is_empty()
+            && rendition
+                .name
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-');
+        if !valid_name {
+            return Err(invalid(format!("invalid rendition name: {:?}", rendition.name)));
+        }
+        if outputs
+            .iter()
+            .any(|output: &VideoOutput| output.name.as_deref() == Some(rendition.name.as_str()))
+        {
+            return Err(invalid(format!("duplicate rendition name: {}", rendition.name)));
+        }
+        let [rendition_width, rendition_height] = rendition.resolution;
+        if rendition_width == 0 || rendition_height == 0 {
+            return Err(invalid(format!(
+                "rendition {} has an empty resolution",
+                rendition.name
+            )));
+        }
+        let mut options = video.clone();
+        options.renditions.clear();
+        if let Some(encode) = &rendition.encode {
+            options.encode = encode.clone();
+        }
+        outputs.push(VideoOutput {
+            name: Some(rendition.name.clone()),
+            width: rendition_width,
+            height: rendition_height,
+            options,
+        });
+    }
+    if video.sink == VideoSinkKind::Ffmpeg {
+        for output in &outputs {
+            output.options.encode.validate().map_err(|err| match &output.name {
+                Some(name) => invalid(format!("rendition {}: {}", name, err)),
+                None => invalid(err),
+            })?;
+        }
+    }
+    Ok(outputs)
+}
+
+// Everything a chunk needs to reopen the video outputs.
+struct VideoParams {
+    outputs: Vec<VideoOutput>,
+    ffmpeg_path: PathBuf,
+    fps: u32,
+}
+
+struct ChunkVideo {
     layout: SessionLayout,
-    ffmpeg: FfmpegWriter,
+    queue: EncodeQueue,
+}
+
+// The video and JSONL files of the chunk being written. Without segmenting
+// the only chunk is the session directory itself.
+struct OpenChunk {
+    layout: SessionLayout,
+    videos: Vec<ChunkVideo>,
     actions: JsonlWriter<BufWriter<File>>,
     compiled: JsonlWriter<BufWriter<File>>,
     thoughts: JsonlWriter<BufWriter<File>>,
     goals: JsonlWriter<BufWriter<File>>,
     auto_events: JsonlWriter<BufWriter<File>>,
+    gauges: Option<JsonlWriter<BufWriter<File>>>,
+    frames: JsonlWriter<BufWriter<File>>,
+    steps: u64,
+    video_frames: u64,
+    started: Instant,
+}
+
+impl OpenChunk {
+    fn open(
+        layout: SessionLayout,
+        video: &VideoParams,
+        flush_every_lines: u64,
+        flush_every: Duration,
+    ) -> io::Result<Self> {
+        fs::create_dir_all(&layout.data_dir)?;
+        let open = |path: &Path| -> io::Result<JsonlWriter<BufWriter<File>>> {
+            Ok(JsonlWriter::new(
+                BufWriter::new(File::create(path)?),
+                flush_every_lines,
+                flush_every,
+            ))
+        };
+        let actions = open(&layout.actions_path)?;
+        let compiled = open(&layout.compiled_path)?;
+        let thoughts = open(&layout.thoughts_path)?;
+        let goals = open(&layout.goals_path)?;
+        let auto_events = open(&layout.auto_events_path)?;
+        let frames = open(&layout.frames_path)?;
+        let mut videos = Vec::new();
+        for output in &video.outputs {
+            let output_layout = output.layout(&layout);
+            let sink = open_video_sink(
+                &output.options,
+                &output_layout,
+                &video.ffmpeg_path,
+                output.width,
+                output.height,
+                video.fps,
+            )?;
+            videos.push(ChunkVideo {
+                layout: output_layout,
+                queue: EncodeQueue::spawn(sink, output.options.queue)?,
+            });
+        }
+        Ok(Self {
+            layout,
+            videos,
+            actions,
+            compiled,
+            thoughts,
+            goals,
+            auto_events,
+            gauges: None,
+            frames,
+            steps: 0,
+            video_frames: 0,
+            started: Instant::now(),
+        })
+    }
+
+    // Drains the encoders and fsyncs every file of the chunk.
+    fn close(self) -> io::Result<QueueStats> {
+        let mut stats = QueueStats::default();
+        let mut first_err = None;
+        let mut layouts = Vec::new();
+        // Every encoder is finished and every file synced even when one
+        // fails, so a failed encoder never costs the JSONL data; the first
+        // error is returned at the end.
+        for video in self.videos {
+            match video.queue.finish() {
+                Ok(queue_stats) => stats.absorb(&queue_stats),
+                Err(err) => {
+                    first_err.get_or_insert(err);
+                }
+            }
+            layouts.push(video.layout);
+        }
+        for writer in [
+            self.actions,
+            self.compiled,
+            self.thoughts,
+            self.goals,
+            self.auto_events,
+            self.frames,
+        ]
+        .into_iter()
+        .chain(self.gauges)
+        {
+            if let Err(err) = sync_jsonl(writer) {
+                first_err.get_or_insert(err);
+            }
+        }
+        for layout in &layouts {
+            for path in [&layout.video_path, &layout.raw_video_path] {
+                if path.exists()
+                    && let Err(err) = File::open(path).and_then(|file| file.sync_all())
+                {
+                    first_err.get_or_insert(err);
+                }
+            }
+            if layout.images_dir.exists()
+                && let Err(err) = sync_dir(&layout.images_dir)
+            {
+                first_err.get_or_insert(err);
+            }
+        }
+        if let Err(err) = sync_dir(&self.layout.data_dir) {
+            first_err.get_or_insert(err);
+        }
+        match first_err {
+            Some(err) => Err(err),
+            None => Ok(stats),
+        }
+    }
+}
+
+pub struct SessionWriter {
+    session: SessionLayout,
+    chunk: OpenChunk,
+    video: VideoParams,
+    segment: SegmentOptions,
+    chunks: ChunkIndex,
+    closed_queue_stats: QueueStats,
+    flush_every_lines: u64,
+    flush_every: Duration,
 }
 
 impl SessionWriter {
+    #[allow(clippy::too_many_arguments)]
     pub fn create(
         dataset_root: &Path,
         session_name: &str,
         ffmpeg_path: &Path,
+        video: &VideoOutputOptions,
+        segment: &SegmentOptions,
         width: u32,
         height: u32,
         fps: u32,
         flush_every_lines: u64,
         flush_every: Duration,
     ) -> io::Result<Self> {
-        let layout = SessionLayout::new(dataset_root, session_name);
-        if layout.temp_dir.exists() || layout.root_dir.exists() {
+        if video.queue.capacity == 0 {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "encoder queue capacity must be at least 1",
+            ));
+        }
+        let outputs = video_outputs(video, width, height)?;
+        let mut session = SessionLayout::new(dataset_root, session_name).staging();
+        session.video_path = session
+            .video_path
+            .with_extension(video.encode.codec.container_extension());
+        if session.temp_dir.exists() || session.root_dir.exists() {
             return Err(io::Error::new(
                 io::ErrorKind::AlreadyExists,
                 "session directory already exists",
             ));
         }
-        fs::create_dir_all(&layout.root_dir)?;
+        fs::create_dir_all(&session.temp_dir)?;
 
-        let actions = JsonlWriter::new(
-            BufWriter::new(File::create(&layout.actions_path)?),
-            flush_every_lines,
-            flush_every,
-        );
-        let compiled = JsonlWriter::new(
-            BufWriter::new(File::create(&layout.compiled_path)?),
-            flush_every_lines,
-            flush_every,
-        );
-        let thoughts = JsonlWriter::new(
-            BufWriter::new(File::create(&layout.thoughts_path)?),
-            flush_every_lines,
-            flush_every,
-        );
-        let goals = JsonlWriter::new(
-            BufWriter::new(File::create(&layout.goals_path)?),
-            flush_every_lines,
-            flush_every,
-        );
-        let auto_events = JsonlWriter::new(
-            BufWriter::new(File::create(&layout.auto_events_path)?),
+        let layout = if segment.enabled() {
+            session.chunk(0)
+        } else {
+            session.clone()
+        };
+        let video = VideoParams {
+            outputs,
+            ffmpeg_path: ffmpeg_path.to_path_buf(),
+            fps,
+        };
+        let chunk = OpenChunk::open(layout, &video, flush_every_lines, flush_every)?;
+
+        let mut writer = Self {
+            session,
+            chunk,
+            video,
+            segment: *segment,
+            chunks: ChunkIndex::default(),
+            closed_queue_stats: QueueStats::default(),
             flush_every_lines,
             flush_every,
-        );
+        };
+        if segment.enabled() {
+            writer.push_chunk_entry(0, 0)?;
+        }
+        Ok(writer)
+    }
 
-        let ffmpeg_config = default_ffmpeg_config(ffmpeg_path, &layout.video_path, width, height, fps);
-        let ffmpeg = FfmpegWriter::spawn(&ffmpeg_config)?;
+    // The layout of the chunk currently being written.
+    pub fn layout(&self) -> &SessionLayout {
+        &self.chunk.layout
+    }
 
-        Ok(Self {
-            layout,
-            ffmpeg,
-            actions,
-            compiled,
-            thoughts,
-            goals,
-            auto_events,
+    pub fn outputs(&self) -> &[VideoOutput] {
+        &self.video.outputs
+    }
+
+    // The ffmpeg command line of the first output, as run for the first chunk.
+    pub fn encoder_command(&self) -> Option<Vec<String>> {
+        let layout = self.first_chunk_layout();
+        self.video.outputs.first().and_then(|output| {
+            output.encoder_command(&layout, &self.video.ffmpeg_path, self.video.fps)
         })
     }
 
-    pub fn layout(&self) -> &SessionLayout {
-        &self.layout
+    pub fn rendition_meta(&self) -> Vec<RenditionMeta> {
+        let layout = self.first_chunk_layout();
+        self.video
+            .outputs
+            .iter()
+            .filter_map(|output| {
+                Some(RenditionMeta {
+                    name: output.name.clone()?,
+                    width: output.width,
+                    height: output.height,
+                    video: output.video_name(&layout),
+                    encoder_command: output.encoder_command(
+                        &layout,
+                        &self.video.ffmpeg_path,
+                        self.video.fps,
+                    ),
+                })
+            })
+            .collect()
+    }
+
+    fn first_chunk_layout(&self) -> SessionLayout {
+        if self.segment.enabled() {
+            self.session.chunk(0)
+        } else {
+            self.session.clone()
+        }
+    }
+
+    pub fn encoder_progress(&self) -> Option<EncoderProgress> {
+        self.chunk
+            .videos
+            .first()
+            .and_then(|video| video.queue.progress())
+    }
+
+    pub fn queue_stats(&self) -> QueueStats {
+        let mut stats = self.closed_queue_stats;
+        for video in &self.chunk.videos {
+            stats.absorb(&video.queue.stats());
+        }
+        stats
+    }
+
+    // Video frames written to the current chunk so far, i.e. the position
+    // of the next frame in the chunk's video.
+    pub fn chunk_video_frames(&self) -> u64 {
+        self.chunk.video_frames
+    }
+
+    // Starts the next chunk when the current one has reached its step or
+    // time limit. Call before writing a frame and its action windows so
+    // every chunk stays aligned.
+    pub fn rotate_if_due(&mut self) -> io::Result<bool> {
+        if !self.segment.enabled() || self.chunk.video_frames == 0 {
+            return Ok(false);
+        }
+        let by_frames =
+            self.segment.every_frames > 0 && self.chunk.video_frames >= self.segment.every_frames;
+        let by_time = self.segment.every_minutes > 0
+            && self.chunk.started.elapsed()
+                >= Duration::from_secs(self.segment.every_minutes as u64 * 60);
+        if !by_frames && !by_time {
+            return Ok(false);
+        }
+        self.rotate()?;
+        Ok(true)
+    }
+
+    fn rotate(&mut self) -> io::Result<()> {
+        let index = self.chunks.chunks.len() as u32;
+        let next = OpenChunk::open(
+            self.session.chunk(index),
+            &self.video,
+            self.flush_every_lines,
+            self.flush_every,
+        )?;
+        let closed = std::mem::replace(&mut self.chunk, next);
+        let (steps, frames) = (closed.steps, closed.video_frames);
+        let stats = closed.close()?;
+        self.closed_queue_stats.absorb(&stats);
+        let next_step = match self.chunks.chunks.last_mut() {
+            Some(entry) => {
+                entry.steps = steps;
+                entry.frames = frames;
+                entry.complete = true;
+                entry.first_step + steps
+            }
+            None => 0,
+        };
+        self.push_chunk_entry(index, next_step)
+    }
+
+    fn push_chunk_entry(&mut self, index: u32, first_step: StepIndex) -> io::Result<()> {
+        self.chunks.chunks.push(ChunkEntry {
+            index,
+            dir: chunk_dir_name(index),
+            video: self
+                .video
+                .outputs
+                .first()
+                .map(|output| output.video_name(&self.session))
+                .unwrap_or_default(),
+            renditions: self
+                .video
+                .outputs
+                .iter()
+                .filter(|output| output.name.is_some())
+                .map(|output| output.video_name(&self.session))
+                .collect(),
+            first_step,
+            steps: 0,
+            frames: 0,
+            complete: false,
+        });
+        write_chunk_index(&self.session.chunk_index_path, &self.chunks)
     }
 
     pub fn write_window(&mut self, window: &AggregatedWindow) -> io::Result<()> {
-        self.actions.write_json(&window.snapshot)?;
-        self.compiled.write_line(&window.compiled_action)?;
+        self.chunk.actions.write_json(&window.snapshot)?;
+        self.chunk.compiled.write_line(&window.compiled_action)?;
+        if self.chunk.steps == 0
+            && let Some(entry) = self.chunks.chunks.last_mut()
+        {
+            entry.first_step = window.snapshot.step_index;
+        }
+        self.chunk.steps += 1;
         Ok(())
     }
 
     pub fn write_thought(&mut self, thought_line: &str) -> io::Result<()> {
-        self.thoughts.write_line(thought_line)
+        self.chunk.thoughts.write_line(thought_line)
     }
 
     pub fn write_goal(&mut self, goal_line: &str) -> io::Result<()> {
-        self.goals.write_line(goal_line)
+        self.chunk.goals.write_line(goal_line)
     }
 
     pub fn write_auto_event<T: Serialize>(&mut self, event: &T) -> io::Result<()> {
-        self.auto_events.write_json(event)
+        self.chunk.auto_events.write_json(event)
+    }
+
+    pub fn write_gauge_sample<T: Serialize>(&mut self, sample: &T) -> io::Result<()> {
+        if self.chunk.gauges.is_none() {
+            self.chunk.gauges = Some(JsonlWriter::new(
+                BufWriter::new(File::create(&self.chunk.layout.gauges_path)?),
+                self.flush_every_lines,
+                self.flush_every,
+            ));
+        }
+        match self.chunk.gauges.as_mut() {
+            Some(gauges) => gauges.write_json(sample),
+            None => Ok(()),
+        }
+    }
+
+    pub fn write_frame_timing(&mut self, timing: &FrameTiming) -> io::Result<()> {
+        self.chunk.frames.write_json(timing)
     }
 
     pub fn write_options<T: Serialize>(&self, options: &T) -> io::Result<()> {
-        write_json_file(&self.layout.options_path, options)
+        write_json_file(&self.session.options_path, options)
     }
 
     pub fn write_meta<T: Serialize>(&self, meta: &T) -> io::Result<()> {
-        write_json_file(&self.layout.meta_path, meta)
+        write_json_file(&self.session.meta_path, meta)
     }
 
-    pub fn write_frame(&mut self, frame: &[u8]) -> io::Result<()> {
-        self.ffmpeg.write_frame(frame)
+    // Queues the frame for the encoder thread; it is copied, so the caller
+    // can reuse its buffer. Only valid for sessions with a single output.
+    pub fn write_frame(&mut self, step_index: StepIndex, frame: &[u8]) -> io::Result<EnqueueReport> {
+        self.write_renditions(step_index, &[frame])
+    }
+
+    // Queues one frame per output, in `outputs()` order, for the frame of
+    // action step `step_index`. The report has the deepest queue, the summed
+    // stall time and evictions, and every frame evicted from any output.
+    pub fn write_renditions(
+        &mut self,
+        step_index: StepIndex,
+        frames: &[&[u8]],
+    ) -> io::Result<EnqueueReport> {
+        if frames.len() != self.chunk.videos.len() {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!(
+                    "expected {} rendition frames, got {}",
+                    self.chunk.videos.len(),
+                    frames.len()
+                ),
+            ));
+        }
+        let frame_ref = FrameRef {
+            step_index,
+            video_frame: self.chunk.video_frames,
+        };
+        let mut report = EnqueueReport::default();
+        for (video, frame) in self.chunk.videos.iter().zip(frames) {
+            let queued = video.queue.push(frame, frame_ref)?;
+            report.depth = report.depth.max(queued.depth);
+            report.stall_ms += queued.stall_ms;
+            report.evicted += queued.evicted;
+            report.evicted_frames.extend(queued.evicted_frames);
+        }
+        report.evicted_frames.sort_by_key(|evicted| evicted.video_frame);
+        report.evicted_frames.dedup();
+        self.chunk.video_frames += 1;
+        Ok(report)
     }
 
+    // Drains the encoder queue, flushes and fsyncs every file, then seals the
+    // staging directory. Returns the final layout of the last chunk.
     pub fn finalize(self) -> io::Result<SessionLayout> {
         let SessionWriter {
-            layout,
-            ffmpeg,
-            mut actions,
-            mut compiled,
-            mut thoughts,
-            mut goals,
-            mut auto_events,
+            session,
+            chunk,
+            mut chunks,
+            ..
         } = self;
 
-        actions.flush()?;
-        compiled.flush()?;
-        thoughts.flush()?;
-        goals.flush()?;
-        auto_events.flush()?;
-        ffmpeg.finish()?;
+        let (steps, frames) = (chunk.steps, chunk.video_frames);
+        let chunk_index = chunks.chunks.last().map(|entry| entry.index);
+        chunk.close()?;
+        if let Some(entry) = chunks.chunks.last_mut() {
+            entry.steps = steps;
+            entry.frames = frames;
+            entry.complete = true;
+            write_chunk_index(&session.chunk_index_path, &chunks)?;
+        }
+        for path in [&session.options_path, &session.meta_path] {
+            if path.exists() {
+                File::open(path)?.sync_all()?;
+            }
+        }
+        seal_session_dir(&session)?;
+        let mut sealed = SessionLayout::with_files_in(
+            session.root_dir.clone(),
+            session.temp_dir.clone(),
+            &session.root_dir,
+        );
+        if let Some(name) = session.video_path.file_name() {
+            sealed.video_path = sealed.root_dir.join(name);
+        }
+        Ok(match chunk_index {
+            Some(index) => sealed.chunk(index),
+            None => sealed,
+        })
+    }
+}
+
+// Writes the completion marker into the staging directory, fsyncs it and
+// renames it to the final session name. Callers must have synced the files.
+pub fn seal_session_dir(layout: &SessionLayout) -> io::Result<()> {
+    let mut files = Vec::new();
+    let mut dirs = vec![layout.temp_dir.clone()];
+    while let Some(dir) = dirs.pop() {
+        for entry in fs::read_dir(&dir)? {
+            let entry = entry?;
+            let file_type = entry.file_type()?;
+            if file_type.is_dir() {
+                dirs.push(entry.path());
+            } else if file_type.is_file() {
+                let path = entry.path();
+                let rel = path.strip_prefix(&layout.temp_dir).unwrap_or(&path);
+                files.push((
+                    rel.to_string_lossy().replace('\\', "/"),
+                    entry.metadata()?.len(),
+                ));
+            }
+        }
+    }
+    files.sort();
+    let marker = CompleteMarker { files };
+    write_json_file(&layout.temp_dir.join(COMPLETE_MARKER), &marker)?;
+    sync_dir(&layout.temp_dir)?;
+
+    fs::rename(&layout.temp_dir, &layout.root_dir)?;
+    if let Some(parent) = layout.root_dir.parent() {
+        sync_dir(parent)?;
+    }
+    Ok(())
+}
+
+#[derive(Serialize)]
+struct CompleteMarker {
+    files: Vec<(String, u64)>,
+}
+
+fn sync_jsonl(writer: JsonlWriter<BufWriter<File>>) -> io::Result<()> {
+    let file = writer.into_inner().into_inner().map_err(|err| err.into_error())?;
+    file.sync_all()
+}
 
-        Ok(layout)
+// Directory fsync makes the rename durable on Unix; Windows cannot open
+// directories as files, so it is skipped there.
+fn sync_dir(path: &Path) -> io::Result<()> {
+    #[cfg(unix)]
+    {
+        File::open(path)?.sync_all()
+    }
+    #[cfg(not(unix))]
+    {
+        let _ = path;
+        Ok(())
     }
 }
 
 fn write_json_file<T: Serialize>(path: &Path, value: &T) -> io::Result<()> {
     let file = File::create(path)?;
-    let writer = BufWriter::new(file);
-    serde_json::to_writer(writer, value)
-        .map_err(|err| io::Error::new(io::ErrorKind::Other, err))
+    let mut writer = BufWriter::new(file);
+    serde_json::to_writer(&mut writer, value)
+        .map_err(io::Error::other)?;
+    writer.flush()
+}
+
+pub fn read_frame_timings(path: &Path) -> io::Result<Vec<FrameTiming>> {
+    let reader = BufReader::new(File::open(path)?);
+    let mut timings = Vec::new();
+    for line in reader.lines() {
+        let line = line?;
+        if line.trim().is_empty() {
+            continue;
+        }
+        let timing = serde_json::from_str(&line)
+            .map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err))?;
+        timings.push(timing);
+    }
+    Ok(timings)
 }
 
 pub struct JsonlWriter<W: Write> {
@@ -295,7 +1065,7 @@ impl<W: Write> JsonlWriter<W> {
 
     pub fn write_json<T: Serialize>(&mut self, value: &T) -> io::Result<()> {
         serde_json::to_writer(&mut self.writer, value)
-            .map_err(|err| io::Error::new(io::ErrorKind::Other, err))?;
+            .map_err(io::Error::other)?;
         self.writer.write_all(b"\n")?;
         self.after_write()
     }
@@ -317,7 +1087,7 @@ impl<W: Write> JsonlWriter<W> {
 
     fn after_write(&mut self) -> io::Result<()> {
         self.line_count = self.line_count.saturating_add(1);
-        if self.line_count % self.flush_every_lines == 0
+        if self.line_count.is_multiple_of(self.flush_every_lines)
             || self.last_flush.elapsed() >= self.flush_every
         {
             self.flush()?;
@@ -357,7 +1127,7 @@ pub fn write_snapshot<W: Write>(
 mod tests {
     use super::*;
     use aggregator::{aggregate_window_with_compiled, AggregatorState, CursorProvider};
-    use collector_core::{InputEvent, InputEventKind};
+    use collector_core::{InputEvent, InputEventKind, Rendition};
 
     #[test]
     fn writes_action_and_compiled_lines() {
@@ -389,4 +1159,348 @@ mod tests {
             .unwrap()
             .contains("<|action_start|>"));
     }
+
+    #[test]
+    fn staging_layout_and_orphan_scan() {
+        let root = std::env::temp_dir().join(format!("writer_orphans_{}", std::process::id()));
+        let _ = fs::remove_dir_all(&root);
+        let layout = SessionLayout::new(&root, "run1");
+        let staging = layout.staging();
+        assert_eq!(layout.actions_path, layout.root_dir.join("actions.jsonl"));
+        assert_eq!(staging.actions_path, layout.temp_dir.join("actions.jsonl"));
+        assert!(layout.temp_dir.ends_with("run1.tmp"));
+
+        assert!(scan_orphaned_sessions(&root).unwrap().is_empty());
+        fs::create_dir_all(&layout.temp_dir).unwrap();
+        fs::create_dir_all(root.join("sessions").join("done")).unwrap();
+        assert_eq!(scan_orphaned_sessions(&root).unwrap(), vec![layout.temp_dir.clone()]);
+        fs::remove_dir_all(&root).unwrap();
+    }
+
+    #[test]
+    fn frame_timings_round_trip() {
+        use collector_core::{FramePacing, FrameRecord, FrameStatus};
+
+        let path = std::env::temp_dir().join(format!("writer_frames_{}.jsonl", std::process::id()));
+        let mut frame = FrameRecord {
+            step_index: 3,
+            qpc_ts: 900,
+            src_width: 1920,
+            src_height: 1080,
+            width: 1280,
+            height: 720,
+            data: Vec::new(),
+            pacing: FramePacing {
+                lateness_ms: 4.5,
+                skipped_source_frames: 7,
+                missed_slots: 0,
+                duplicated: false,
+            },
+            source: None,
+        };
+        let mut writer = JsonlWriter::new(
+            BufWriter::new(File::create(&path).unwrap()),
+            10,
+            Duration::from_secs(1),
+        );
+        writer.write_json(&FrameTiming::from_frame(&frame, 6, 3)).unwrap();
+        frame.step_index = 5;
+        frame.pacing.missed_slots = 1;
+        writer.write_json(&FrameTiming::from_frame(&frame, 10, 4)).unwrap();
+        writer.flush().unwrap();
+        drop(writer);
+
+        let timings = read_frame_timings(&path).unwrap();
+        assert_eq!(timings.len(), 2);
+        assert_eq!(timings[0].step_index, 6);
+        assert_eq!(timings[0].src_width, 1920);
+        assert_eq!(timings[0].skipped_source_frames, 7);
+        assert_eq!(timings[0].status, FrameStatus::Captured);
+        assert_eq!(timings[1].frame_index, 5);
+        assert_eq!(timings[1].video_frame, 4);
+        assert_eq!(timings[1].status, FrameStatus::Dropped);
+        fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn session_writer_finalizes_without_ffmpeg() {
+        let root = std::env::temp_dir().join(format!("writer_session_{}", std::process::id()));
+        let _ = fs::remove_dir_all(&root);
+        let video = VideoOutputOptions {
+            sink: VideoSinkKind::RawBgra,
+            ..VideoOutputOptions::default()
+        };
+        let mut writer = SessionWriter::create(
+            &root,
+            "raw",
+            Path::new("ffmpeg-not-needed"),
+            &video,
+            &SegmentOptions::default(),
+            2,
+            2,
+            5,
+            10,
+            Duration::from_secs(1),
+        )
+        .unwrap();
+        assert!(writer.layout().temp_dir.exists());
+        writer.write_frame(0, &[7u8; 16]).unwrap();
+        writer.write_frame(1, &[9u8; 16]).unwrap();
+        let layout = writer.finalize().unwrap();
+
+        assert!(!layout.temp_dir.exists());
+        assert!(layout.complete_marker_path().exists());
+        assert_eq!(fs::read(&layout.raw_video_path).unwrap().len(), 32);
+        fs::remove_dir_all(&root).unwrap();
+    }
+
+    #[test]
+    fn segmented_session_rotates_chunks() {
+        let root = std::env::temp_dir().join(format!("writer_chunks_{}", std::process::id()));
+        let _ = fs::remove_dir_all(&root);
+        let video = VideoOutputOptions {
+            sink: VideoSinkKind::RawBgra,
+            ..VideoOutputOptions::default()
+        };
+        let segment = SegmentOptions {
+            every_frames: 2,
+            every_minutes: 0,
+        };
+        let mut writer = SessionWriter::create(
+            &root,
+            "chunked",
+            Path::new("ffmpeg-not-needed"),
+            &video,
+            &segment,
+            2,
+            2,
+            5,
+            10,
+            Duration::from_secs(1),
+        )
+        .unwrap();
+        let mut rotations = 0;
+        for value in 0..5u8 {
+            if writer.rotate_if_due().unwrap() {
+                rotations += 1;
+            }
+            assert_eq!(writer.chunk_video_frames(), (value % 2) as u64);
+            writer.write_frame(value as StepIndex, &[value; 16]).unwrap();
+        }
+        assert_eq!(rotations, 2);
+        let layout = writer.finalize().unwrap();
+        assert_eq!(layout.data_dir, layout.root_dir.join("chunk_0002"));
+
+        let session = SessionLayout::new(&root, "chunked");
+        let index = read_chunk_index(&session.chunk_index_path).unwrap();
+        let frames: Vec<u64> = index.chunks.iter().map(|entry| entry.frames).collect();
+        assert_eq!(frames, vec![2, 2, 1]);
+        assert!(index.chunks.iter().all(|entry| entry.complete));
+        assert_eq!(index.chunks[1].dir, "chunk_0001");
+        assert_eq!(index.chunks[1].video, "video.bgra");
+        let chunks = session.chunk_layouts().unwrap();
+        assert_eq!(chunks.len(), 3);
+        assert_eq!(
+            fs::read(&chunks[1].raw_video_path).unwrap(),
+            [[2u8; 16], [3u8; 16]].concat()
+        );
+        assert!(chunks[2].actions_path.exists());
+        assert!(!session.actions_path.exists());
+        let marker = fs::read_to_string(session.complete_marker_path()).unwrap();
+        assert!(marker.contains("\"chunk_0002/video.bgra\",16"));
+        fs::remove_dir_all(&root).unwrap();
+    }
+
+    #[test]
+    fn renditions_write_one_video_each() {
+        let rendition = |name: &str, width, height| Rendition {
+            name: name.to_string(),
+            resolution: [width, height],
+            encode: None,
+        };
+        let mut video = VideoOutputOptions {
+            sink: VideoSinkKind::RawBgra,
+            ..VideoOutputOptions::default()
+        };
+        video.renditions = vec![rendition("a", 2, 2), rendition("a", 4, 2)];
+        assert!(video_outputs(&video, 4, 2).is_err());
+        video.renditions = vec![rendition("a/b", 2, 2)];
+        assert!(video_outputs(&video, 4, 2).is_err());
+        video.renditions = vec![rendition("a", 0, 2)];
+        assert!(video_outputs(&video, 4, 2).is_err());
+
+        let root = std::env::temp_dir().join(format!("writer_renditions_{}", std::process::id()));
+        let _ = fs::remove_dir_all(&root);
+        video.renditions = vec![rendition("a", 2, 2), rendition("b", 4, 2)];
+        let mut writer = SessionWriter::create(
+            &root,
+            "renditions",
+            Path::new("ffmpeg-not-needed"),
+            &video,
+            &SegmentOptions::default(),
+            4,
+            2,
+            5,
+            10,
+            Duration::from_secs(1),
+        )
+        .unwrap();
+        assert!(writer.write_renditions(0, &[&[0u8; 16]]).is_err());
+        for value in 0..3u8 {
+            writer
+                .write_renditions(value as StepIndex, &[&[value; 16], &[value; 32]])
+                .unwrap();
+        }
+        let meta = writer.rendition_meta();
+        assert_eq!(meta.len(), 2);
+        assert_eq!((meta[1].width, meta[1].height), (4, 2));
+        assert_eq!(meta[1].video, "video_b.bgra");
+        let layout = writer.finalize().unwrap();
+        assert_eq!(fs::read(layout.root_dir.join("video_a.bgra")).unwrap().len(), 48);
+        assert_eq!(fs::read(layout.root_dir.join("video_b.bgra")).unwrap().len(), 96);
+        assert!(!layout.raw_video_path.exists());
+        fs::remove_dir_all(&root).unwrap();
+    }
+
+    #[test]
+    fn ffmpeg_args_follow_encode_options() {
+        let mut config =
+            default_ffmpeg_config(Path::new("ffmpeg"), Path::new("out.mp4"), 1280, 720, 5);
+        let args = ffmpeg_args(&config).join(" ");
+        assert!(args.contains("-c:v libx264 -crf 16 -g 10 -pix_fmt yuv420p -movflags"));
+
+        config.encode = EncodeOptions {
+            codec: VideoCodec::X265,
+            preset: Some("slow".to_string()),
+            crf: None,
+            bitrate_kbps: Some(4000),
+            gop: 30,
+            pixel_format: collector_core::PixelFormat::Yuv444p,
+            tune: Some("grain".to_string()),
+        };
+        let args = ffmpeg_args(&config).join(" ");
+        assert!(args.contains(
+            "-c:v libx265 -preset slow -tune grain -b:v 4000k -g 30 -pix_fmt yuv444p"
+        ));
+
+        config.encode = EncodeOptions {
+            codec: VideoCodec::Ffv1,
+            crf: None,
+            ..EncodeOptions::default()
+        };
+        config.encode.validate().unwrap();
+        let args = ffmpeg_args(&config).join(" ");
+        assert!(args.ends_with("-c:v ffv1 -g 10 -pix_fmt yuv420p"));
+        let line = ffmpeg_command_line(&config);
+        assert_eq!(line.first().map(String::as_str), Some("ffmpeg"));
+        assert_eq!(line.last().map(String::as_str), Some("out.mp4"));
+    }
+
+    #[test]
+    fn rejects_invalid_encode_combinations() {
+        let invalid = [
+            EncodeOptions {
+                codec: VideoCodec::Ffv1,
+                ..EncodeOptions::default()
+            },
+            EncodeOptions {
+                crf: Some(60),
+                ..EncodeOptions::default()
+            },
+            EncodeOptions {
+                bitrate_kbps: Some(2000),
+                ..EncodeOptions::default()
+            },
+            EncodeOptions {
+                codec: VideoCodec::Av1,
+                tune: Some("film".to_string()),
+                ..EncodeOptions::default()
+            },
+            EncodeOptions {
+                codec: VideoCodec::Mjpeg,
+                crf: Some(3),
+                ..EncodeOptions::default()
+            },
+            EncodeOptions {
+                preset: Some("warp".to_string()),
+                ..EncodeOptions::default()
+            },
+            EncodeOptions {
+                gop: 0,
+                ..EncodeOptions::default()
+            },
+        ];
+        for encode in invalid {
+            assert!(encode.validate().is_err(), "{:?}", encode);
+        }
+        let av1 = EncodeOptions {
+            codec: VideoCodec::Av1,
+            preset: Some("8".to_string()),
+            crf: Some(35),
+            ..EncodeOptions::default()
+        };
+        av1.validate().unwrap();
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn ffmpeg_failure_reports_stderr_tail() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let dir = std::env::temp_dir().join(format!("writer_ffmpeg_fail_{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        let script = dir.join("ffmpeg");
+        fs::write(
+            &script,
+            "#!/bin/sh\ncat > /dev/null\necho 'frame=    2 fps=0.0 time=00:00:00.40 speed=1x' >&2\necho 'Unknown encoder libfoo' >&2\nexit 1\n",
+        )
+        .unwrap();
+        fs::set_permissions(&script, fs::Permissions::from_mode(0o755)).unwrap();
+
+        let log_path = dir.join("encoder.log");
+        let mut config = default_ffmpeg_config(&script, &dir.join("video.mp4"), 2, 2, 5);
+        config.log_path = Some(log_path.clone());
+        let mut writer = FfmpegWriter::spawn(&config).unwrap();
+        let _ = writer.write_frame(&[0u8; 16]);
+        let err = writer.finish().unwrap_err().to_string();
+        assert!(err.starts_with("ffmpeg exited with"));
+        assert!(err.contains("Unknown encoder libfoo"));
+        let log = fs::read_to_string(&log_path).unwrap();
+        assert!(log.contains("frame=    2"));
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn failed_encoder_still_syncs_jsonl_files() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let root = std::env::temp_dir().join(format!("writer_close_fail_{}", std::process::id()));
+        let _ = fs::remove_dir_all(&root);
+        fs::create_dir_all(&root).unwrap();
+        let script = root.join("ffmpeg");
+        fs::write(&script, "#!/bin/sh\ncat > /dev/null\nexit 1\n").unwrap();
+        fs::set_permissions(&script, fs::Permissions::from_mode(0o755)).unwrap();
+
+        let mut writer = SessionWriter::create(
+            &root,
+            "failing",
+            &script,
+            &VideoOutputOptions::default(),
+            &SegmentOptions::default(),
+            2,
+            2,
+            5,
+            10,
+            Duration::from_secs(60),
+        )
+        .unwrap();
+        let thoughts_path = writer.layout().thoughts_path.clone();
+        writer.write_thought("kept").unwrap();
+        let _ = writer.write_frame(0, &[0u8; 16]);
+        assert!(writer.finalize().is_err());
+        assert_eq!(fs::read_to_string(&thoughts_path).unwrap(), "kept\n");
+        fs::remove_dir_all(&root).unwrap();
+    }
 }
@@ -0,0 +1,376 @@
+use std::collections::VecDeque;
+use std::io;
+use std::sync::{Arc, Condvar, Mutex, MutexGuard};
+use std::thread::{self, JoinHandle};
+use std::time::Instant;
+
+use collector_core::{EncodeQueueOptions, FrameRef, QueueFullPolicy};
+
+use crate::{EncoderProgress, VideoSink};
+
+// What happened to one frame on its way into the queue.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct EnqueueReport {
+    pub depth: u32,
+    pub stall_ms: f32,
+    pub evicted: u32,
+    // The frames evicted to make room, oldest first.
+    pub evicted_frames: Vec<FrameRef>,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct QueueStats {
+    pub frames_queued: u64,
+    pub frames_encoded: u64,
+    pub frames_evicted: u64,
+    pub max_depth: u32,
+    pub stall_ms_total: f64,
+}
+
+impl QueueStats {
+    // Adds the totals of another queue, e.g. one per chunk.
+    pub fn absorb(&mut self, other: &QueueStats) {
+        self.frames_queued += other.frames_queued;
+        self.frames_encoded += other.frames_encoded;
+        self.frames_evicted += other.frames_evicted;
+        self.max_depth = self.max_depth.max(other.max_depth);
+        self.stall_ms_total += other.stall_ms_total;
+    }
+}
+
+#[derive(Default)]
+struct QueueState {
+    frames: VecDeque<(FrameRef, Vec<u8>)>,
+    closed: bool,
+    failed: Option<(io::ErrorKind, String)>,
+    progress: Option<EncoderProgress>,
+    stats: QueueStats,
+}
+
+struct Shared {
+    state: Mutex<QueueState>,
+    // Signalled when a frame is queued or the queue is closed.
+    not_empty: Condvar,
+    // Signalled when the worker takes a frame or stops.
+    not_full: Condvar,
+}
+
+impl Shared {
+    fn lock(&self) -> MutexGuard<'_, QueueState> {
+        self.state.lock().unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+}
+
+// Feeds a `VideoSink` from a dedicated thread so a slow encoder never blocks
+// capture. Frames are encoded in order; `finish` encodes everything still
+// queued before finishing the sink.
+pub struct EncodeQueue {
+    shared: Arc<Shared>,
+    options: EncodeQueueOptions,
+    handle: Option<JoinHandle<io::Result<()>>>,
+}
+
+impl EncodeQueue {
+    pub fn spawn(sink: Box<dyn VideoSink>, options: EncodeQueueOptions) -> io::Result<Self> {
+        if options.capacity == 0 {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "encoder queue capacity must be at least 1",
+            ));
+        }
+        let shared = Arc::new(Shared {
+            state: Mutex::new(QueueState::default()),
+            not_empty: Condvar::new(),
+            not_full: Condvar::new(),
+        });
+        let worker = Arc::clone(&shared);
+        let handle = thread::Builder::new()
+            .name("encoder".to_string())
+            .spawn(move || run_worker(sink, &worker))?;
+        Ok(Self {
+            shared,
+            options,
+            handle: Some(handle),
+        })
+    }
+
+    pub fn push(&self, frame: &[u8], frame_ref: FrameRef) -> io::Result<EnqueueReport> {
+        let capacity = self.options.capacity as usize;
+        let frame = frame.to_vec();
+        let started = Instant::now();
+        let mut evicted_frames = Vec::new();
+        let mut state = self.shared.lock();
+        loop {
+            if let Some((kind, message)) = &state.failed {
+                return Err(io::Error::new(*kind, message.clone()));
+            }
+            if state.frames.len() < capacity {
+                break;
+            }
+            match self.options.full_policy {
+                QueueFullPolicy::Block => {
+                    state = self
+                        .shared
+                        .not_full
+                        .wait(state)
+                        .unwrap_or_else(|poisoned| poisoned.into_inner());
+                }
+                QueueFullPolicy::DropOldest => {
+                    if let Some((evicted, _)) = state.frames.pop_front() {
+                        evicted_frames.push(evicted);
+                    }
+                }
+                QueueFullPolicy::Error => {
+                    return Err(io::Error::other(format!(
+                        "encoder queue full ({} frames)",
+                        capacity
+                    )));
+                }
+            }
+        }
+        state.frames.push_back((frame_ref, frame));
+        let depth = state.frames.len() as u32;
+        let stall_ms = started.elapsed().as_secs_f64() * 1000.0;
+        state.stats.frames_queued += 1;
+        state.stats.frames_evicted += evicted_frames.len() as u64;
+        state.stats.max_depth = state.stats.max_depth.max(depth);
+        state.stats.stall_ms_total += stall_ms;
+        drop(state);
+        self.shared.not_empty.notify_one();
+        Ok(EnqueueReport {
+            depth,
+            stall_ms: stall_ms as f32,
+            evicted: evicted_frames.len() as u32,
+            evicted_frames,
+        })
+    }
+
+    pub fn depth(&self) -> u32 {
+        self.shared.lock().frames.len() as u32
+    }
+
+    pub fn stats(&self) -> QueueStats {
+        self.shared.lock().stats
+    }
+
+    pub fn progress(&self) -> Option<EncoderProgress> {
+        self.shared.lock().progress.clone()
+    }
+
+    // Encodes every queued frame, then finishes the sink.
+    pub fn finish(mut self) -> io::Result<QueueStats> {
+        self.close();
+        let result = match self.handle.take() {
+            Some(handle) => handle
+                .join()
+                .unwrap_or_else(|_| Err(io::Error::other("encoder thread panicked"))),
+            None => Ok(()),
+        };
+        result.map(|()| self.stats())
+    }
+
+    fn close(&self) {
+        self.shared.lock().closed = true;
+        self.shared.not_empty.notify_all();
+    }
+}
+
+impl Drop for EncodeQueue {
+    // Lets the worker drain and exit when a session is abandoned.
+    fn drop(&mut self) {
+        self.close();
+    }
+}
+
+// Wakes every waiting `push` when the worker stops. A sink that panics
+// leaves the queue failed, so a push blocked on a full queue returns an
+// error instead of waiting forever.
+struct WorkerExit<'a>(&'a Shared);
+
+impl Drop for WorkerExit<'_> {
+    fn drop(&mut self) {
+        if thread::panicking() {
+            let mut state = self.0.lock();
+            state
+                .failed
+                .get_or_insert((io::ErrorKind::Other, "encoder thread panicked".to_string()));
+            state.frames.clear();
+        }
+        self.0.not_full.notify_all();
+    }
+}
+
+fn run_worker(mut sink: Box<dyn VideoSink>, shared: &Shared) -> io::Result<()> {
+    let _exit = WorkerExit(shared);
+    loop {
+        let mut state = shared.lock();
+        while state.frames.is_empty() && !state.closed {
+            state = shared
+                .not_empty
+                .wait(state)
+                .unwrap_or_else(|poisoned| poisoned.into_inner());
+        }
+        let Some((_, frame)) = state.frames.pop_front() else {
+            break;
+        };
+        drop(state);
+        shared.not_full.notify_one();
+
+        let written = sink.write_frame(&frame);
+        let mut state = shared.lock();
+        state.progress = sink.progress();
+        if let Err(err) = written {
+            // Later pushes fail with the same error instead of piling up.
+            state.failed = Some((err.kind(), err.to_string()));
+            state.frames.clear();
+            drop(state);
+            shared.not_full.notify_all();
+            let _ = sink.finish();
+            return Err(err);
+        }
+        state.stats.frames_encoded += 1;
+    }
+    let finished = sink.finish();
+    if let Err(err) = &finished {
+        shared.lock().failed = Some((err.kind(), err.to_string()));
+    }
+    finished
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::mpsc::{self, Receiver, Sender};
+
+    // Records frames, holding each write until the test releases it.
+    struct GatedSink {
+        written: Arc<Mutex<Vec<u8>>>,
+        gate: Receiver<()>,
+    }
+
+    impl VideoSink for GatedSink {
+        fn write_frame(&mut self, frame: &[u8]) -> io::Result<()> {
+            self.gate
+                .recv()
+                .map_err(|_| io::Error::other("gate closed"))?;
+            self.written.lock().unwrap().push(frame[0]);
+            Ok(())
+        }
+
+        fn finish(self: Box<Self>) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    fn gated_queue(
+        capacity: u32,
+        full_policy: QueueFullPolicy,
+    ) -> (EncodeQueue, Sender<()>, Arc<Mutex<Vec<u8>>>) {
+        let written = Arc::new(Mutex::new(Vec::new()));
+        let (release, gate) = mpsc::channel();
+        let sink = GatedSink {
+            written: Arc::clone(&written),
+            gate,
+        };
+        let queue = EncodeQueue::spawn(
+            Box::new(sink),
+            EncodeQueueOptions {
+                capacity,
+                full_policy,
+            },
+        )
+        .unwrap();
+        (queue, release, written)
+    }
+
+    fn frame_ref(video_frame: u64) -> FrameRef {
+        FrameRef {
+            step_index: video_frame * 2,
+            video_frame,
+        }
+    }
+
+    // Waits until the worker has taken the frame it is blocked on.
+    fn wait_for_depth(queue: &EncodeQueue, depth: u32) {
+        while queue.depth() != depth {
+            thread::yield_now();
+        }
+    }
+
+    #[test]
+    fn drop_oldest_evicts_and_finish_drains_in_order() {
+        let (queue, release, written) = gated_queue(2, QueueFullPolicy::DropOldest);
+        queue.push(&[0], frame_ref(0)).unwrap();
+        wait_for_depth(&queue, 0);
+        assert_eq!(queue.push(&[1], frame_ref(1)).unwrap().depth, 1);
+        assert_eq!(queue.push(&[2], frame_ref(2)).unwrap().depth, 2);
+        let report = queue.push(&[3], frame_ref(3)).unwrap();
+        assert_eq!((report.depth, report.evicted), (2, 1));
+        assert_eq!(report.evicted_frames, vec![frame_ref(1)]);
+        for _ in 0..3 {
+            release.send(()).unwrap();
+        }
+        let stats = queue.finish().unwrap();
+        assert_eq!(*written.lock().unwrap(), vec![0, 2, 3]);
+        assert_eq!(stats.frames_queued, 4);
+        assert_eq!(stats.frames_encoded, 3);
+        assert_eq!(stats.frames_evicted, 1);
+        assert_eq!(stats.max_depth, 2);
+    }
+
+    struct PanickingSink;
+
+    impl VideoSink for PanickingSink {
+        fn write_frame(&mut self, _frame: &[u8]) -> io::Result<()> {
+            panic!("sink failed");
+        }
+
+        fn finish(self: Box<Self>) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn panicking_sink_fails_blocked_pushes() {
+        let queue = EncodeQueue::spawn(
+            Box::new(PanickingSink),
+            EncodeQueueOptions {
+                capacity: 1,
+                full_policy: QueueFullPolicy::Block,
+            },
+        )
+        .unwrap();
+        // The queue holds one frame, so one of these pushes blocks until the
+        // worker is gone.
+        let failed = (0..3).any(|value| queue.push(&[value], frame_ref(value as u64)).is_err());
+        assert!(failed);
+        assert!(queue.finish().is_err());
+    }
+
+    #[test]
+    fn error_policy_fails_when_full_and_block_waits() {
+        let (queue, release, _) = gated_queue(1, QueueFullPolicy::Error);
+        queue.push(&[0], frame_ref(0)).unwrap();
+        wait_for_depth(&queue, 0);
+        queue.push(&[1], frame_ref(1)).unwrap();
+        assert!(queue.push(&[2], frame_ref(2)).is_err());
+        release.send(()).unwrap();
+        release.send(()).unwrap();
+        queue.finish().unwrap();
+
+        let (queue, release, written) = gated_queue(1, QueueFullPolicy::Block);
+        queue.push(&[0], frame_ref(0)).unwrap();
+        wait_for_depth(&queue, 0);
+        queue.push(&[1], frame_ref(1)).unwrap();
+        let releaser = thread::spawn(move || {
+            for _ in 0..3 {
+                release.send(()).unwrap();
+            }
+        });
+        let report = queue.push(&[2], frame_ref(2)).unwrap();
+        assert_eq!(report.evicted, 0);
+        releaser.join().unwrap();
+        queue.finish().unwrap();
+        assert_eq!(*written.lock().unwrap(), vec![0, 1, 2]);
+    }
+}
@@ -0,0 +1,223 @@
+use std::fs::{self, File};
+use std::io::{self, BufWriter, Write};
+use std::path::{Path, PathBuf};
+
+use collector_core::ImageFormat;
+
+use crate::EncoderProgress;
+
+pub trait VideoSink: Send {
+    fn write_frame(&mut self, frame: &[u8]) -> io::Result<()>;
+    fn finish(self: Box<Self>) -> io::Result<()>;
+
+    fn progress(&self) -> Option<EncoderProgress> {
+        None
+    }
+}
+
+fn check_frame_size(frame: &[u8], frame_bytes: usize) -> io::Result<()> {
+    if frame.len() != frame_bytes {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            "frame buffer size does not match expected BGRA size",
+        ));
+    }
+    Ok(())
+}
+
+// One image file per frame, named by video frame index.
+pub struct ImageSequenceSink {
+    dir: PathBuf,
+    format: ImageFormat,
+    quality: u8,
+    width: u32,
+    height: u32,
+    frame_bytes: usize,
+    next_index: u64,
+}
+
+impl ImageSequenceSink {
+    pub fn create(
+        dir: &Path,
+        format: ImageFormat,
+        quality: u8,
+        width: u32,
+        height: u32,
+    ) -> io::Result<Self> {
+        fs::create_dir_all(dir)?;
+        Ok(Self {
+            dir: dir.to_path_buf(),
+            format,
+            quality,
+            width,
+            height,
+            frame_bytes: (width as usize)
+                .saturating_mul(height as usize)
+                .saturating_mul(4),
+            next_index: 0,
+        })
+    }
+
+    pub fn frame_path(&self, index: u64) -> PathBuf {
+        self.dir
+            .join(format!("{:06}.{}", index, self.format.extension()))
+    }
+}
+
+impl VideoSink for ImageSequenceSink {
+    fn write_frame(&mut self, frame: &[u8]) -> io::Result<()> {
+        check_frame_size(frame, self.frame_bytes)?;
+        let mut out = BufWriter::new(File::create(self.frame_path(self.next_index))?);
+        match self.format {
+            ImageFormat::Png => encode_png(&mut out, frame, self.width, self.height)?,
+            ImageFormat::Jpeg => {
+                encode_jpeg(&mut out, frame, self.width, self.height, self.quality)?
+            }
+            ImageFormat::Webp => encode_webp(&mut out, frame, self.width, self.height)?,
+        }
+        out.flush()?;
+        self.next_index += 1;
+        Ok(())
+    }
+
+    fn finish(self: Box<Self>) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+fn bgra_to_rgb(bgra: &[u8]) -> Vec<u8> {
+    bgra.chunks_exact(4)
+        .flat_map(|pixel| [pixel[2], pixel[1], pixel[0]])
+        .collect()
+}
+
+fn encode_png<W: Write>(out: &mut W, bgra: &[u8], width: u32, height: u32) -> io::Result<()> {
+    let mut encoder = png::Encoder::new(out, width, height);
+    encoder.set_color(png::ColorType::Rgb);
+    encoder.set_depth(png::BitDepth::Eight);
+    encoder.set_compression(png::Compression::Fast);
+    let mut writer = encoder.write_header().map_err(io::Error::other)?;
+    writer.write_image_data(&bgra_to_rgb(bgra)).map_err(io::Error::other)?;
+    writer.finish().map_err(io::Error::other)
+}
+
+// Baseline JPEG at `quality` (1-100); alpha is dropped.
+fn encode_jpeg<W: Write>(
+    out: &mut W,
+    bgra: &[u8],
+    width: u32,
+    height: u32,
+    quality: u8,
+) -> io::Result<()> {
+    let (Ok(width), Ok(height)) = (u16::try_from(width), u16::try_from(height)) else {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            "JPEG frames are limited to 65535x65535",
+        ));
+    };
+    jpeg_encoder::Encoder::new(out, quality.clamp(1, 100))
+        .encode(bgra, width, height, jpeg_encoder::ColorType::Bgra)
+        .map_err(io::Error::other)
+}
+
+// Lossless WebP; there is no quality setting.
+fn encode_webp<W: Write>(out: &mut W, bgra: &[u8], width: u32, height: u32) -> io::Result<()> {
+    image_webp::WebPEncoder::new(out)
+        .encode(&bgra_to_rgb(bgra), width, height, image_webp::ColorType::Rgb8)
+        .map_err(io::Error::other)
+}
+
+// Frames appended back to back as raw BGRA; dimensions come from options.json.
+pub struct RawBgraSink {
+    file: BufWriter<File>,
+    frame_bytes: usize,
+}
+
+impl RawBgraSink {
+    pub fn create(path: &Path, width: u32, height: u32) -> io::Result<Self> {
+        Ok(Self {
+            file: BufWriter::new(File::create(path)?),
+            frame_bytes: (width as usize)
+                .saturating_mul(height as usize)
+                .saturating_mul(4),
+        })
+    }
+}
+
+impl VideoSink for RawBgraSink {
+    fn write_frame(&mut self, frame: &[u8]) -> io::Result<()> {
+        check_frame_size(frame, self.frame_bytes)?;
+        self.file.write_all(frame)
+    }
+
+    fn finish(self: Box<Self>) -> io::Result<()> {
+        let file = self.file.into_inner().map_err(|err| err.into_error())?;
+        file.sync_all()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn image_sequence_writes_numbered_files() {
+        let dir = std::env::temp_dir().join(format!("writer_images_{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        let frame = vec![40u8; 4 * 4 * 4];
+        for format in [ImageFormat::Png, ImageFormat::Jpeg, ImageFormat::Webp] {
+            let mut sink: Box<dyn VideoSink> =
+                Box::new(ImageSequenceSink::create(&dir, format, 80, 4, 4).unwrap());
+            sink.write_frame(&frame).unwrap();
+            sink.write_frame(&frame).unwrap();
+            assert!(sink.write_frame(&frame[4..]).is_err());
+            sink.finish().unwrap();
+            for index in 0..2 {
+                let path = dir.join(format!("{:06}.{}", index, format.extension()));
+                assert!(fs::metadata(&path).unwrap().len() > 0);
+            }
+        }
+        let png = fs::read(dir.join("000000.png")).unwrap();
+        assert_eq!(&png[1..4], b"PNG");
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    // A gradient with distinct channels, so swapped or shifted channels show.
+    fn gradient(width: u32, height: u32) -> Vec<u8> {
+        let mut bgra = Vec::new();
+        for y in 0..height {
+            for x in 0..width {
+                bgra.extend_from_slice(&[(x * 8) as u8, (y * 8) as u8, (255 - x * 4) as u8, 255]);
+            }
+        }
+        bgra
+    }
+
+    #[test]
+    fn jpeg_round_trips_within_tolerance() {
+        let bgra = gradient(24, 16);
+        let mut out = Vec::new();
+        encode_jpeg(&mut out, &bgra, 24, 16, 95).unwrap();
+        let mut decoder = jpeg_decoder::Decoder::new(out.as_slice());
+        let rgb = decoder.decode().unwrap();
+        let info = decoder.info().unwrap();
+        assert_eq!((info.width, info.height), (24, 16));
+        for (decoded, expected) in rgb.iter().zip(bgra_to_rgb(&bgra)) {
+            assert!(decoded.abs_diff(expected) <= 12, "{} vs {}", decoded, expected);
+        }
+        assert!(encode_jpeg(&mut Vec::new(), &bgra, 70000, 1, 90).is_err());
+    }
+
+    #[test]
+    fn webp_round_trips_exactly() {
+        let bgra = gradient(24, 16);
+        let mut out = Vec::new();
+        encode_webp(&mut out, &bgra, 24, 16).unwrap();
+        let mut decoder = image_webp::WebPDecoder::new(std::io::Cursor::new(out)).unwrap();
+        assert_eq!(decoder.dimensions(), (24, 16));
+        assert!(!decoder.has_alpha());
+        let mut rgb = vec![0; decoder.output_buffer_size().unwrap()];
+        decoder.read_image(&mut rgb).unwrap();
+        assert_eq!(rgb, bgra_to_rgb(&bgra));
+    }
+}